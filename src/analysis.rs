@@ -0,0 +1,383 @@
+use crate::{ Point };
+
+use std::collections::{ BTreeMap };
+
+const NOTE_NAMES: [&str; 12] = ["C", "C#", "D", "D#", "E", "F", "F#", "G", "G#", "A", "A#", "B"];
+
+// (suffix, intervals above the root)
+const CHORDS: [(&str, &[u8]); 13] = [
+    ("", &[0, 4, 7]),
+    ("m", &[0, 3, 7]),
+    ("dim", &[0, 3, 6]),
+    ("aug", &[0, 4, 8]),
+    ("sus2", &[0, 2, 7]),
+    ("sus4", &[0, 5, 7]),
+    ("7", &[0, 4, 7, 10]),
+    ("maj7", &[0, 4, 7, 11]),
+    ("m7", &[0, 3, 7, 10]),
+    ("mmaj7", &[0, 3, 7, 11]),
+    ("m7b5", &[0, 3, 6, 10]),
+    ("dim7", &[0, 3, 6, 9]),
+    ("5", &[0, 7]),
+];
+
+fn pitch_class(note: f32) -> usize{
+    (note.round() as i64).rem_euclid(12) as usize
+}
+
+// onsets within window of the first onset of a group form a chord,
+// returns (onset, sorted distinct notes) for every group of two or more notes
+pub fn detect_chords(floww: &[Point], window: f32) -> Vec<(f32, Vec<f32>)>{
+    let mut onsets = floww.iter().filter(|p| p.3 > 0.0).map(|p| (p.1, p.2)).collect::<Vec<_>>();
+    onsets.sort_by(|a, b| a.0.total_cmp(&b.0));
+    let mut res = Vec::new();
+    let mut i = 0;
+    while i < onsets.len(){
+        let start = onsets[i].0;
+        let mut notes = Vec::new();
+        while i < onsets.len() && onsets[i].0 - start <= window{
+            notes.push(onsets[i].1);
+            i += 1;
+        }
+        notes.sort_by(|a, b| a.total_cmp(b));
+        notes.dedup();
+        if notes.len() > 1 {
+            res.push((start, notes));
+        }
+    }
+    res
+}
+
+// the bass note is tried as root first, so inversions are named after their bass when ambiguous
+pub fn chord_name(notes: &[f32]) -> Option<String>{
+    let mut pcs = notes.iter().map(|n| pitch_class(*n)).collect::<Vec<_>>();
+    let bass = *notes.iter().min_by(|a, b| a.total_cmp(b))?;
+    pcs.sort_unstable();
+    pcs.dedup();
+    let bass = pitch_class(bass);
+    let roots = std::iter::once(bass).chain(pcs.iter().copied().filter(|pc| *pc != bass));
+    for root in roots{
+        let mut rel = pcs.iter().map(|pc| ((pc + 12 - root) % 12) as u8).collect::<Vec<_>>();
+        rel.sort_unstable();
+        for (suffix, intervals) in CHORDS.iter(){
+            if rel == *intervals {
+                let mut name = format!("{}{}", NOTE_NAMES[root], suffix);
+                if root != bass {
+                    name.push('/');
+                    name.push_str(NOTE_NAMES[bass]);
+                }
+                return Some(name);
+            }
+        }
+    }
+    None
+}
+
+pub fn detect_named_chords(floww: &[Point], window: f32) -> Vec<(f32, Vec<f32>, Option<String>)>{
+    detect_chords(floww, window).into_iter().map(|(t, notes)| {
+        let name = chord_name(&notes);
+        (t, notes, name)
+    }).collect()
+}
+
+// velocity and pitch figures only count note ons (vel > 0), duration spans all points
+#[derive(Clone,Copy,PartialEq,Debug,Default)]
+pub struct FlowwStats{
+    pub note_count: usize,
+    pub duration: f32,
+    pub notes_per_second: f32,
+    pub vel_min: f32,
+    pub vel_mean: f32,
+    pub vel_max: f32,
+    pub pitch_min: f32,
+    pub pitch_max: f32,
+}
+
+pub fn stats(floww: &[Point]) -> FlowwStats{
+    let mut res = FlowwStats::default();
+    if floww.is_empty() { return res; }
+    let begin = floww.iter().fold(f32::MAX, |m, p| m.min(p.1));
+    let end = floww.iter().fold(f32::MIN, |m, p| m.max(p.1));
+    res.duration = end - begin;
+    let mut vel_sum = 0.0;
+    res.vel_min = f32::MAX;
+    res.vel_max = f32::MIN;
+    res.pitch_min = f32::MAX;
+    res.pitch_max = f32::MIN;
+    for p in floww.iter().filter(|p| p.3 > 0.0){
+        res.note_count += 1;
+        vel_sum += p.3;
+        res.vel_min = res.vel_min.min(p.3);
+        res.vel_max = res.vel_max.max(p.3);
+        res.pitch_min = res.pitch_min.min(p.2);
+        res.pitch_max = res.pitch_max.max(p.2);
+    }
+    if res.note_count == 0 {
+        return FlowwStats{ duration: res.duration, ..Default::default() };
+    }
+    res.vel_mean = vel_sum / res.note_count as f32;
+    if res.duration > 0.0 {
+        res.notes_per_second = res.note_count as f32 / res.duration;
+    }
+    res
+}
+
+// note on counts per rounded pitch
+pub fn pitch_histogram(floww: &[Point]) -> BTreeMap<i32, usize>{
+    let mut hist = BTreeMap::new();
+    for p in floww.iter().filter(|p| p.3 > 0.0){
+        *hist.entry(p.2.round() as i32).or_insert(0) += 1;
+    }
+    hist
+}
+
+// note on counts per pitch class (0 = C), normalized to sum to one
+pub fn pitch_class_profile(floww: &[Point]) -> [f32; 12]{
+    let mut profile = [0.0; 12];
+    let mut total = 0.0;
+    for p in floww.iter().filter(|p| p.3 > 0.0){
+        profile[pitch_class(p.2)] += 1.0;
+        total += 1.0;
+    }
+    if total > 0.0 {
+        profile.iter_mut().for_each(|x| *x /= total);
+    }
+    profile
+}
+
+// indices and times of the note ons, times relative to the first one
+fn onsets(floww: &[Point]) -> Vec<(usize, f32, f32)>{
+    let mut res = floww.iter().enumerate().filter(|(_, p)| p.3 > 0.0)
+        .map(|(i, p)| (i, p.1, p.2)).collect::<Vec<_>>();
+    res.sort_by(|a, b| a.1.total_cmp(&b.1));
+    let start = res.first().map(|o| o.1).unwrap_or(0.0);
+    res.iter_mut().for_each(|o| o.1 -= start);
+    res
+}
+
+// dynamic time warping over the note ons, a semitone costs as much as 1/12 of a time unit,
+// returns matched (index into a, index into b) pairs in order
+pub fn align(a: &[Point], b: &[Point]) -> Vec<(usize, usize)>{
+    let (xs, ys) = (onsets(a), onsets(b));
+    let (n, m) = (xs.len(), ys.len());
+    if n == 0 || m == 0 { return Vec::new(); }
+    let cost = |i: usize, j: usize| (xs[i].1 - ys[j].1).abs() + (xs[i].2 - ys[j].2).abs() / 12.0;
+    let mut acc = vec![vec![f32::INFINITY; m]; n];
+    for i in 0..n{
+        for j in 0..m{
+            let prev = if i == 0 && j == 0 {
+                0.0
+            } else {
+                let mut best = f32::INFINITY;
+                if i > 0 { best = best.min(acc[i - 1][j]); }
+                if j > 0 { best = best.min(acc[i][j - 1]); }
+                if i > 0 && j > 0 { best = best.min(acc[i - 1][j - 1]); }
+                best
+            };
+            acc[i][j] = prev + cost(i, j);
+        }
+    }
+    let (mut i, mut j) = (n - 1, m - 1);
+    let mut path = vec![(xs[i].0, ys[j].0)];
+    while i > 0 || j > 0 {
+        if i == 0 {
+            j -= 1;
+        } else if j == 0 {
+            i -= 1;
+        } else {
+            let diag = acc[i - 1][j - 1];
+            if diag <= acc[i - 1][j] && diag <= acc[i][j - 1] {
+                i -= 1;
+                j -= 1;
+            } else if acc[i - 1][j] < acc[i][j - 1] {
+                i -= 1;
+            } else {
+                j -= 1;
+            }
+        }
+        path.push((xs[i].0, ys[j].0));
+    }
+    path.reverse();
+    path
+}
+
+#[derive(Clone,Copy,PartialEq,Debug)]
+pub struct SimilarityWeights{
+    pub rhythm: f32,
+    pub pitch: f32,
+    pub velocity: f32,
+}
+
+impl Default for SimilarityWeights{
+    fn default() -> Self{
+        Self{ rhythm: 1.0, pitch: 1.0, velocity: 1.0 }
+    }
+}
+
+// onsets closer than this count as the same hit
+const RHYTHM_TOLERANCE: f32 = 0.05;
+
+// fraction of onsets (relative to the first one) that have a partner in the other floww
+fn rhythm_similarity(a: &[(usize, f32, f32)], b: &[(usize, f32, f32)]) -> f32{
+    let matched = |xs: &[(usize, f32, f32)], ys: &[(usize, f32, f32)]| xs.iter()
+        .filter(|x| ys.iter().any(|y| (x.1 - y.1).abs() <= RHYTHM_TOLERANCE)).count();
+    (matched(a, b) + matched(b, a)) as f32 / (a.len() + b.len()) as f32
+}
+
+// cosine similarity of the pitch-class profiles
+fn pitch_similarity(a: &[Point], b: &[Point]) -> f32{
+    let (pa, pb) = (pitch_class_profile(a), pitch_class_profile(b));
+    let dot = pa.iter().zip(pb.iter()).map(|(x, y)| x * y).sum::<f32>();
+    let norm = |p: &[f32; 12]| p.iter().map(|x| x * x).sum::<f32>().sqrt();
+    let n = norm(&pa) * norm(&pb);
+    if n == 0.0 { 0.0 } else { dot / n }
+}
+
+// in [0, 1], two empty flowws are identical
+pub fn similarity(a: &[Point], b: &[Point]) -> f32{
+    similarity_weighted(a, b, &SimilarityWeights::default())
+}
+
+pub fn similarity_weighted(a: &[Point], b: &[Point], weights: &SimilarityWeights) -> f32{
+    let (xs, ys) = (onsets(a), onsets(b));
+    if xs.is_empty() && ys.is_empty() { return 1.0; }
+    if xs.is_empty() || ys.is_empty() { return 0.0; }
+    let total = weights.rhythm + weights.pitch + weights.velocity;
+    if total <= 0.0 { return 0.0; }
+    let rhythm = rhythm_similarity(&xs, &ys);
+    let pitch = pitch_similarity(a, b);
+    let velocity = 1.0 - (stats(a).vel_mean - stats(b).vel_mean).abs().min(1.0);
+    (weights.rhythm * rhythm + weights.pitch * pitch + weights.velocity * velocity) / total
+}
+
+#[derive(Clone,PartialEq,Debug,Default)]
+pub struct FlowwDiff{
+    pub added: Vec<Point>,
+    pub removed: Vec<Point>,
+    // (old, new)
+    pub moved: Vec<(Point, Point)>,
+}
+
+impl FlowwDiff{
+    pub fn is_empty(&self) -> bool{
+        self.added.is_empty() && self.removed.is_empty() && self.moved.is_empty()
+    }
+}
+
+// points are the same note when id, note and on/off kind agree, identical points match first,
+// then the closest same note within tolerance counts as moved (time or velocity changed)
+pub fn diff_points(old: &[Point], new: &[Point], tolerance: f32) -> FlowwDiff{
+    let same_note = |a: &Point, b: &Point| a.0 == b.0 && a.2 == b.2 && (a.3 > 0.0) == (b.3 > 0.0);
+    let mut old_used = vec![false; old.len()];
+    let mut new_used = vec![false; new.len()];
+    for (i, a) in old.iter().enumerate(){
+        if let Some(j) = (0..new.len()).find(|j| !new_used[*j] && new[*j] == *a){
+            old_used[i] = true;
+            new_used[j] = true;
+        }
+    }
+    let mut diff = FlowwDiff::default();
+    for (i, a) in old.iter().enumerate(){
+        if old_used[i] { continue; }
+        let closest = (0..new.len())
+            .filter(|j| !new_used[*j] && same_note(a, &new[*j]) && (a.1 - new[*j].1).abs() <= tolerance)
+            .min_by(|x, y| (a.1 - new[*x].1).abs().total_cmp(&(a.1 - new[*y].1).abs()));
+        if let Some(j) = closest {
+            new_used[j] = true;
+            diff.moved.push((*a, new[j]));
+        } else {
+            diff.removed.push(*a);
+        }
+    }
+    diff.added = new.iter().zip(new_used).filter(|(_, used)| !used).map(|(p, _)| *p).collect();
+    diff
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+    #[test]
+    fn chords(){
+        let f = vec![
+            (0, 0.0, 60.0, 1.0), (0, 0.01, 64.0, 1.0), (0, 0.02, 67.0, 1.0),
+            (0, 0.5, 60.0, 0.0), (0, 0.5, 64.0, 0.0), (0, 0.5, 67.0, 0.0),
+            (0, 1.0, 72.0, 1.0),
+            (0, 2.0, 64.0, 1.0), (0, 2.0, 69.0, 1.0), (0, 2.0, 72.0, 1.0),
+        ];
+        let chords = detect_chords(&f, 0.05);
+        assert_eq!(chords, vec![(0.0, vec![60.0, 64.0, 67.0]), (2.0, vec![64.0, 69.0, 72.0])]);
+        let named = detect_named_chords(&f, 0.05);
+        assert_eq!(named[0].2, Some("C".to_string()));
+        assert_eq!(named[1].2, Some("Am/E".to_string()));
+        assert_eq!(chord_name(&[62.0, 65.0, 69.0, 72.0]), Some("Dm7".to_string()));
+        assert_eq!(chord_name(&[60.0, 61.0]), None);
+    }
+
+    #[test]
+    fn floww_stats(){
+        let f = vec![(0, 0.0, 60.0, 0.5), (0, 1.0, 67.0, 1.0), (0, 1.5, 67.0, 0.0), (0, 2.0, 55.0, 0.75)];
+        assert_eq!(stats(&f), FlowwStats{
+            note_count: 3,
+            duration: 2.0,
+            notes_per_second: 1.5,
+            vel_min: 0.5,
+            vel_mean: 0.75,
+            vel_max: 1.0,
+            pitch_min: 55.0,
+            pitch_max: 67.0,
+        });
+        assert_eq!(stats(&[]), FlowwStats::default());
+    }
+
+    #[test]
+    fn pitch_summaries(){
+        let f = vec![(0, 0.0, 60.0, 1.0), (0, 0.5, 60.0, 0.0), (0, 1.0, 72.0, 1.0), (0, 2.0, 67.0, 1.0), (0, 3.0, 60.0, 1.0)];
+        let h = pitch_histogram(&f);
+        assert_eq!(h.into_iter().collect::<Vec<_>>(), vec![(60, 2), (67, 1), (72, 1)]);
+        let pcp = pitch_class_profile(&f);
+        assert_eq!(pcp[0], 0.75);
+        assert_eq!(pcp[7], 0.25);
+        assert_eq!(pcp.iter().sum::<f32>(), 1.0);
+        assert_eq!(pitch_class_profile(&[]), [0.0; 12]);
+    }
+
+    #[test]
+    fn dtw_align(){
+        let score = vec![(0, 0.0, 60.0, 1.0), (0, 1.0, 62.0, 1.0), (0, 2.0, 64.0, 1.0)];
+        let take = vec![
+            (0, 10.05, 60.0, 1.0), (0, 10.5, 60.0, 0.0), (0, 11.1, 62.0, 1.0),
+            (0, 11.15, 62.0, 1.0), (0, 11.9, 64.0, 1.0),
+        ];
+        assert_eq!(align(&score, &take), vec![(0, 0), (1, 2), (1, 3), (2, 4)]);
+        assert_eq!(align(&score, &[]), vec![]);
+    }
+
+    #[test]
+    fn similarity_metric(){
+        let a = vec![(0, 0.0, 60.0, 1.0), (0, 1.0, 64.0, 1.0), (0, 2.0, 67.0, 1.0)];
+        assert!((similarity(&a, &a) - 1.0).abs() < 1e-6);
+        assert!((similarity(&a, &a.clone().time_shifted(4.0)) - 1.0).abs() < 1e-6);
+        let b = vec![(0, 0.0, 61.0, 0.5), (0, 1.5, 66.0, 0.5), (0, 2.0, 68.0, 0.5)];
+        let only_rhythm = SimilarityWeights{ rhythm: 1.0, pitch: 0.0, velocity: 0.0 };
+        assert!((similarity_weighted(&a, &b, &only_rhythm) - 2.0 / 3.0).abs() < 1e-6);
+        let only_pitch = SimilarityWeights{ rhythm: 0.0, pitch: 1.0, velocity: 0.0 };
+        assert_eq!(similarity_weighted(&a, &b, &only_pitch), 0.0);
+        assert!(similarity(&a, &b) < similarity(&a, &a));
+        assert_eq!(similarity(&[], &[]), 1.0);
+        assert_eq!(similarity(&a, &[]), 0.0);
+    }
+
+    #[test]
+    fn diff(){
+        let old = vec![(0, 0.0, 60.0, 1.0), (0, 1.0, 62.0, 1.0), (0, 2.0, 64.0, 1.0)];
+        let new = vec![(0, 0.0, 60.0, 1.0), (0, 1.1, 62.0, 1.0), (0, 3.0, 65.0, 1.0)];
+        let d = diff_points(&old, &new, 0.25);
+        assert_eq!(d, FlowwDiff{
+            added: vec![(0, 3.0, 65.0, 1.0)],
+            removed: vec![(0, 2.0, 64.0, 1.0)],
+            moved: vec![((0, 1.0, 62.0, 1.0), (0, 1.1, 62.0, 1.0))],
+        });
+        assert!(diff_points(&old, &old, 0.0).is_empty());
+        assert_eq!(diff_points(&old, &new, 0.0).moved, vec![]);
+    }
+}
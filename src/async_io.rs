@@ -0,0 +1,154 @@
+use crate::{ FlowwPacket, DEFAULT_BATCH_SIZE };
+use crate::net::{ invalid_data, MAX_FRAME_LEN };
+
+use bytes::{ Bytes, BytesMut };
+use futures_core::{ Stream, ready };
+use futures_sink::Sink;
+use tokio::io::{ AsyncRead, AsyncWrite };
+use tokio_util::codec::{ FramedRead, FramedWrite, LengthDelimitedCodec };
+
+use std::collections::VecDeque;
+use std::io;
+use std::pin::Pin;
+use std::task::{ Context, Poll };
+
+// same frames as write_frame and read_frame, so async and blocking peers can talk
+fn codec() -> LengthDelimitedCodec{
+    LengthDelimitedCodec::builder()
+        .length_field_type::<u32>()
+        .little_endian()
+        .max_frame_length(MAX_FRAME_LEN)
+        .new_codec()
+}
+
+// yields the packets of every received batch one by one
+pub struct FlowwStream<R>{
+    inner: FramedRead<R, LengthDelimitedCodec>,
+    pending: VecDeque<FlowwPacket>,
+}
+
+impl<R: AsyncRead> FlowwStream<R>{
+    pub fn new(reader: R) -> Self{
+        Self{ inner: FramedRead::new(reader, codec()), pending: VecDeque::new() }
+    }
+
+    pub fn into_inner(self) -> R{
+        self.inner.into_inner()
+    }
+}
+
+impl<R: AsyncRead + Unpin> Stream for FlowwStream<R>{
+    type Item = io::Result<FlowwPacket>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>>{
+        let this = self.get_mut();
+        loop{
+            if let Some(packet) = this.pending.pop_front(){
+                return Poll::Ready(Some(Ok(packet)));
+            }
+            let frame: BytesMut = match ready!(Pin::new(&mut this.inner).poll_next(cx)){
+                Some(Ok(frame)) => frame,
+                Some(Err(e)) => return Poll::Ready(Some(Err(e))),
+                None => return Poll::Ready(None),
+            };
+            match bincode::deserialize::<Vec<FlowwPacket>>(&frame){
+                Ok(batch) => this.pending.extend(batch),
+                Err(e) => return Poll::Ready(Some(Err(invalid_data(e)))),
+            }
+        }
+    }
+}
+
+// packets are batched into one frame until the batch is full or the sink is flushed
+pub struct FlowwSink<W>{
+    inner: FramedWrite<W, LengthDelimitedCodec>,
+    batch: Vec<FlowwPacket>,
+    batch_size: usize,
+}
+
+impl<W: AsyncWrite> FlowwSink<W>{
+    pub fn new(writer: W) -> Self{
+        Self{ inner: FramedWrite::new(writer, codec()), batch: Vec::new(), batch_size: DEFAULT_BATCH_SIZE }
+    }
+
+    pub fn with_batch_size(mut self, batch_size: usize) -> Self{
+        self.batch_size = batch_size.max(1);
+        self
+    }
+
+    pub fn into_inner(self) -> W{
+        self.inner.into_inner()
+    }
+}
+
+impl<W: AsyncWrite + Unpin> FlowwSink<W>{
+    // hands the batch to the framed writer, doesn't flush it
+    fn poll_send_batch(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>>{
+        if self.batch.is_empty() { return Poll::Ready(Ok(())); }
+        ready!(Sink::<Bytes>::poll_ready(Pin::new(&mut self.inner), cx))?;
+        let bytes = bincode::serialize(&self.batch).map_err(invalid_data)?;
+        Sink::<Bytes>::start_send(Pin::new(&mut self.inner), Bytes::from(bytes))?;
+        self.batch.clear();
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<W: AsyncWrite + Unpin> Sink<FlowwPacket> for FlowwSink<W>{
+    type Error = io::Error;
+
+    fn poll_ready(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>>{
+        let this = self.get_mut();
+        if this.batch.len() >= this.batch_size {
+            ready!(this.poll_send_batch(cx))?;
+        }
+        Poll::Ready(Ok(()))
+    }
+
+    fn start_send(self: Pin<&mut Self>, packet: FlowwPacket) -> io::Result<()>{
+        self.get_mut().batch.push(packet);
+        Ok(())
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>>{
+        let this = self.get_mut();
+        ready!(this.poll_send_batch(cx))?;
+        Sink::<Bytes>::poll_flush(Pin::new(&mut this.inner), cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>>{
+        let this = self.get_mut();
+        ready!(this.poll_send_batch(cx))?;
+        Sink::<Bytes>::poll_close(Pin::new(&mut this.inner), cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+    use futures::{ SinkExt, StreamExt, executor::block_on };
+    #[test]
+    fn async_stream(){
+        let packets = vec![
+            FlowwPacket::Track("kick".to_string()), FlowwPacket::Point((0, 0.0, 36.0, 1.0)),
+            FlowwPacket::Msg("hi".to_string()),
+        ];
+        let mut sink = FlowwSink::new(Vec::new()).with_batch_size(2);
+        block_on(async {
+            for p in packets.clone(){
+                sink.feed(p).await.unwrap();
+            }
+            sink.close().await.unwrap();
+        });
+        let bytes = sink.into_inner();
+        let mut r = &bytes[..];
+        assert_eq!(read_frame(&mut r).unwrap().unwrap().len(), 2);
+        let stream = FlowwStream::new(&bytes[..]);
+        let got = block_on(stream.map(|p| p.unwrap()).collect::<Vec<_>>());
+        assert_eq!(got, packets);
+        let mut bad = Vec::new();
+        bad.extend(3u32.to_le_bytes());
+        bad.extend([9, 9, 9]);
+        let mut stream = FlowwStream::new(&bad[..]);
+        assert!(block_on(stream.next()).unwrap().is_err());
+    }
+}
@@ -0,0 +1,145 @@
+use crate::{ FlowwPacket, DEFAULT_BATCH_SIZE };
+
+use std::io;
+use std::sync::mpsc::{ self, Sender, SyncSender, Receiver, TryRecvError };
+
+fn disconnected() -> io::Error{
+    io::ErrorKind::NotConnected.into()
+}
+
+// the sending half of any channel that carries batches, implement it for crossbeam or other
+// channels to use them with ChannelSender
+pub trait BatchSend{
+    fn send_batch(&self, batch: Vec<FlowwPacket>) -> io::Result<()>;
+}
+
+pub trait BatchRecv{
+    // blocks until a batch arrives
+    fn recv_batch(&self) -> io::Result<Vec<FlowwPacket>>;
+    // None when nothing is waiting
+    fn try_recv_batch(&self) -> io::Result<Option<Vec<FlowwPacket>>>;
+}
+
+impl BatchSend for Sender<Vec<FlowwPacket>>{
+    fn send_batch(&self, batch: Vec<FlowwPacket>) -> io::Result<()>{
+        self.send(batch).map_err(|_| disconnected())
+    }
+}
+
+// blocks while the channel is full
+impl BatchSend for SyncSender<Vec<FlowwPacket>>{
+    fn send_batch(&self, batch: Vec<FlowwPacket>) -> io::Result<()>{
+        self.send(batch).map_err(|_| disconnected())
+    }
+}
+
+impl BatchRecv for Receiver<Vec<FlowwPacket>>{
+    fn recv_batch(&self) -> io::Result<Vec<FlowwPacket>>{
+        self.recv().map_err(|_| disconnected())
+    }
+
+    fn try_recv_batch(&self) -> io::Result<Option<Vec<FlowwPacket>>>{
+        match self.try_recv(){
+            Ok(batch) => Ok(Some(batch)),
+            Err(TryRecvError::Empty) => Ok(None),
+            Err(TryRecvError::Disconnected) => Err(disconnected()),
+        }
+    }
+}
+
+// batches like FlowwSender but hands the batches to a channel in the same process
+pub struct ChannelSender<S>{
+    tx: S,
+    batch: Vec<FlowwPacket>,
+    batch_size: usize,
+}
+
+impl<S: BatchSend> ChannelSender<S>{
+    pub fn with_batch_size(mut self, batch_size: usize) -> Self{
+        self.batch_size = batch_size.max(1);
+        self
+    }
+
+    pub fn into_inner(self) -> S{
+        self.tx
+    }
+
+    pub fn push(&mut self, packet: FlowwPacket) -> io::Result<()>{
+        self.batch.push(packet);
+        if self.batch.len() >= self.batch_size {
+            self.flush()?;
+        }
+        Ok(())
+    }
+
+    pub fn send(&mut self, packets: Vec<FlowwPacket>) -> io::Result<()>{
+        self.batch.extend(packets);
+        self.flush()
+    }
+
+    // NotConnected once the receiving end is gone, the batch is lost then
+    pub fn flush(&mut self) -> io::Result<()>{
+        if self.batch.is_empty() { return Ok(()); }
+        self.tx.send_batch(std::mem::take(&mut self.batch))
+    }
+}
+
+pub struct ChannelReceiver<R>{
+    rx: R,
+}
+
+impl<R: BatchRecv> ChannelReceiver<R>{
+    pub fn into_inner(self) -> R{
+        self.rx
+    }
+
+    // blocks until a batch arrives, NotConnected once every sender is gone
+    pub fn recv(&mut self) -> io::Result<Vec<FlowwPacket>>{
+        self.rx.recv_batch()
+    }
+
+    pub fn try_recv(&mut self) -> io::Result<Option<Vec<FlowwPacket>>>{
+        self.rx.try_recv_batch()
+    }
+}
+
+pub fn channel_sender<S: BatchSend>(tx: S) -> ChannelSender<S>{
+    ChannelSender{ tx, batch: Vec::new(), batch_size: DEFAULT_BATCH_SIZE }
+}
+
+pub fn channel_receiver<R: BatchRecv>(rx: R) -> ChannelReceiver<R>{
+    ChannelReceiver{ rx }
+}
+
+pub type StdChannelSender = ChannelSender<Sender<Vec<FlowwPacket>>>;
+pub type StdChannelReceiver = ChannelReceiver<Receiver<Vec<FlowwPacket>>>;
+
+// both ends over a std channel
+pub fn floww_channel() -> (StdChannelSender, StdChannelReceiver){
+    let (tx, rx) = mpsc::channel();
+    (channel_sender(tx), channel_receiver(rx))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+    #[test]
+    fn channels(){
+        let (tx, mut rx) = floww_channel();
+        let mut tx = tx.with_batch_size(2);
+        tx.push(FlowwPacket::Track("kick".to_string())).unwrap();
+        assert_eq!(rx.try_recv().unwrap(), None);
+        tx.push(FlowwPacket::Point((0, 0.0, 36.0, 1.0))).unwrap();
+        assert_eq!(rx.recv().unwrap().len(), 2);
+        let handle = std::thread::spawn(move || {
+            tx.send(vec![FlowwPacket::Msg("from a thread".to_string())]).unwrap();
+        });
+        assert_eq!(rx.recv().unwrap(), vec![FlowwPacket::Msg("from a thread".to_string())]);
+        handle.join().unwrap();
+        assert_eq!(rx.recv().unwrap_err().kind(), std::io::ErrorKind::NotConnected);
+        let (stx, srx) = std::sync::mpsc::sync_channel(1);
+        let mut tx = channel_sender(stx);
+        drop(srx);
+        assert!(tx.send(vec![FlowwPacket::Start]).is_err());
+    }
+}
@@ -0,0 +1,120 @@
+use crate::{ Timed };
+
+// yields the points in [k * interval, (k + 1) * interval) for k = 0, 1, .. until no points are left,
+// empty windows give empty slices so chunk k always belongs to window k
+pub struct IntervalChunks<'a, T>{
+    slice: &'a [T],
+    interval: f32,
+    origin: f32,
+    window: usize,
+    pos: usize,
+}
+
+impl<'a, T: Timed> IntervalChunks<'a, T>{
+    // assumes sorted, negative times move the first window back
+    pub(crate) fn new(slice: &'a [T], interval: f32) -> Self{
+        let first = slice.first().map(|p| p.time()).unwrap_or(0.0);
+        let origin = if interval > 0.0 && first < 0.0 {
+            (first / interval).floor() * interval
+        } else {
+            0.0
+        };
+        Self{ slice, interval, origin, window: 0, pos: 0 }
+    }
+}
+
+impl<'a, T: Timed> Iterator for IntervalChunks<'a, T>{
+    type Item = &'a [T];
+
+    fn next(&mut self) -> Option<Self::Item>{
+        if self.interval.is_nan() || self.interval <= 0.0 { return None; }
+        let rest = &self.slice[self.pos..];
+        if !rest.first()?.time().is_finite() { return None; }
+        self.window += 1;
+        let end = self.origin + self.window as f32 * self.interval;
+        let n = rest.partition_point(|p| p.time() < end);
+        self.pos += n;
+        Some(&rest[..n])
+    }
+}
+
+// playhead over a sorted floww, advance returns the points in [last, now) in O(k),
+// the cursor doesn't borrow the floww so points can be appended between calls
+#[derive(Clone,Copy,PartialEq,Debug,Default)]
+pub struct FlowwCursor{
+    pos: usize,
+    time: f32,
+}
+
+impl FlowwCursor{
+    pub fn new() -> Self{
+        Self::default()
+    }
+
+    pub fn at(time: f32) -> Self{
+        Self{ pos: 0, time }
+    }
+
+    pub fn time(&self) -> f32{
+        self.time
+    }
+
+    pub fn seek<T: Timed>(&mut self, floww: &[T], time: f32){
+        self.pos = floww.partition_point(|p| p.time() < time);
+        self.time = time;
+    }
+
+    // moving back in time acts like a seek and returns nothing
+    pub fn advance<'a, T: Timed>(&mut self, floww: &'a [T], now: f32) -> &'a [T]{
+        if now < self.time {
+            self.seek(floww, now);
+            return &[];
+        }
+        let l = floww.len();
+        let mut start = self.pos.min(l);
+        while start > 0 && floww[start - 1].time() >= self.time{
+            start -= 1;
+        }
+        while start < l && floww[start].time() < self.time{
+            start += 1;
+        }
+        let mut end = start;
+        while end < l && floww[end].time() < now{
+            end += 1;
+        }
+        self.pos = end;
+        self.time = now;
+        &floww[start..end]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+    #[test]
+    fn chunks(){
+        let a = vec![(0, 0.0, 0.0, 1.0), (1, 0.5, 0.0, 1.0), (2, 2.25, 0.0, 1.0), (3, 3.0, 0.0, 1.0)];
+        let chunks = a.chunks_by_interval(1.0).collect::<Vec<_>>();
+        assert_eq!(chunks, vec![
+            &a[0..2], &a[2..2], &a[2..3], &a[3..4],
+        ]);
+        assert_eq!(a.chunks_by_interval(0.0).count(), 0);
+        assert_eq!(Floww::new().chunks_by_interval(1.0).count(), 0);
+        let b = vec![(0, -0.5, 0.0, 1.0), (1, 0.5, 0.0, 1.0)];
+        assert_eq!(b.chunks_by_interval(1.0).collect::<Vec<_>>(), vec![&b[0..1], &b[1..2]]);
+    }
+
+    #[test]
+    fn cursor(){
+        let mut a = vec![(0, -1.0, 0.0, 1.0), (1, 0.0, 0.0, 1.0), (2, 0.5, 0.0, 1.0), (3, 1.0, 0.0, 1.0)];
+        let mut c = FlowwCursor::new();
+        assert_eq!(c.advance(&a, 0.5), &[(1, 0.0, 0.0, 1.0)]);
+        assert_eq!(c.advance(&a, 0.5), &[]);
+        assert_eq!(c.advance(&a, 2.0), &[(2, 0.5, 0.0, 1.0), (3, 1.0, 0.0, 1.0)]);
+        a.push((4, 2.5, 0.0, 1.0));
+        assert_eq!(c.advance(&a, 3.0), &[(4, 2.5, 0.0, 1.0)]);
+        assert_eq!(c.advance(&a, 0.0), &[]);
+        assert_eq!(c.time(), 0.0);
+        assert_eq!(c.advance(&a, 0.75), &[(1, 0.0, 0.0, 1.0), (2, 0.5, 0.0, 1.0)]);
+    }
+}
@@ -0,0 +1,135 @@
+use crate::{ Floww };
+
+// maps velocities in [0, 1] onto [0, 1]
+#[derive(Clone,PartialEq,Debug)]
+pub enum VelocityCurve{
+    // v^gamma: below 1 compresses towards loud, above 1 towards soft
+    Gamma(f32),
+    // logistic s-curve around 0.5 with the given steepness, expands the dynamic range
+    Sigmoid(f32),
+    // piecewise linear (in, out) breakpoints, flat outside the first and last one
+    Breakpoints(Vec<(f32, f32)>),
+}
+
+impl VelocityCurve{
+    pub fn apply(&self, v: f32) -> f32{
+        let v = v.clamp(0.0, 1.0);
+        let res = match self{
+            Self::Gamma(g) => v.powf(*g),
+            Self::Sigmoid(k) => {
+                if *k == 0.0 { return v; }
+                let s = |x: f32| 1.0 / (1.0 + (-k * (x - 0.5)).exp());
+                (s(v) - s(0.0)) / (s(1.0) - s(0.0))
+            },
+            Self::Breakpoints(points) => {
+                let mut points = points.clone();
+                points.sort_by(|a, b| a.0.total_cmp(&b.0));
+                interpolate_breakpoints(&points, v)
+            },
+        };
+        res.clamp(0.0, 1.0)
+    }
+}
+
+// assumes breakpoints sorted on x
+fn interpolate_breakpoints(points: &[(f32, f32)], x: f32) -> f32{
+    let l = points.len();
+    if l == 0 { return x; }
+    if x <= points[0].0 { return points[0].1; }
+    if x >= points[l - 1].0 { return points[l - 1].1; }
+    let i = points.partition_point(|p| p.0 <= x);
+    let (x0, y0) = points[i - 1];
+    let (x1, y1) = points[i];
+    if x1 == x0 { return y1; }
+    y0 + (y1 - y0) * (x - x0) / (x1 - x0)
+}
+
+#[derive(Clone,Copy,PartialEq,Eq,Debug)]
+pub enum Interpolation{
+    Step,
+    Linear,
+    // catmull-rom through the neighbouring points
+    Cubic,
+}
+
+// flowws used as automation lanes: the note field is the value,
+// outside the points the curve holds the first or last value
+pub trait ControlCurve{
+    fn sample_at(&self, t: f32, interpolation: Interpolation) -> f32;
+    fn resample(&self, rate: f32, interpolation: Interpolation) -> Floww;
+}
+
+impl ControlCurve for Floww{
+    // assumes sorted
+    fn sample_at(&self, t: f32, interpolation: Interpolation) -> f32{
+        let l = self.len();
+        if l == 0 { return 0.0; }
+        if t <= self[0].1 { return self[0].2; }
+        if t >= self[l - 1].1 { return self[l - 1].2; }
+        let i = self.partition_point(|p| p.1 <= t);
+        let (a, b) = (self[i - 1], self[i]);
+        let dt = b.1 - a.1;
+        if dt <= 0.0 { return b.2; }
+        let u = (t - a.1) / dt;
+        match interpolation{
+            Interpolation::Step => a.2,
+            Interpolation::Linear => a.2 + (b.2 - a.2) * u,
+            Interpolation::Cubic => {
+                let p0 = if i >= 2 { self[i - 2].2 } else { a.2 };
+                let p3 = if i + 1 < l { self[i + 1].2 } else { b.2 };
+                let (p1, p2) = (a.2, b.2);
+                0.5 * (2.0 * p1 + (p2 - p0) * u
+                    + (2.0 * p0 - 5.0 * p1 + 4.0 * p2 - p3) * u * u
+                    + (3.0 * p1 - p0 - 3.0 * p2 + p3) * u * u * u)
+            },
+        }
+    }
+
+    // rate points per time unit from the first to the last point,
+    // the new points take the id and velocity of the first one
+    fn resample(&self, rate: f32, interpolation: Interpolation) -> Floww{
+        let l = self.len();
+        if l == 0 || rate.is_nan() || rate <= 0.0 { return Vec::new(); }
+        let (id, start, vel) = (self[0].0, self[0].1, self[0].3);
+        let n = ((self[l - 1].1 - start) * rate).floor() as usize;
+        (0..=n).map(|i| {
+            let t = start + i as f32 / rate;
+            (id, t, self.sample_at(t, interpolation), vel)
+        }).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+    #[test]
+    fn velocity_curves(){
+        assert_eq!(VelocityCurve::Gamma(2.0).apply(0.5), 0.25);
+        let s = VelocityCurve::Sigmoid(8.0);
+        assert!((s.apply(0.0)).abs() < 1e-6);
+        assert!((s.apply(1.0) - 1.0).abs() < 1e-6);
+        assert!((s.apply(0.5) - 0.5).abs() < 1e-6);
+        assert!(s.apply(0.75) > 0.75);
+        let b = VelocityCurve::Breakpoints(vec![(1.0, 0.8), (0.0, 0.2)]);
+        assert_eq!(b.apply(0.5), 0.5);
+        assert_eq!(b.apply(0.0), 0.2);
+        let mut f = vec![(0, 0.0, 60.0, 0.5), (0, 1.0, 60.0, 0.0)];
+        f.shape_velocity(VelocityCurve::Gamma(2.0));
+        assert_eq!(f, vec![(0, 0.0, 60.0, 0.25), (0, 1.0, 60.0, 0.0)]);
+    }
+
+    #[test]
+    fn control_curves(){
+        let c = vec![(7, 0.0, 0.0, 1.0), (7, 1.0, 1.0, 1.0), (7, 2.0, 0.0, 1.0)];
+        assert_eq!(c.sample_at(-1.0, Interpolation::Linear), 0.0);
+        assert_eq!(c.sample_at(0.5, Interpolation::Linear), 0.5);
+        assert_eq!(c.sample_at(0.5, Interpolation::Step), 0.0);
+        assert_eq!(c.sample_at(1.0, Interpolation::Cubic), 1.0);
+        assert!(c.sample_at(0.75, Interpolation::Cubic) > 0.75);
+        assert_eq!(c.sample_at(5.0, Interpolation::Cubic), 0.0);
+        assert_eq!(c.resample(2.0, Interpolation::Linear), vec![
+            (7, 0.0, 0.0, 1.0), (7, 0.5, 0.5, 1.0), (7, 1.0, 1.0, 1.0), (7, 1.5, 0.5, 1.0), (7, 2.0, 0.0, 1.0),
+        ]);
+        assert_eq!(Floww::new().sample_at(1.0, Interpolation::Linear), 0.0);
+    }
+}
@@ -0,0 +1,101 @@
+use crate::{ Point, FlowwPacket };
+
+use serde::{ Serialize, Deserialize };
+
+// points of one track with the time as ticks since the previous point and the velocity as a
+// byte, 9 bytes a point on the wire instead of 20, times are rounded to the tick
+#[derive(Clone,PartialEq,Debug,Serialize, Deserialize)]
+pub struct DeltaPoints{
+    pub start: f32,
+    pub tick: f32,
+    // (id, ticks since the previous point, note, velocity)
+    pub points: Vec<(u16, u16, f32, u8)>,
+}
+
+impl DeltaPoints{
+    pub fn len(&self) -> usize{
+        self.points.len()
+    }
+
+    pub fn is_empty(&self) -> bool{
+        self.points.is_empty()
+    }
+
+    pub fn decode(&self) -> Vec<Point>{
+        let mut ticks = 0u64;
+        self.points.iter().map(|(id, dt, note, vel)| {
+            ticks += *dt as u64;
+            (*id as usize, self.start + ticks as f32 * self.tick, *note, *vel as f32 / 255.0)
+        }).collect()
+    }
+}
+
+// an on stays an on however quiet it is
+fn velocity_byte(vel: f32) -> u8{
+    let byte = (vel.clamp(0.0, 1.0) * 255.0).round() as u8;
+    if vel > 0.0 { byte.max(1) } else { byte }
+}
+
+// delta encodes sorted points, a new packet starts whenever the time goes back or the gap doesn't
+// fit, points that can't be encoded (big ids, times that aren't finite) go out as Point
+pub fn delta_packets(points: &[Point], tick: f32) -> Vec<FlowwPacket>{
+    let tick = if tick > 0.0 { tick } else { 0.001 };
+    let mut res = Vec::new();
+    let mut current: Option<(DeltaPoints, u64)> = None;
+    for point in points{
+        let (id, time, note, vel) = *point;
+        if id > u16::MAX as usize || !time.is_finite() {
+            if let Some((chunk, _)) = current.take(){
+                res.push(FlowwPacket::Deltas(chunk));
+            }
+            res.push(FlowwPacket::Point(*point));
+            continue;
+        }
+        if let Some((chunk, ticks)) = &mut current{
+            let at = ((time - chunk.start) / tick).round();
+            if at >= *ticks as f32 && at - (*ticks as f32) <= u16::MAX as f32 {
+                let at = at as u64;
+                chunk.points.push((id as u16, (at - *ticks) as u16, note, velocity_byte(vel)));
+                *ticks = at;
+                continue;
+            }
+            res.push(FlowwPacket::Deltas(current.take().unwrap().0));
+        }
+        current = Some((DeltaPoints{ start: time, tick, points: vec![(id as u16, 0, note, velocity_byte(vel))] }, 0));
+    }
+    if let Some((chunk, _)) = current{
+        res.push(FlowwPacket::Deltas(chunk));
+    }
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+    #[test]
+    fn delta_points(){
+        let points: Floww = (0..64).map(|i| (i % 4, i as f32 * 0.125 + 0.0004, 36.0 + (i % 3) as f32, (i % 2) as f32 * 0.8)).collect();
+        let packets = delta_packets(&points, 0.001);
+        assert_eq!(packets.len(), 1);
+        let full = bincode::serialize(&vec![FlowwPacket::Points(points.clone())]).unwrap().len();
+        assert!(bincode::serialize(&packets).unwrap().len() * 2 < full);
+        let decoded = packets[0].points();
+        assert_eq!(decoded.len(), 64);
+        for (a, b) in points.iter().zip(decoded.iter()){
+            assert_eq!((a.0, a.2), (b.0, b.2));
+            assert!((a.1 - b.1).abs() < 0.001);
+            assert!((a.3 - b.3).abs() < 0.005);
+        }
+        let odd = delta_packets(&[(0, 1.0, 1.0, 0.001), (0, 0.5, 1.0, 0.0), (70000, 2.0, 1.0, 1.0), (0, 100.0, 1.0, 1.0)], 0.001);
+        assert_eq!(odd.len(), 4);
+        assert_eq!(odd[0].points()[0].3, 1.0 / 255.0);
+        assert_eq!(odd[2], FlowwPacket::Point((70000, 2.0, 1.0, 1.0)));
+        let mut sheet = FlowwSheet::new();
+        sheet.add(Vec::new(), "kick".to_string());
+        let mut stream = vec![FlowwPacket::Track("kick".to_string())];
+        stream.extend(packets);
+        sheet.unpacket(stream, UnknownTrackPolicy::Drop);
+        assert_eq!(sheet.get_floww_ref_by_name("kick").len(), 64);
+        assert_eq!(FlowwSheet::from_packets(sheet.to_delta_packets(0.001)).get_floww_ref_by_name("kick").len(), 64);
+    }
+}
@@ -0,0 +1,63 @@
+use crate::{ FlowwPacket };
+
+// gathers packets until a Flush so a receiver only ever works with complete frames, a frame can
+// be spread over several batches and one batch can hold several frames, the Flush packets
+// themselves are left out
+#[derive(Clone,PartialEq,Debug,Default)]
+pub struct FrameBuffer{
+    pending: Vec<FlowwPacket>,
+}
+
+impl FrameBuffer{
+    pub fn new() -> Self{
+        Self::default()
+    }
+
+    // packets of the frame that isn't complete yet
+    pub fn pending(&self) -> &[FlowwPacket]{
+        &self.pending
+    }
+
+    pub fn is_empty(&self) -> bool{
+        self.pending.is_empty()
+    }
+
+    // the frames this batch completed, in order
+    pub fn push(&mut self, packets: Vec<FlowwPacket>) -> Vec<Vec<FlowwPacket>>{
+        let mut frames = Vec::new();
+        for packet in packets{
+            if packet == FlowwPacket::Flush {
+                frames.push(std::mem::take(&mut self.pending));
+            } else {
+                self.pending.push(packet);
+            }
+        }
+        frames
+    }
+
+    // hands out the incomplete frame, for when the sender went away halfway through
+    pub fn take_pending(&mut self) -> Vec<FlowwPacket>{
+        std::mem::take(&mut self.pending)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+    #[test]
+    fn frame_buffer(){
+        let mut fb = FrameBuffer::new();
+        let point = |t| FlowwPacket::Point((0, t, 36.0, 1.0));
+        assert!(fb.push(vec![FlowwPacket::Track("kick".to_string()), point(0.0)]).is_empty());
+        assert_eq!(fb.pending().len(), 2);
+        let frames = fb.push(vec![point(0.5), FlowwPacket::Flush, point(1.0), FlowwPacket::Flush, point(1.5)]);
+        assert_eq!(frames, vec![
+            vec![FlowwPacket::Track("kick".to_string()), point(0.0), point(0.5)],
+            vec![point(1.0)],
+        ]);
+        assert_eq!(fb.push(vec![FlowwPacket::Flush, FlowwPacket::Flush]), vec![vec![point(1.5)], vec![]]);
+        fb.push(vec![point(2.0)]);
+        assert_eq!(fb.take_pending(), vec![point(2.0)]);
+        assert!(fb.is_empty());
+    }
+}
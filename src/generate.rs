@@ -0,0 +1,63 @@
+use crate::{ Floww, TimedVec, MIN_VELOCITY };
+use crate::rng::Rng;
+
+#[derive(Clone,PartialEq,Debug)]
+pub struct RandomFlowwConfig{
+    // expected notes per time unit
+    pub density: f32,
+    // inclusive range of whole notes
+    pub note_range: (f32, f32),
+    pub vel_range: (f32, f32),
+    pub length: f32,
+    pub seed: u64,
+}
+
+impl Default for RandomFlowwConfig{
+    fn default() -> Self{
+        Self{
+            density: 4.0,
+            note_range: (48.0, 72.0),
+            vel_range: (0.5, 1.0),
+            length: 4.0,
+            seed: 0,
+        }
+    }
+}
+
+// generates note ons only, the id of a point is its note like midi_to_floww does
+pub fn generate_random(config: &RandomFlowwConfig) -> Floww{
+    let mut rng = Rng::new(config.seed);
+    let n = (config.density * config.length).round().max(0.0) as usize;
+    let (lo, hi) = config.note_range;
+    let (lo, hi) = (lo.min(hi).round(), lo.max(hi).round());
+    let mut floww = Vec::with_capacity(n);
+    for _ in 0..n{
+        let time = rng.range(0.0, config.length);
+        let note = (lo + (rng.next_f32() * (hi - lo + 1.0)).floor()).min(hi);
+        let vel = rng.range(config.vel_range.0, config.vel_range.1).clamp(MIN_VELOCITY, 1.0);
+        floww.push((note as usize, time, note, vel));
+    }
+    floww.sorted()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+    #[test]
+    fn random(){
+        let config = RandomFlowwConfig{ seed: 3, ..Default::default() };
+        let a = generate_random(&config);
+        assert_eq!(a, generate_random(&config));
+        assert_ne!(a, generate_random(&RandomFlowwConfig{ seed: 4, ..Default::default() }));
+        assert_eq!(a.len(), 16);
+        for w in a.windows(2){
+            assert!(w[0].1 <= w[1].1);
+        }
+        for p in &a{
+            assert!((0.0..4.0).contains(&p.1));
+            assert!((48.0..=72.0).contains(&p.2));
+            assert_eq!(p.2.fract(), 0.0);
+            assert!((0.5..=1.0).contains(&p.3));
+        }
+    }
+}
@@ -0,0 +1,90 @@
+use crate::{ Point, Floww, MIN_VELOCITY };
+
+use serde::{ Serialize, Deserialize };
+
+// one (time offset, velocity offset) per grid step, the template repeats every steps.len() * grid
+#[derive(Clone,PartialEq,Debug,Default,Serialize,Deserialize)]
+pub struct Groove{
+    pub grid: f32,
+    pub steps: Vec<(f32, f32)>,
+}
+
+impl Groove{
+    pub fn len(&self) -> f32{
+        self.grid * self.steps.len() as f32
+    }
+
+    pub fn is_empty(&self) -> bool{
+        self.steps.is_empty()
+    }
+
+    fn step_of(&self, t: f32) -> Option<usize>{
+        if self.steps.is_empty() || self.grid.is_nan() || self.grid <= 0.0 { return None; }
+        let abs = (t / self.grid).round();
+        if !abs.is_finite() { return None; }
+        Some((abs as i64).rem_euclid(self.steps.len() as i64) as usize)
+    }
+}
+
+// the template spans the reference from zero to its last note on, every step holds the mean
+// deviation of the note ons closest to it: timing from the grid, velocity from the overall mean
+pub fn extract_groove(floww: &[Point], grid: f32) -> Groove{
+    let onsets = floww.iter().filter(|p| p.3 > 0.0 && p.1 >= 0.0).collect::<Vec<_>>();
+    if onsets.is_empty() || grid.is_nan() || grid <= 0.0 {
+        return Groove{ grid, steps: Vec::new() };
+    }
+    let last = onsets.iter().fold(0.0f32, |m, p| m.max(p.1));
+    let n = (last / grid).round() as usize + 1;
+    let mean_vel = onsets.iter().map(|p| p.3).sum::<f32>() / onsets.len() as f32;
+    let mut sums = vec![(0.0, 0.0, 0usize); n];
+    for p in onsets{
+        let step = ((p.1 / grid).round() as usize).min(n - 1);
+        sums[step].0 += p.1 - step as f32 * grid;
+        sums[step].1 += p.3 - mean_vel;
+        sums[step].2 += 1;
+    }
+    let steps = sums.into_iter().map(|(dt, dv, c)| {
+        if c == 0 { (0.0, 0.0) } else { (dt / c as f32, dv / c as f32) }
+    }).collect();
+    Groove{ grid, steps }
+}
+
+// every point moves by the timing offset of its nearest step, note ons also get the velocity offset
+pub fn apply_groove(floww: &mut Floww, groove: &Groove){
+    for p in floww.iter_mut(){
+        if let Some(step) = groove.step_of(p.1){
+            let (dt, dv) = groove.steps[step];
+            p.1 = (p.1 + dt).max(0.0);
+            if p.3 > 0.0 {
+                p.3 = (p.3 + dv).clamp(MIN_VELOCITY, 1.0);
+            }
+        }
+    }
+    crate::notes::sort_offs_first(floww);
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+    #[test]
+    fn groove(){
+        let reference = vec![
+            (0, 0.0, 36.0, 1.0), (0, 0.3, 36.0, 0.6), (0, 0.5, 36.0, 0.8), (0, 0.8, 36.0, 0.6),
+        ];
+        let g = extract_groove(&reference, 0.25);
+        assert_eq!(g.steps.len(), 4);
+        assert!((g.steps[1].0 - 0.05).abs() < 1e-6);
+        assert!((g.steps[0].1 - 0.25).abs() < 1e-6);
+        assert_eq!(g.len(), 1.0);
+        let mut straight = vec![
+            (1, 0.0, 38.0, 0.5), (1, 0.25, 38.0, 0.5), (1, 0.5, 38.0, 0.5), (1, 0.75, 38.0, 0.5),
+            (1, 1.25, 38.0, 0.5),
+        ];
+        apply_groove(&mut straight, &g);
+        assert_eq!(straight.iter().map(|p| (p.1 * 100.0).round() / 100.0).collect::<Vec<_>>(),
+            vec![0.0, 0.3, 0.5, 0.8, 1.3]);
+        assert!((straight[0].3 - 0.75).abs() < 1e-6);
+        assert!((straight[1].3 - 0.35).abs() < 1e-6);
+        assert_eq!(extract_groove(&[], 0.25), Groove{ grid: 0.25, steps: vec![] });
+    }
+}
@@ -0,0 +1,109 @@
+use crate::{ Scheduler, point_to_midi_bytes };
+
+use jack::{ AsyncClient, Client, ClientOptions, Control, MidiOut, Port, ProcessHandler, ProcessScope,
+    RawMidi, TransportState };
+
+// where in the cycle a point that became due lands, cycle_start is the sheet time at the first
+// frame, points before it came after the loop wrapped and are counted on from the loop end
+pub fn cycle_frame(cycle_start: f32, time: f32, loop_range: Option<(f32, f32)>, sample_rate: f32, n_frames: u32) -> u32{
+    let elapsed = match loop_range{
+        Some((start, end)) if time < cycle_start => (end - cycle_start) + (time - start),
+        _ => time - cycle_start,
+    };
+    let last = n_frames.saturating_sub(1);
+    ((elapsed * sample_rate).max(0.0) as u32).min(last)
+}
+
+struct JackHandler{
+    scheduler: Scheduler,
+    port: Port<MidiOut>,
+    channel: u8,
+    // (frame, midi bytes) of the current cycle, reserved outside the process callback
+    events: Vec<(u32, [u8; 3])>,
+    // the transport frame the next cycle starts at if nobody relocated
+    expected_frame: Option<u32>,
+}
+
+impl ProcessHandler for JackHandler{
+    fn process(&mut self, client: &Client, ps: &ProcessScope) -> Control{
+        let sample_rate = client.sample_rate() as f32;
+        let n_frames = ps.n_frames();
+        let (rolling, frame) = match client.transport().query(){
+            Ok(tp) => (tp.state == TransportState::Rolling, tp.pos.frame()),
+            Err(_) => (false, self.expected_frame.unwrap_or(0)),
+        };
+        // relocating jumps the scheduler, a loop inside the sheet keeps running on its own clock
+        if self.expected_frame != Some(frame) {
+            self.scheduler.seek(frame as f32 / sample_rate);
+        }
+        let was_playing = self.scheduler.is_playing();
+        if rolling { self.scheduler.play(); } else { self.scheduler.stop(); }
+        self.expected_frame = Some(if rolling { frame.wrapping_add(n_frames) } else { frame });
+
+        self.events.clear();
+        if was_playing && !rolling {
+            // all notes off, otherwise notes that were sounding hang
+            self.events.push((0, [0xb0 | (self.channel & 0x0f), 123, 0]));
+        }
+        let cycle_start = self.scheduler.time();
+        let loop_range = self.scheduler.loop_range();
+        let (channel, events) = (self.channel, &mut self.events);
+        self.scheduler.tick(n_frames as f32 / sample_rate, |_, p| {
+            events.push((cycle_frame(cycle_start, p.1, loop_range, sample_rate, n_frames), point_to_midi_bytes(p, channel)));
+        });
+        // offs before ons on the same frame, jack wants the frames in order
+        self.events.sort_unstable_by_key(|(frame, bytes)| (*frame, bytes[0] & 0xf0 == 0x90));
+
+        let mut writer = self.port.writer(ps);
+        for (time, bytes) in &self.events{
+            // a full port buffer drops the rest of the cycle
+            if writer.write(&RawMidi{ time: *time, bytes }).is_err() {
+                break;
+            }
+        }
+        Control::Continue
+    }
+
+    fn buffer_size(&mut self, _: &Client, size: jack::Frames) -> Control{
+        self.events.reserve(size as usize);
+        Control::Continue
+    }
+}
+
+// a jack client with one midi out port that plays the scheduler along with the jack transport,
+// every track goes out on the same channel
+pub struct JackOutput{
+    client: AsyncClient<(), JackHandler>,
+}
+
+impl JackOutput{
+    pub fn new(name: &str, scheduler: Scheduler, channel: u8) -> Result<Self, jack::Error>{
+        let (client, _) = Client::new(name, ClientOptions::NO_START_SERVER)?;
+        let port = client.register_port("midi_out", MidiOut::default())?;
+        let handler = JackHandler{ scheduler, port, channel, events: Vec::new(), expected_frame: None };
+        Ok(Self{ client: client.activate_async((), handler)? })
+    }
+
+    pub fn client(&self) -> &Client{
+        self.client.as_client()
+    }
+
+    // stops processing and gives the scheduler back
+    pub fn deactivate(self) -> Result<Scheduler, jack::Error>{
+        let (_, _, handler) = self.client.deactivate()?;
+        Ok(handler.scheduler)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+    #[test]
+    fn jack_cycle_frames(){
+        assert_eq!(cycle_frame(1.0, 1.0, None, 48000.0, 256), 0);
+        assert_eq!(cycle_frame(1.0, 1.001, None, 48000.0, 256), 48);
+        assert_eq!(cycle_frame(1.0, 2.0, None, 48000.0, 256), 255);
+        assert_eq!(cycle_frame(1.0, 0.5, None, 48000.0, 256), 0);
+        assert_eq!(cycle_frame(1.999, 0.0005, Some((0.0, 2.0)), 48000.0, 256), 72);
+    }
+}
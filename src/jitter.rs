@@ -0,0 +1,177 @@
+use crate::{ FlowwPacket, LatencyCompensator, TrackIds };
+
+use bincode::ErrorKind;
+use serde::{ Serialize, Deserialize };
+
+// a batch stamped with the sender clock when it was sent, point times are on the same clock
+#[derive(Clone,PartialEq,Debug,Serialize,Deserialize)]
+pub struct TimedBatch{
+    pub sent: f32,
+    pub packets: Vec<FlowwPacket>,
+}
+
+impl TimedBatch{
+    pub fn new(sent: f32, packets: Vec<FlowwPacket>) -> Self{
+        Self{ sent, packets }
+    }
+
+    pub fn to_bytes(&self) -> Result<Vec<u8>, Box<ErrorKind>>{
+        bincode::serialize(self)
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, Box<ErrorKind>>{
+        bincode::deserialize(bytes)
+    }
+}
+
+// holds points back until their time on the local clock, the send time of every batch feeds a
+// latency estimate so a batch that arrives late doesn't pull its points along with it,
+// everything that isn't a point is released on the next call
+pub struct DejitterBuffer{
+    latency: LatencyCompensator,
+    // (due, track, packet) sorted on due, packets with the same due keep arrival order
+    pending: Vec<(f32, Option<String>, FlowwPacket)>,
+    // track of the last pushed and last released point
+    incoming: Option<String>,
+    outgoing: Option<String>,
+    ids: TrackIds,
+}
+
+impl DejitterBuffer{
+    pub fn new(window: usize) -> Self{
+        Self{ latency: LatencyCompensator::new(window), pending: Vec::new(), incoming: None, outgoing: None, ids: TrackIds::new() }
+    }
+
+    pub fn latency(&self) -> &LatencyCompensator{
+        &self.latency
+    }
+
+    pub fn len(&self) -> usize{
+        self.pending.len()
+    }
+
+    pub fn is_empty(&self) -> bool{
+        self.pending.is_empty()
+    }
+
+    // forgets pending packets and the latency estimate
+    pub fn clear(&mut self){
+        self.pending.clear();
+        self.latency.reset();
+        self.incoming = None;
+        self.outgoing = None;
+        self.ids.clear();
+    }
+
+    fn insert(&mut self, due: f32, packet: FlowwPacket){
+        let i = self.pending.partition_point(|(d, _, _)| *d <= due);
+        self.pending.insert(i, (due, self.incoming.clone(), packet));
+    }
+
+    pub fn push(&mut self, batch: TimedBatch, local_time: f32){
+        self.latency.observe(batch.sent, local_time);
+        for packet in self.ids.resolve(batch.packets){
+            match packet{
+                FlowwPacket::Track(name) => self.incoming = Some(name),
+                // stamps of single packets refine the estimate of the batch
+                FlowwPacket::Timestamp(t) => self.latency.observe(t, local_time),
+                FlowwPacket::Point(p) => {
+                    let p = self.latency.retime(p);
+                    self.insert(p.1, FlowwPacket::Point(p));
+                },
+                FlowwPacket::RichPoint(mut r) => {
+                    r.point = self.latency.retime(r.point);
+                    self.insert(r.point.1, FlowwPacket::RichPoint(r));
+                },
+                // split up since the points can be due at different times
+                FlowwPacket::Points(_) | FlowwPacket::Deltas(_) => for p in packet.points().iter().copied(){
+                    let p = self.latency.retime(p);
+                    self.insert(p.1, FlowwPacket::Point(p));
+                },
+                // points that were waiting would otherwise come back after the clear
+                FlowwPacket::Clear(name) => {
+                    self.pending.retain(|(_, t, p)| !p.has_points() || t.as_deref() != Some(name.as_str()));
+                    self.insert(f32::NEG_INFINITY, FlowwPacket::Clear(name));
+                },
+                FlowwPacket::ClearAll => {
+                    self.pending.retain(|(_, _, p)| !p.has_points());
+                    self.insert(f32::NEG_INFINITY, FlowwPacket::ClearAll);
+                },
+                other => self.insert(f32::NEG_INFINITY, other),
+            }
+        }
+    }
+
+    // the packets due at local_time, track packets are put back in front of points whenever
+    // the track changes so the result can go straight into unpacket
+    pub fn release(&mut self, local_time: f32) -> Vec<FlowwPacket>{
+        let n = self.pending.partition_point(|(d, _, _)| *d <= local_time);
+        let mut res = Vec::new();
+        for (_, track, packet) in self.pending.drain(..n){
+            if packet.has_points() && track.is_some() && track != self.outgoing {
+                res.push(FlowwPacket::Track(track.clone().unwrap_or_default()));
+                self.outgoing = track;
+            }
+            res.push(packet);
+        }
+        res
+    }
+
+    // when the next pending packet is due
+    pub fn next_due(&self) -> Option<f32>{
+        self.pending.first().map(|(d, _, _)| *d)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+    #[test]
+    fn dejitter(){
+        let batch = TimedBatch::new(0.0, vec![
+            FlowwPacket::Track("kick".to_string()), FlowwPacket::Point((0, 0.5, 36.0, 1.0)),
+            FlowwPacket::Track("keys".to_string()), FlowwPacket::Point((0, 0.25, 60.0, 1.0)),
+        ]);
+        assert_eq!(TimedBatch::from_bytes(&batch.to_bytes().unwrap()).unwrap(), batch);
+        let mut buf = DejitterBuffer::new(8);
+        buf.push(batch, 10.0);
+        // arrives 0.2 late, the jitter estimate grows and its point is held until 11.1
+        buf.push(TimedBatch::new(1.0, vec![
+            FlowwPacket::Msg("hi".to_string()), FlowwPacket::Track("kick".to_string()),
+            FlowwPacket::Point((0, 1.0, 36.0, 1.0)),
+        ]), 11.2);
+        assert_eq!(buf.len(), 4);
+        assert_eq!(buf.release(10.1), vec![FlowwPacket::Msg("hi".to_string())]);
+        assert_eq!(buf.next_due(), Some(10.25));
+        let due = buf.release(10.6);
+        assert_eq!(due.iter().map(|p| match p{
+            FlowwPacket::Track(name) => name.clone(),
+            _ => "point".to_string(),
+        }).collect::<Vec<_>>(), vec!["keys", "point", "kick", "point"]);
+        let rest = buf.release(11.2);
+        assert_eq!(rest.len(), 1);
+        if let FlowwPacket::Point(p) = rest[0] {
+            assert!((p.1 - 11.1).abs() < 1e-4);
+        } else {
+            panic!();
+        }
+        assert!(buf.is_empty());
+    }
+    #[test]
+    fn dejitter_rich_points(){
+        let rich = |time, note| FlowwPacket::RichPoint(RichPoint::new((0, time, note, 1.0)).with(PARAM_PAN, 0.5));
+        let mut buf = DejitterBuffer::new(8);
+        buf.push(TimedBatch::new(0.0, vec![
+            FlowwPacket::Track("kick".to_string()), rich(0.5, 36.0),
+            FlowwPacket::Track("keys".to_string()), rich(0.25, 60.0), rich(0.75, 62.0),
+        ]), 0.0);
+        let mut sheet = FlowwSheet::new();
+        sheet.add(Vec::new(), "other".to_string());
+        sheet.add(Vec::new(), "kick".to_string());
+        sheet.add(Vec::new(), "keys".to_string());
+        sheet.unpacket(buf.release(1.0), UnknownTrackPolicy::Drop);
+        assert!(sheet.get_floww_ref_by_name("other").is_empty());
+        assert_eq!(sheet.get_floww_ref_by_name("kick"), &[(0, 0.5, 36.0, 1.0)]);
+        assert_eq!(sheet.get_floww_ref_by_name("keys"), &[(0, 0.25, 60.0, 1.0), (0, 0.75, 62.0, 1.0)]);
+    }
+}
@@ -0,0 +1,136 @@
+use crate::{ Point, RichPoint, FlowwPacket };
+
+use std::collections::VecDeque;
+use std::time::Instant;
+
+// monotonic seconds since the sender started, stamp puts the current time in front of a batch
+#[derive(Clone,Copy,PartialEq,Debug)]
+pub struct SenderClock{
+    start: Instant,
+}
+
+impl Default for SenderClock{
+    fn default() -> Self{
+        Self::new()
+    }
+}
+
+impl SenderClock{
+    pub fn new() -> Self{
+        Self{ start: Instant::now() }
+    }
+
+    pub fn now(&self) -> f32{
+        self.start.elapsed().as_secs_f32()
+    }
+
+    pub fn stamp(&self, packets: Vec<FlowwPacket>) -> Vec<FlowwPacket>{
+        self.stamp_at(packets, self.now())
+    }
+
+    pub fn stamp_at(&self, mut packets: Vec<FlowwPacket>, time: f32) -> Vec<FlowwPacket>{
+        packets.insert(0, FlowwPacket::Timestamp(time));
+        packets
+    }
+}
+
+// estimates how the sender clock maps onto the local clock from (sender time, arrival time) pairs,
+// the least delayed sample of the window is taken as the offset and the mean delay on top of
+// it as jitter, points are moved by both so they are scheduled after most late arrivals
+#[derive(Clone,PartialEq,Debug)]
+pub struct LatencyCompensator{
+    samples: VecDeque<f32>,
+    window: usize,
+}
+
+impl LatencyCompensator{
+    pub fn new(window: usize) -> Self{
+        Self{ samples: VecDeque::new(), window: window.max(1) }
+    }
+
+    pub fn observe(&mut self, sender_time: f32, local_time: f32){
+        let sample = local_time - sender_time;
+        if !sample.is_finite() { return; }
+        if self.samples.len() == self.window {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(sample);
+    }
+
+    pub fn reset(&mut self){
+        self.samples.clear();
+    }
+
+    // None until something was observed
+    pub fn offset(&self) -> Option<f32>{
+        self.samples.iter().copied().reduce(f32::min)
+    }
+
+    pub fn jitter(&self) -> f32{
+        let offset = if let Some(offset) = self.offset(){
+            offset
+        } else {
+            return 0.0;
+        };
+        self.samples.iter().map(|s| s - offset).sum::<f32>() / self.samples.len() as f32
+    }
+
+    // what gets added to sender times
+    pub fn latency(&self) -> f32{
+        self.offset().map(|o| o + self.jitter()).unwrap_or(0.0)
+    }
+
+    pub fn retime(&self, point: Point) -> Point{
+        (point.0, point.1 + self.latency(), point.2, point.3)
+    }
+
+    // Position and Timestamp packets are stamped with local_time and observed, points are moved
+    // onto the local clock
+    pub fn process(&mut self, packets: Vec<FlowwPacket>, local_time: f32) -> Vec<FlowwPacket>{
+        for packet in &packets{
+            if let FlowwPacket::Position(t) | FlowwPacket::Timestamp(t) = packet{
+                self.observe(*t, local_time);
+            }
+        }
+        packets.into_iter().map(|packet| match packet{
+            FlowwPacket::Point(p) => FlowwPacket::Point(self.retime(p)),
+            FlowwPacket::RichPoint(r) => FlowwPacket::RichPoint(RichPoint{ point: self.retime(r.point), ..r }),
+            FlowwPacket::Points(ps) => FlowwPacket::Points(ps.into_iter().map(|p| self.retime(p)).collect()),
+            FlowwPacket::Deltas(d) => FlowwPacket::Points(d.decode().into_iter().map(|p| self.retime(p)).collect()),
+            other => other,
+        }).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+    #[test]
+    fn latency(){
+        let mut lc = LatencyCompensator::new(3);
+        assert_eq!(lc.offset(), None);
+        assert_eq!(lc.retime((0, 1.0, 60.0, 1.0)), (0, 1.0, 60.0, 1.0));
+        lc.observe(0.0, 10.2);
+        lc.observe(1.0, 11.1);
+        lc.observe(2.0, 12.3);
+        assert!((lc.offset().unwrap() - 10.1).abs() < 1e-4);
+        assert!((lc.jitter() - 0.1).abs() < 1e-4);
+        lc.observe(3.0, 13.1);
+        lc.observe(4.0, 14.1);
+        assert!((lc.jitter() - 0.0667).abs() < 1e-3);
+        let out = lc.process(vec![FlowwPacket::Position(5.0), FlowwPacket::Point((0, 5.0, 60.0, 1.0))], 15.1);
+        assert_eq!(out[0], FlowwPacket::Position(5.0));
+        if let FlowwPacket::Point(p) = out[1] {
+            assert!((p.1 - 15.1).abs() < 1e-4);
+        } else {
+            panic!();
+        }
+        let clock = SenderClock::new();
+        let stamped = clock.stamp_at(vec![FlowwPacket::Point((0, 1.0, 60.0, 1.0))], 1.0);
+        assert_eq!(stamped[0], FlowwPacket::Timestamp(1.0));
+        let mut lc = LatencyCompensator::new(4);
+        lc.process(stamped, 1.5);
+        assert!((lc.offset().unwrap() - 0.5).abs() < 1e-4);
+        assert!(clock.now() >= 0.0);
+    }
+}
@@ -0,0 +1,115 @@
+use crate::{ Point, Floww, FlowwSheet };
+
+use bincode::ErrorKind;
+
+use std::collections::{ HashMap };
+
+fn unexpected_eof() -> Box<ErrorKind>{
+    Box::new(ErrorKind::Io(std::io::ErrorKind::UnexpectedEof.into()))
+}
+
+// reads the index of FlowwSheet::serialize output by hopping over the length prefixes,
+// the points of a track are only decoded when it is loaded
+pub struct LazySheet<'a>{
+    bytes: &'a [u8],
+    ranges: Vec<(usize, usize)>,
+    names: Vec<String>,
+    map: HashMap<String, usize>,
+}
+
+impl<'a> LazySheet<'a>{
+    pub fn new(bytes: &'a [u8]) -> Result<Self, Box<ErrorKind>>{
+        let point_size = bincode::serialized_size(&(0usize, 0.0f32, 0.0f32, 0.0f32))? as usize;
+        let prefix = bincode::serialized_size(&0u64)? as usize;
+        let read_len = |pos: usize| -> Result<usize, Box<ErrorKind>>{
+            let len: u64 = bincode::deserialize(bytes.get(pos..).ok_or_else(unexpected_eof)?)?;
+            Ok(len as usize)
+        };
+        let n = read_len(0)?;
+        let mut pos = prefix;
+        let mut ranges = Vec::new();
+        for _ in 0..n{
+            let start = pos;
+            pos = read_len(pos)?.checked_mul(point_size)
+                .and_then(|l| l.checked_add(pos + prefix))
+                .filter(|end| *end <= bytes.len())
+                .ok_or_else(unexpected_eof)?;
+            ranges.push((start, pos));
+        }
+        let names: Vec<String> = bincode::deserialize(&bytes[pos..])?;
+        if names.len() != ranges.len() {
+            return Err(Box::new(ErrorKind::Custom("floww: track and name count differ".to_string())));
+        }
+        let map = names.iter().enumerate().map(|(i, n)| (n.clone(), i)).collect();
+        Ok(Self{ bytes, ranges, names, map })
+    }
+
+    pub fn names(&self) -> &[String]{
+        &self.names
+    }
+
+    pub fn len(&self) -> usize{
+        self.names.len()
+    }
+
+    pub fn is_empty(&self) -> bool{
+        self.names.is_empty()
+    }
+
+    pub fn contains(&self, name: &str) -> bool{
+        self.map.contains_key(name)
+    }
+
+    // number of points without decoding them
+    pub fn track_len(&self, name: &str) -> Option<usize>{
+        let (start, _) = self.ranges[*self.map.get(name)?];
+        bincode::deserialize::<u64>(&self.bytes[start..]).ok().map(|l| l as usize)
+    }
+
+    pub fn load(&self, name: &str) -> Option<Floww>{
+        self.load_index(*self.map.get(name)?)
+    }
+
+    fn load_index(&self, index: usize) -> Option<Floww>{
+        let (start, end) = self.ranges[index];
+        bincode::deserialize::<Vec<Point>>(&self.bytes[start..end]).ok()
+    }
+
+    pub fn into_sheet(self) -> FlowwSheet{
+        let mut sheet = FlowwSheet::new();
+        for (i, name) in self.names.iter().enumerate(){
+            sheet.add(self.load_index(i).unwrap_or_default(), name.clone());
+        }
+        sheet
+    }
+}
+
+impl FlowwSheet{
+    // inverse of serialize, track metadata isn't stored so it starts out as default
+    pub fn deserialize(bytes: &[u8]) -> Result<FlowwSheet, Box<ErrorKind>>{
+        Ok(LazySheet::new(bytes)?.into_sheet())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+    #[test]
+    fn lazy_sheet(){
+        let mut sheet = FlowwSheet::new();
+        sheet.add(vec![(0, 0.0, 36.0, 1.0), (1, 0.5, 38.0, 0.5)], "drums".to_string());
+        sheet.add(Vec::new(), "empty".to_string());
+        sheet.add(vec![(0, 1.0, 60.0, 1.0)], "keys".to_string());
+        let bytes = sheet.clone().serialize().unwrap();
+        let lazy = LazySheet::new(&bytes).unwrap();
+        assert_eq!(lazy.names(), &["drums".to_string(), "empty".to_string(), "keys".to_string()]);
+        assert_eq!(lazy.track_len("drums"), Some(2));
+        assert_eq!(lazy.load("keys"), Some(vec![(0, 1.0, 60.0, 1.0)]));
+        assert_eq!(lazy.load("empty"), Some(vec![]));
+        assert_eq!(lazy.load("bass"), None);
+        let back = FlowwSheet::deserialize(&bytes).unwrap();
+        assert_eq!(back["drums"], sheet["drums"]);
+        assert!(LazySheet::new(&bytes[..bytes.len() - 3]).is_err());
+        assert!(LazySheet::new(&bytes[..20]).is_err());
+    }
+}
@@ -1,16 +1,115 @@
-use apres::{ MIDI, ApresError };
+use apres::{ MIDI, MIDIBytes, ApresError };
 use apres::MIDIEvent::{ NoteOn, NoteOff, SetTempo };
 use serde::{ Serialize, Deserialize };
 use bincode::ErrorKind;
 use fnrs::MutFunc;
 
+use std::borrow::Cow;
 use std::collections::{ HashMap };
 use std::io::Read;
+use std::sync::Arc;
+
+mod rng;
+mod scale;
+mod analysis;
+mod notes;
+mod curve;
+mod generate;
+mod cursor;
+mod tempo;
+mod groove;
+mod meta;
+mod selection;
+mod merge;
+mod lazy;
+mod scheduler;
+mod ring;
+mod latency;
+mod jitter;
+mod liveness;
+mod channel;
+mod recorder;
+mod replay;
+mod pipeline;
+mod multi;
+mod frame;
+mod validate;
+mod delta;
+mod priority;
+mod wire;
+#[cfg(feature = "net")]
+mod net;
+#[cfg(feature = "net")]
+mod udp;
+#[cfg(feature = "osc")]
+mod osc;
+#[cfg(feature = "async")]
+mod async_io;
+#[cfg(feature = "jack")]
+mod jack_io;
+#[cfg(feature = "link")]
+mod link;
+#[cfg(feature = "ws")]
+mod ws;
+#[cfg(feature = "shm")]
+mod shm;
+#[cfg(feature = "serial")]
+mod serial;
+
+use rng::Rng;
+pub use scale::*;
+pub use analysis::*;
+pub use notes::*;
+pub use curve::*;
+pub use generate::*;
+pub use cursor::*;
+pub use tempo::*;
+pub use groove::*;
+pub use meta::*;
+pub use selection::*;
+pub use merge::*;
+pub use lazy::*;
+pub use scheduler::*;
+pub use ring::*;
+pub use latency::*;
+pub use jitter::*;
+pub use liveness::*;
+pub use channel::*;
+pub use recorder::*;
+pub use replay::*;
+pub use pipeline::*;
+pub use multi::*;
+pub use frame::*;
+pub use validate::*;
+pub use delta::*;
+pub use priority::*;
+pub use wire::{ WireId, WirePoint, to_wire, from_wire };
+#[cfg(feature = "net")]
+pub use net::*;
+#[cfg(feature = "net")]
+pub use udp::*;
+#[cfg(feature = "osc")]
+pub use osc::*;
+#[cfg(feature = "async")]
+pub use async_io::*;
+#[cfg(feature = "jack")]
+pub use jack_io::*;
+#[cfg(feature = "link")]
+pub use link::*;
+#[cfg(feature = "ws")]
+pub use ws::*;
+#[cfg(feature = "shm")]
+pub use shm::*;
+#[cfg(feature = "serial")]
+pub use serial::*;
 
 // (id, time, note, vel)
 pub type Point = (usize, f32, f32, f32);
 pub type Floww = Vec<Point>;
 
+// lowest midi velocity, transforms keep note ons above this so they don't turn into note offs
+const MIN_VELOCITY: f32 = 1.0 / 127.0;
+
 pub trait Timed{
     fn time(&self) -> f32;
     fn time_mut(&mut self) -> &mut f32;
@@ -39,13 +138,37 @@ impl Timed for Point{
     }
 }
 
+// index of the first offending point
+#[derive(Clone,Copy,PartialEq,Eq,Debug)]
+pub enum InvalidTime{
+    NaN(usize),
+    Infinite(usize),
+    Negative(usize),
+}
+
 pub trait TimedVec{
+    type Item;
+
     fn sort(&mut self);
     fn shift_time(&mut self, t: f32);
     fn start_from_zero(&mut self);
     fn scale(&mut self, factor: f32);
     fn merge(&mut self, other: Self);
     fn fuse(&mut self, other: Self);
+    fn points_between(&self, t0: f32, t1: f32) -> &[Self::Item];
+    fn reverse_time(&mut self);
+    fn swing(&mut self, grid: f32, amount: f32);
+    fn trim(&mut self, start: f32, end: f32);
+    fn crop(&mut self, start: f32, end: f32);
+    fn stretch_to(&mut self, target_len: f32);
+    fn validate(&self) -> Result<(), InvalidTime>;
+    fn chunks_by_interval(&self, interval: f32) -> IntervalChunks<'_, Self::Item>;
+    fn quantize(&mut self, grid: f32);
+    fn rotate(&mut self, offset: f32, period: f32);
+    fn mirror(&mut self, pivot: f32);
+    fn retime(&mut self, from_bpm: f32, to_bpm: f32);
+    fn retime_around(&mut self, from_bpm: f32, to_bpm: f32, anchor: f32);
+    fn fold_into(&mut self, period: f32);
 
     fn sorted(self) -> Self;
     fn time_shifted(self, t: f32) -> Self;
@@ -53,11 +176,33 @@ pub trait TimedVec{
     fn scaled(self, factor: f32) -> Self;
     fn merged(self, other: Self) -> Self;
     fn fused(self, other: Self) -> Self;
+    fn time_reversed(self) -> Self;
+    fn repeated(self, n: usize, period: f32) -> Self;
+    fn swung(self, grid: f32, amount: f32) -> Self;
+    fn trimmed(self, start: f32, end: f32) -> Self;
+    fn cropped(self, start: f32, end: f32) -> Self;
+    fn stretched_to(self, target_len: f32) -> Self;
+    fn quantized(self, grid: f32) -> Self;
+    fn rotated(self, offset: f32, period: f32) -> Self;
+    fn mirrored(self, pivot: f32) -> Self;
+    fn retimed(self, from_bpm: f32, to_bpm: f32) -> Self;
+    fn folded_into(self, period: f32) -> Self;
+}
+
+// (first time, last end)
+fn time_bounds<T: Timed>(v: &[T]) -> Option<(f32, f32)>{
+    if v.is_empty() { return None; }
+    let begin = v.iter().fold(f32::MAX, |m, p| m.min(p.time()));
+    let end = v.iter().fold(f32::MIN, |m, p| m.max(p.end()));
+    Some((begin, end))
 }
 
-impl<T: Timed> TimedVec for Vec<T>{
+impl<T: Timed + Clone> TimedVec for Vec<T>{
+    type Item = T;
+
+    // ieee total order, NaN times end up at the back (the front if negative) instead of panicking
     fn sort(&mut self){
-        self.sort_by(|a,b| a.time().partial_cmp(&b.time()).unwrap());
+        self.sort_by(|a,b| a.time().total_cmp(&b.time()));
     }
 
     fn shift_time(&mut self, t: f32){
@@ -99,6 +244,127 @@ impl<T: Timed> TimedVec for Vec<T>{
         }
     }
 
+    // assumes sorted, half open: [t0, t1)
+    fn points_between(&self, t0: f32, t1: f32) -> &[T]{
+        let a = self.partition_point(|p| p.time() < t0);
+        let b = self.partition_point(|p| p.time() < t1).max(a);
+        &self[a..b]
+    }
+
+    fn reverse_time(&mut self){
+        let (begin, end) = if let Some(b) = time_bounds(self){
+            b
+        } else {
+            return;
+        };
+        self.iter_mut().for_each(|p| *p.time_mut() = begin + end - p.time());
+        self.reverse();
+        self.sort();
+    }
+
+    // time inside every pair of grid steps is warped so the off-beat lands amount * grid later,
+    // amount is clamped to [-1, 1] where the mapping is monotonic so order is kept
+    fn swing(&mut self, grid: f32, amount: f32){
+        if grid <= 0.0 { return; }
+        let amount = amount.clamp(-1.0, 1.0);
+        let pair = grid * 2.0;
+        for p in self.iter_mut(){
+            let t = p.time();
+            let base = (t / pair).floor() * pair;
+            let x = t - base;
+            let y = if x < grid {
+                x * (1.0 + amount)
+            } else {
+                grid * (1.0 + amount) + (x - grid) * (1.0 - amount)
+            };
+            *p.time_mut() = base + y;
+        }
+    }
+
+    // keeps [start, end)
+    fn trim(&mut self, start: f32, end: f32){
+        self.retain(|p| p.time() >= start && p.time() < end);
+    }
+
+    fn crop(&mut self, start: f32, end: f32){
+        self.trim(start, end);
+        self.iter_mut().for_each(|p| *p.time_mut() -= start);
+    }
+
+    // scales around the first point so only the span changes
+    fn stretch_to(&mut self, target_len: f32){
+        let (begin, end) = if let Some(b) = time_bounds(self){
+            b
+        } else {
+            return;
+        };
+        let span = end - begin;
+        if span <= 0.0 { return; }
+        self.iter_mut().for_each(|p| *p.time_mut() -= begin);
+        self.scale(target_len / span);
+        self.iter_mut().for_each(|p| *p.time_mut() += begin);
+    }
+
+    fn validate(&self) -> Result<(), InvalidTime>{
+        for (i, p) in self.iter().enumerate(){
+            let t = p.time();
+            if t.is_nan() {
+                return Err(InvalidTime::NaN(i));
+            } else if t.is_infinite() {
+                return Err(InvalidTime::Infinite(i));
+            } else if t < 0.0 {
+                return Err(InvalidTime::Negative(i));
+            }
+        }
+        Ok(())
+    }
+
+    fn chunks_by_interval(&self, interval: f32) -> IntervalChunks<'_, T>{
+        IntervalChunks::new(self, interval)
+    }
+
+    // snaps every time to the nearest multiple of grid, the mapping keeps order
+    fn quantize(&mut self, grid: f32){
+        if grid.is_nan() || grid <= 0.0 { return; }
+        self.iter_mut().for_each(|p| *p.time_mut() = (p.time() / grid).round() * grid);
+    }
+
+    // shifts by offset and wraps around [0, period)
+    fn rotate(&mut self, offset: f32, period: f32){
+        if period.is_nan() || period <= 0.0 { return; }
+        self.iter_mut().for_each(|p| {
+            // rem_euclid rounds tiny negatives up to period itself
+            let t = (p.time() + offset).rem_euclid(period);
+            *p.time_mut() = if t >= period { 0.0 } else { t };
+        });
+        self.sort();
+    }
+
+    // times left of zero after mirroring are kept, only shift_time clamps
+    fn mirror(&mut self, pivot: f32){
+        self.iter_mut().for_each(|p| *p.time_mut() = 2.0 * pivot - p.time());
+        self.reverse();
+        self.sort();
+    }
+
+    // playing at to_bpm what was written at from_bpm, so times scale by from / to
+    fn retime(&mut self, from_bpm: f32, to_bpm: f32){
+        self.retime_around(from_bpm, to_bpm, 0.0);
+    }
+
+    // the point at anchor stays where it is
+    fn retime_around(&mut self, from_bpm: f32, to_bpm: f32, anchor: f32){
+        if from_bpm <= 0.0 || to_bpm <= 0.0 { return; }
+        self.iter_mut().for_each(|p| *p.time_mut() -= anchor);
+        self.scale(from_bpm / to_bpm);
+        self.iter_mut().for_each(|p| *p.time_mut() += anchor);
+    }
+
+    // wraps every time into [0, period), stacking all cycles onto the first
+    fn fold_into(&mut self, period: f32){
+        self.rotate(0.0, period);
+    }
+
     fn sorted(mut self) -> Self{
         self.sort();
         self
@@ -128,184 +394,1574 @@ impl<T: Timed> TimedVec for Vec<T>{
         self.fuse(other);
         self
     }
-}
 
-#[derive(Clone,Default)]
-pub struct FlowwSheet{
-    flowws: Vec<Floww>,
-    names: Vec<String>,
-    map: HashMap<String, usize>,
-}
+    fn time_reversed(mut self) -> Self{
+        self.reverse_time();
+        self
+    }
 
-impl FlowwSheet{
-    pub fn new() -> Self{
-        Self::default()
+    // no mutating repeat as it would hide <[T]>::repeat
+    fn repeated(mut self, n: usize, period: f32) -> Self{
+        if n == 0 {
+            self.clear();
+            return self;
+        }
+        let l = self.len();
+        for i in 1..n{
+            let shift = i as f32 * period;
+            for j in 0..l{
+                let mut p = self[j].clone();
+                *p.time_mut() += shift;
+                self.push(p);
+            }
+        }
+        self.sort();
+        self
     }
 
-    pub fn add(&mut self, floww: Floww, name: String){
-        let index = self.flowws.len();
-        self.flowws.push(floww);
-        self.map.insert(name.clone(), index);
-        self.names.push(name);
+    fn swung(mut self, grid: f32, amount: f32) -> Self{
+        self.swing(grid, amount);
+        self
     }
 
-    pub fn get_floww_ref_by_name(&self, name: &str) -> &[Point]{
-        if let Some(index) = self.map.get(name){
-            &self.flowws[*index]
-        } else {
-            &[]
-        }
+    fn trimmed(mut self, start: f32, end: f32) -> Self{
+        self.trim(start, end);
+        self
     }
 
-    pub fn get_names(&self) -> Vec<String>{
-        self.names.clone()
+    fn cropped(mut self, start: f32, end: f32) -> Self{
+        self.crop(start, end);
+        self
     }
 
-    pub fn reset(&mut self, name: &str, new: Floww) -> bool{
-        if let Some(index) = self.map.get(name){
-            self.flowws[*index] = new;
-            true
-        } else {
-            false
+    fn stretched_to(mut self, target_len: f32) -> Self{
+        self.stretch_to(target_len);
+        self
+    }
+
+    fn quantized(mut self, grid: f32) -> Self{
+        self.quantize(grid);
+        self
+    }
+
+    fn rotated(mut self, offset: f32, period: f32) -> Self{
+        self.rotate(offset, period);
+        self
+    }
+
+    fn mirrored(mut self, pivot: f32) -> Self{
+        self.mirror(pivot);
+        self
+    }
+
+    fn retimed(mut self, from_bpm: f32, to_bpm: f32) -> Self{
+        self.retime(from_bpm, to_bpm);
+        self
+    }
+
+    fn folded_into(mut self, period: f32) -> Self{
+        self.fold_into(period);
+        self
+    }
+}
+
+fn add_grace_notes<F: Fn(&Point) -> bool>(floww: &mut Floww, selector: F, count: usize, offset: f32, vel_ratio: f32){
+    let vel_ratio = vel_ratio.max(0.0);
+    let mut grace = Vec::new();
+    for p in floww.iter().filter(|p| p.3 > 0.0 && selector(p)){
+        for i in 1..=count{
+            let t = (p.1 - offset * i as f32).max(0.0);
+            grace.push((p.0, t, p.2, (p.3 * vel_ratio).clamp(MIN_VELOCITY, 1.0)));
         }
     }
+    floww.merge(grace);
+}
 
-    pub fn to_floww_packets(self) -> Vec<FlowwPacket>{
-        let mut res = Vec::new();
-        for (floww, name) in self.flowws.into_iter().zip(self.names.into_iter()){
-            res.push(FlowwPacket::Track(name));
-            for point in floww{
-                res.push(FlowwPacket::Point(point));
+pub trait PointVec{
+    fn humanize(&mut self, time_jitter: f32, vel_jitter: f32, seed: u64);
+    fn shape_velocity(&mut self, curve: VelocityCurve);
+    fn invert_notes(&mut self, pivot: f32);
+    fn ratchet<F: Fn(&Point) -> bool>(&mut self, selector: F, divisions: usize, length: f32);
+    fn remap_ids(&mut self, map: &HashMap<usize, usize>);
+    fn map_ids<F: Fn(usize) -> usize>(&mut self, f: F);
+    fn remove_below_velocity(&mut self, threshold: f32);
+    fn boost_below_velocity(&mut self, threshold: f32, floor: f32);
+    fn thin(&mut self, keep_probability: f32, seed: u64);
+    fn thin_steps(&mut self, grid: f32, keep_probabilities: &[f32], seed: u64);
+    fn add_flams<F: Fn(&Point) -> bool>(&mut self, selector: F, offset: f32, vel_ratio: f32);
+    fn add_drags<F: Fn(&Point) -> bool>(&mut self, selector: F, offset: f32, vel_ratio: f32);
+    fn merge_prioritized<F: Fn(&Point, &Point) -> bool>(&mut self, other: Self, epsilon: f32, prefer_other: F);
+
+    fn humanized(self, time_jitter: f32, vel_jitter: f32, seed: u64) -> Self;
+    fn velocity_shaped(self, curve: VelocityCurve) -> Self;
+    fn notes_inverted(self, pivot: f32) -> Self;
+    fn ratcheted<F: Fn(&Point) -> bool>(self, selector: F, divisions: usize, length: f32) -> Self;
+    fn ids_remapped(self, map: &HashMap<usize, usize>) -> Self;
+    fn removed_below_velocity(self, threshold: f32) -> Self;
+    fn thinned(self, keep_probability: f32, seed: u64) -> Self;
+    fn fuse_with_overlap(self, other: Self, overlap: f32) -> Self;
+}
+
+impl PointVec for Floww{
+    // note offs (vel 0) keep their velocity
+    fn humanize(&mut self, time_jitter: f32, vel_jitter: f32, seed: u64){
+        let mut rng = Rng::new(seed);
+        for p in self.iter_mut(){
+            p.1 = (p.1 + rng.range(-time_jitter, time_jitter)).max(0.0);
+            let dv = rng.range(-vel_jitter, vel_jitter);
+            if p.3 > 0.0 {
+                p.3 = (p.3 + dv).clamp(MIN_VELOCITY, 1.0);
             }
         }
-        res
+        self.sort();
     }
 
-    pub fn serialize(self) -> Result<Vec<u8>, Box<bincode::ErrorKind>>{
-        let x = bincode::serialize(&self.flowws)?;
-        let y = bincode::serialize(&self.names)?;
-        Ok(x.conc(y))
+    fn shape_velocity(&mut self, curve: VelocityCurve){
+        self.iter_mut().filter(|p| p.3 > 0.0).for_each(|p| p.3 = curve.apply(p.3).max(MIN_VELOCITY));
     }
-}
 
-pub fn midi_to_floww(midi: MIDI) -> Floww{
-    let ppqn = midi.get_ppqn() as f32;
-    let mut time_mult = 1.0; // 60bpm per default
-    let mut floww = Vec::new();
-    for track in midi.get_tracks(){
-        let mut time = 0.0;
-        for (tick, id) in track{
-            time += tick as f32 / ppqn * time_mult;
-            let ev = midi.get_event(id);
-            if let Some(NoteOn(_, note, vel)) = ev {
-                floww.push((note as usize, time, note as f32, vel as f32 / 127.0));
+    fn invert_notes(&mut self, pivot: f32){
+        self.iter_mut().for_each(|p| p.2 = 2.0 * pivot - p.2);
+    }
+
+    // every selected note on becomes divisions hits spread evenly over length,
+    // each repeat a bit softer than the one before
+    fn ratchet<F: Fn(&Point) -> bool>(&mut self, selector: F, divisions: usize, length: f32){
+        if divisions < 2 { return; }
+        let step = length / divisions as f32;
+        let mut extra = Vec::new();
+        for p in self.iter().filter(|p| p.3 > 0.0 && selector(p)){
+            for i in 1..divisions{
+                let decay = 1.0 - i as f32 / divisions as f32;
+                extra.push((p.0, p.1 + step * i as f32, p.2, (p.3 * decay).max(MIN_VELOCITY)));
             }
-            else if let Some(NoteOff(_, note, _)) = ev {
-                floww.push((note as usize, time, note as f32, 0.0));
+        }
+        self.merge(extra);
+    }
+
+    // ids missing from the map are kept
+    fn remap_ids(&mut self, map: &HashMap<usize, usize>){
+        self.map_ids(|id| *map.get(&id).unwrap_or(&id));
+    }
+
+    fn map_ids<F: Fn(usize) -> usize>(&mut self, f: F){
+        self.iter_mut().for_each(|p| p.0 = f(p.0));
+    }
+
+    // also removes the matching note offs so nothing is left dangling
+    fn remove_below_velocity(&mut self, threshold: f32){
+        let mut remove = self.iter().map(|p| p.3 > 0.0 && p.3 < threshold).collect::<Vec<_>>();
+        for (on, off) in note_spans(self){
+            if remove[on] {
+                remove[off] = true;
             }
-            else if let Some(SetTempo(t)) = ev {
-                time_mult = t as f32 / 1_000_000.0;
+        }
+        retain_unmarked(self, &remove);
+    }
+
+    fn boost_below_velocity(&mut self, threshold: f32, floor: f32){
+        self.iter_mut().filter(|p| p.3 > 0.0 && p.3 < threshold).for_each(|p| p.3 = floor);
+    }
+
+    // note ons are kept with the given probability, their note offs follow them
+    fn thin(&mut self, keep_probability: f32, seed: u64){
+        self.thin_steps(f32::INFINITY, &[keep_probability], seed);
+    }
+
+    // the probability of a note on is the one of its nearest grid step, repeating over the slice
+    fn thin_steps(&mut self, grid: f32, keep_probabilities: &[f32], seed: u64){
+        if keep_probabilities.is_empty() { return; }
+        let mut rng = Rng::new(seed);
+        let n = keep_probabilities.len() as i64;
+        let mut remove = self.iter().map(|p| {
+            if p.3 <= 0.0 { return false; }
+            let step = if grid.is_finite() && grid > 0.0 {
+                ((p.1 / grid).round() as i64).rem_euclid(n) as usize
+            } else {
+                0
+            };
+            rng.next_f32() >= keep_probabilities[step]
+        }).collect::<Vec<_>>();
+        for (on, off) in note_spans(self){
+            remove[off] = remove[on];
+        }
+        retain_unmarked(self, &remove);
+    }
+
+    // one grace hit offset before every selected note on
+    fn add_flams<F: Fn(&Point) -> bool>(&mut self, selector: F, offset: f32, vel_ratio: f32){
+        add_grace_notes(self, selector, 1, offset, vel_ratio);
+    }
+
+    // two grace hits, offset apart, before every selected note on
+    fn add_drags<F: Fn(&Point) -> bool>(&mut self, selector: F, offset: f32, vel_ratio: f32){
+        add_grace_notes(self, selector, 2, offset, vel_ratio);
+    }
+
+    // points of other that coincide with a point of self (same id, of the same on/off kind, within
+    // epsilon) replace it when prefer_other(ours, theirs) says so and are dropped otherwise
+    fn merge_prioritized<F: Fn(&Point, &Point) -> bool>(&mut self, other: Self, epsilon: f32, prefer_other: F){
+        let mut extra = Vec::new();
+        for q in other{
+            let hit = self.iter().position(|p| p.0 == q.0 && (p.3 > 0.0) == (q.3 > 0.0) && (p.1 - q.1).abs() <= epsilon);
+            match hit{
+                Some(i) => if prefer_other(&self[i], &q) {
+                    self[i] = q;
+                },
+                None => extra.push(q),
             }
         }
+        self.merge(extra);
     }
-    floww
-}
 
-pub fn read_floww_from_midi(path: &str) -> Result<Floww, ApresError>{
-    match MIDI::from_path(path){
-        Ok(midi) => { Ok(midi_to_floww(midi)) },
-        Err(e) => Err(e),
+    fn humanized(mut self, time_jitter: f32, vel_jitter: f32, seed: u64) -> Self{
+        self.humanize(time_jitter, vel_jitter, seed);
+        self
+    }
+
+    fn velocity_shaped(mut self, curve: VelocityCurve) -> Self{
+        self.shape_velocity(curve);
+        self
+    }
+
+    fn notes_inverted(mut self, pivot: f32) -> Self{
+        self.invert_notes(pivot);
+        self
+    }
+
+    fn ratcheted<F: Fn(&Point) -> bool>(mut self, selector: F, divisions: usize, length: f32) -> Self{
+        self.ratchet(selector, divisions, length);
+        self
+    }
+
+    fn ids_remapped(mut self, map: &HashMap<usize, usize>) -> Self{
+        self.remap_ids(map);
+        self
+    }
+
+    fn removed_below_velocity(mut self, threshold: f32) -> Self{
+        self.remove_below_velocity(threshold);
+        self
+    }
+
+    fn thinned(mut self, keep_probability: f32, seed: u64) -> Self{
+        self.thin(keep_probability, seed);
+        self
+    }
+
+    // other starts overlap before the end of self, velocities fade out and in linearly across the seam
+    fn fuse_with_overlap(mut self, other: Self, overlap: f32) -> Self{
+        let last_t = if let Some(p) = self.last(){
+            p.1
+        } else {
+            return other;
+        };
+        if overlap <= 0.0 { return self.fused(other); }
+        let seam = (last_t - overlap).max(0.0);
+        let fade = |p: &mut Point, gain: f32| if p.3 > 0.0 {
+            p.3 = (p.3 * gain.clamp(0.0, 1.0)).max(MIN_VELOCITY);
+        };
+        self.iter_mut().filter(|p| p.1 > seam).for_each(|p| fade(p, (last_t - p.1) / overlap));
+        let mut other = other;
+        other.iter_mut().for_each(|p| {
+            p.1 += seam;
+            if p.1 < seam + overlap {
+                fade(p, (p.1 - seam) / overlap);
+            }
+        });
+        self.merged(other)
     }
 }
 
-#[derive(Clone,PartialEq,Debug,Serialize, Deserialize)]
-pub enum FlowwPacket{
-    Msg(String),
-    Track(String),
-    Point(Point),
+// what to do when a merged sheet has a track name that already exists
+#[derive(Clone,Copy,PartialEq,Eq,Debug)]
+pub enum ConflictPolicy{
+    // the points are merged into the existing track
+    Merge,
+    // the incoming track is added as name_2, name_3, ..
+    Rename,
+    KeepOurs,
+    TakeTheirs,
 }
 
-pub trait IntoFlowwPacket{
-    fn into_packet(self) -> FlowwPacket;
+// session state of a track, a soloed track is heard even when muted
+#[derive(Clone,PartialEq,Debug,Serialize,Deserialize)]
+pub struct TrackMeta{
+    pub mute: bool,
+    pub solo: bool,
+    pub gain: f32,
+    pub color: Option<(u8, u8, u8)>,
+    pub tags: Vec<String>,
+    // added to every time when rendering, compensates input latency without touching the points
+    pub offset: f32,
 }
 
-impl IntoFlowwPacket for Point{
-    fn into_packet(self) -> FlowwPacket{
-        FlowwPacket::Point(self)
+impl TrackMeta{
+    fn is_audible(&self, any_solo: bool) -> bool{
+        if any_solo { self.solo } else { !self.mute }
     }
 }
 
-pub trait IntoFlowwPackets{
-    fn into_packets(self) -> Vec<FlowwPacket>;
+impl Default for TrackMeta{
+    fn default() -> Self{
+        Self{ mute: false, solo: false, gain: 1.0, color: None, tags: Vec::new(), offset: 0.0 }
+    }
 }
 
-impl<T: IntoFlowwPacket> IntoFlowwPackets for Vec<T>{
-    fn into_packets(self) -> Vec<FlowwPacket>{
-        self.into_iter().map(|i| i.into_packet()).collect::<Vec<_>>()
-    }
+// a frozen copy of a sheet, tracks are shared with the sheet until either side edits them
+#[derive(Clone)]
+pub struct SheetSnapshot{
+    sheet: FlowwSheet,
 }
 
-pub trait Encodable{
-    fn encode(&self) -> Vec<u8>;
-    fn encoded(self) -> Vec<u8>;
+#[derive(Clone,Default)]
+pub struct FlowwSheet{
+    // shared with snapshots, written through Arc::make_mut so only edited tracks get copied
+    flowws: Vec<Arc<Floww>>,
+    names: Vec<String>,
+    metas: Vec<TrackMeta>,
+    map: HashMap<String, usize>,
+    groups: HashMap<String, Vec<String>>,
+    // ids learned from the packets unpacketed into this sheet
+    track_ids: TrackIds,
 }
 
-impl Encodable for Vec<FlowwPacket>{
-    fn encode(&self) -> Vec<u8>{
-        bincode::serialize(self).unwrap()
+impl FlowwSheet{
+    pub fn new() -> Self{
+        Self::default()
     }
 
-    fn encoded(self) -> Vec<u8>{
-        self.encode()
+    pub fn add(&mut self, floww: Floww, name: String){
+        self.add_with_meta(floww, name, TrackMeta::default());
     }
-}
-
-pub trait DecodeIntoFlowwPackets{
-    fn decoded(self) -> Result<Vec<FlowwPacket>, Box<ErrorKind>>;
-}
 
-impl<T: Read> DecodeIntoFlowwPackets for T{
-    fn decoded(self) -> Result<Vec<FlowwPacket>, Box<ErrorKind>>{
-        bincode::deserialize_from(self)
+    pub fn add_with_meta(&mut self, floww: Floww, name: String, meta: TrackMeta){
+        let index = self.flowws.len();
+        self.flowws.push(Arc::new(floww));
+        self.metas.push(meta);
+        self.map.insert(name.clone(), index);
+        self.names.push(name);
     }
-}
 
-pub fn unpacket(flowws: &mut Vec<Floww>, map: &HashMap<String, usize>, packets: Vec<FlowwPacket>) -> Vec<String>{
-    let mut current = 0;
-    let mut messages = Vec::new();
-    for packet in packets{
-        match packet{
-            FlowwPacket::Msg(msg) => {
-                messages.push(msg);
-            },
-            FlowwPacket::Track(name) => {
-                current = if let Some(index) = map.get(&name){
-                    *index
-                } else {
-                    std::usize::MAX
-                };
-            },
-            FlowwPacket::Point(point) => {
-                if current == std::usize::MAX { continue; }
-                if current >= flowws.len() { continue; }
-                flowws[current].push(point);
-            },
+    pub fn get_floww_ref_by_name(&self, name: &str) -> &[Point]{
+        if let Some(index) = self.map.get(name){
+            &self.flowws[*index]
+        } else {
+            &[]
         }
     }
-    messages
-}
 
-#[cfg(test)]
-mod tests {
-    use crate::*;
-    #[test]
-    fn it_works(){
-        assert_eq!(2 + 2, 4);
+    pub fn get_floww_mut_by_name(&mut self, name: &str) -> Option<&mut Floww>{
+        let index = self.map.get(name)?;
+        self.flowws.get_mut(*index).map(Arc::make_mut)
     }
 
-    #[test]
-    fn encode_decode(){
-        let mut tracks = vec![vec![], vec![], vec![]];
+    pub fn len(&self) -> usize{
+        self.flowws.len()
+    }
+
+    pub fn is_empty(&self) -> bool{
+        self.flowws.is_empty()
+    }
+
+    pub fn contains(&self, name: &str) -> bool{
+        self.map.contains_key(name)
+    }
+
+    pub fn get_by_index(&self, index: usize) -> Option<(&str, &Floww)>{
+        Some((self.names.get(index)?.as_str(), &**self.flowws.get(index)?))
+    }
+
+    // points the sender puts on from go to the track to of this sheet
+    pub fn alias_track(&mut self, from: &str, to: &str){
+        self.track_ids.alias(from.to_string(), to.to_string());
+    }
+
+    // receives packets into the tracks of the sheet, created tracks get default metadata
+    pub fn unpacket(&mut self, packets: Vec<FlowwPacket>, policy: UnknownTrackPolicy) -> Unpacked{
+        let packets = self.track_ids.resolve(packets);
+        // received points go to a scratch copy first so untouched tracks stay shared
+        let mut incoming = vec![Vec::new(); self.flowws.len()];
+        let scratch_policy = if policy == UnknownTrackPolicy::Create { UnknownTrackPolicy::Collect } else { policy };
+        let mut res = unpacket_with(&mut incoming, &mut self.map, packets, scratch_policy);
+        // the scratch copies were cleared as well, so only what came after the clear is left in them
+        for name in &res.cleared{
+            if let Some(index) = self.map.get(name){
+                self.flowws[*index] = Arc::new(Vec::new());
+            }
+        }
+        for (floww, points) in self.flowws.iter_mut().zip(incoming){
+            if !points.is_empty() {
+                Arc::make_mut(floww).extend(points);
+            }
+        }
+        if policy != UnknownTrackPolicy::Create { return res; }
+        for (name, floww) in std::mem::take(&mut res.collected){
+            self.add(floww, name);
+        }
+        res
+    }
+
+    pub fn names(&self) -> &[String]{
+        &self.names
+    }
+
+    pub fn get_names(&self) -> Vec<String>{
+        self.names.clone()
+    }
+
+    pub fn remove(&mut self, name: &str) -> Option<Floww>{
+        let index = self.map.remove(name)?;
+        self.names.remove(index);
+        self.metas.remove(index);
+        let floww = self.flowws.remove(index);
+        self.groups.values_mut().for_each(|members| members.retain(|m| m != name));
+        self.rebuild_map();
+        Some(unshared(floww))
+    }
+
+    // fails when old doesn't exist or new is taken by another track
+    pub fn rename(&mut self, old: &str, new: &str) -> bool{
+        let index = if let Some(index) = self.map.get(old){
+            *index
+        } else {
+            return false;
+        };
+        if old == new { return true; }
+        if self.map.contains_key(new) { return false; }
+        self.map.remove(old);
+        self.map.insert(new.to_string(), index);
+        self.names[index] = new.to_string();
+        for members in self.groups.values_mut(){
+            members.iter_mut().filter(|m| *m == old).for_each(|m| *m = new.to_string());
+        }
+        true
+    }
+
+    pub fn merge(&mut self, other: FlowwSheet, conflict: ConflictPolicy){
+        let tracks = other.flowws.into_iter().zip(other.names).zip(other.metas);
+        for ((floww, name), meta) in tracks{
+            let index = if let Some(index) = self.map.get(&name){
+                *index
+            } else {
+                self.add_with_meta(unshared(floww), name, meta);
+                continue;
+            };
+            match conflict{
+                ConflictPolicy::Merge => Arc::make_mut(&mut self.flowws[index]).merge(unshared(floww)),
+                ConflictPolicy::Rename => {
+                    let mut i = 2;
+                    while self.map.contains_key(&format!("{}_{}", name, i)){
+                        i += 1;
+                    }
+                    self.add_with_meta(unshared(floww), format!("{}_{}", name, i), meta);
+                },
+                ConflictPolicy::KeepOurs => {},
+                ConflictPolicy::TakeTheirs => {
+                    self.flowws[index] = floww;
+                    self.metas[index] = meta;
+                },
+            }
+        }
+    }
+
+    // new_index past the end moves the track to the back
+    pub fn move_track(&mut self, name: &str, new_index: usize) -> bool{
+        let index = if let Some(index) = self.map.get(name){
+            *index
+        } else {
+            return false;
+        };
+        let new_index = new_index.min(self.flowws.len() - 1);
+        let floww = self.flowws.remove(index);
+        let name = self.names.remove(index);
+        let meta = self.metas.remove(index);
+        self.flowws.insert(new_index, floww);
+        self.names.insert(new_index, name);
+        self.metas.insert(new_index, meta);
+        self.rebuild_map();
+        true
+    }
+
+    pub fn sort_tracks_by_name(&mut self){
+        let mut tracks = std::mem::take(&mut self.names).into_iter()
+            .zip(std::mem::take(&mut self.flowws))
+            .zip(std::mem::take(&mut self.metas)).collect::<Vec<_>>();
+        tracks.sort_by(|a, b| (a.0).0.cmp(&(b.0).0));
+        for ((name, floww), meta) in tracks{
+            self.names.push(name);
+            self.flowws.push(floww);
+            self.metas.push(meta);
+        }
+        self.rebuild_map();
+    }
+
+    // later tracks win when names are shared, like add does
+    fn rebuild_map(&mut self){
+        self.map.clear();
+        for (i, name) in self.names.iter().enumerate(){
+            self.map.insert(name.clone(), i);
+        }
+    }
+
+    pub fn reset(&mut self, name: &str, new: Floww) -> bool{
+        if let Some(index) = self.map.get(name){
+            self.flowws[*index] = Arc::new(new);
+            true
+        } else {
+            false
+        }
+    }
+
+    pub fn clear_track(&mut self, name: &str) -> bool{
+        if let Some(index) = self.map.get(name){
+            self.flowws[*index] = Arc::new(Vec::new());
+            true
+        } else {
+            false
+        }
+    }
+
+    // drops the points before before_time, for receivers discarding what was already played
+    pub fn truncate_track(&mut self, name: &str, before_time: f32) -> bool{
+        if let Some(floww) = self.get_floww_mut_by_name(name){
+            floww.retain(|p| p.1 >= before_time);
+            true
+        } else {
+            false
+        }
+    }
+
+    // cheap: only names and metadata are copied, for undo stacks and scene recall
+    pub fn snapshot(&self) -> SheetSnapshot{
+        SheetSnapshot{ sheet: self.clone() }
+    }
+
+    pub fn restore(&mut self, snapshot: &SheetSnapshot){
+        *self = snapshot.sheet.clone();
+    }
+
+    pub fn get_meta(&self, name: &str) -> Option<&TrackMeta>{
+        self.metas.get(*self.map.get(name)?)
+    }
+
+    pub fn get_meta_mut(&mut self, name: &str) -> Option<&mut TrackMeta>{
+        self.metas.get_mut(*self.map.get(name)?)
+    }
+
+    pub fn set_mute(&mut self, name: &str, mute: bool) -> bool{
+        self.get_meta_mut(name).map(|m| m.mute = mute).is_some()
+    }
+
+    pub fn set_solo(&mut self, name: &str, solo: bool) -> bool{
+        self.get_meta_mut(name).map(|m| m.solo = solo).is_some()
+    }
+
+    pub fn set_gain(&mut self, name: &str, gain: f32) -> bool{
+        self.get_meta_mut(name).map(|m| m.gain = gain).is_some()
+    }
+
+    pub fn set_color(&mut self, name: &str, color: Option<(u8, u8, u8)>) -> bool{
+        self.get_meta_mut(name).map(|m| m.color = color).is_some()
+    }
+
+    // with any track soloed only soloed tracks are audible, otherwise all unmuted ones
+    // false when the track doesn't exist, tagging twice keeps one tag
+    pub fn add_tag(&mut self, name: &str, tag: &str) -> bool{
+        let meta = if let Some(meta) = self.get_meta_mut(name){
+            meta
+        } else {
+            return false;
+        };
+        if !meta.tags.iter().any(|t| t == tag) {
+            meta.tags.push(tag.to_string());
+        }
+        true
+    }
+
+    pub fn remove_tag(&mut self, name: &str, tag: &str) -> bool{
+        if let Some(meta) = self.get_meta_mut(name){
+            let l = meta.tags.len();
+            meta.tags.retain(|t| t != tag);
+            meta.tags.len() != l
+        } else {
+            false
+        }
+    }
+
+    pub fn tracks_with_tag(&self, tag: &str) -> Vec<&str>{
+        self.names.iter().zip(self.metas.iter()).filter(|(_, m)| m.tags.iter().any(|t| t == tag))
+            .map(|(n, _)| n.as_str()).collect()
+    }
+
+    pub fn set_offset(&mut self, name: &str, offset: f32) -> bool{
+        self.get_meta_mut(name).map(|m| m.offset = offset).is_some()
+    }
+
+    // the track as it should sound, the offset is applied like shift_time so it stops at zero
+    pub fn rendered(&self, name: &str) -> Floww{
+        let offset = self.get_meta(name).map(|m| m.offset).unwrap_or(0.0);
+        self.get_floww_ref_by_name(name).to_vec().time_shifted(offset)
+    }
+
+    pub fn is_audible(&self, name: &str) -> bool{
+        let meta = if let Some(meta) = self.get_meta(name){
+            meta
+        } else {
+            return false;
+        };
+        meta.is_audible(self.metas.iter().any(|m| m.solo))
+    }
+
+    // (re)defines a group, names that aren't tracks are left out, removed tracks leave their groups
+    pub fn group(&mut self, name: &str, members: &[&str]){
+        let members = members.iter().filter(|m| self.map.contains_key(**m)).map(|m| m.to_string()).collect();
+        self.groups.insert(name.to_string(), members);
+    }
+
+    pub fn ungroup(&mut self, name: &str) -> bool{
+        self.groups.remove(name).is_some()
+    }
+
+    pub fn group_members(&self, name: &str) -> Option<&[String]>{
+        self.groups.get(name).map(|m| m.as_slice())
+    }
+
+    fn group_indices(&self, name: &str) -> Vec<usize>{
+        self.groups.get(name).map(|members| members.iter().filter_map(|m| self.map.get(m).copied()).collect())
+            .unwrap_or_default()
+    }
+
+    pub fn shift_group(&mut self, name: &str, t: f32) -> bool{
+        if !self.groups.contains_key(name) { return false; }
+        for i in self.group_indices(name){
+            Arc::make_mut(&mut self.flowws[i]).shift_time(t);
+        }
+        true
+    }
+
+    pub fn scale_group(&mut self, name: &str, factor: f32) -> bool{
+        if !self.groups.contains_key(name) { return false; }
+        for i in self.group_indices(name){
+            Arc::make_mut(&mut self.flowws[i]).scale(factor);
+        }
+        true
+    }
+
+    pub fn mute_group(&mut self, name: &str, mute: bool) -> bool{
+        if !self.groups.contains_key(name) { return false; }
+        for i in self.group_indices(name){
+            self.metas[i].mute = mute;
+        }
+        true
+    }
+
+    pub fn shift_all(&mut self, t: f32){
+        self.flowws.iter_mut().for_each(|f| Arc::make_mut(f).shift_time(t));
+    }
+
+    pub fn scale_all(&mut self, factor: f32){
+        self.flowws.iter_mut().for_each(|f| Arc::make_mut(f).scale(factor));
+    }
+
+    pub fn quantize_all(&mut self, grid: f32){
+        self.flowws.iter_mut().for_each(|f| Arc::make_mut(f).quantize(grid));
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &Floww)>{
+        self.names.iter().map(|n| n.as_str()).zip(self.flowws.iter().map(|f| &**f))
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (&str, &mut Floww)>{
+        self.names.iter().map(|n| n.as_str()).zip(self.flowws.iter_mut().map(Arc::make_mut))
+    }
+
+    // inverse of to_floww_packets, points before the first track header are dropped and
+    // a repeated header continues the existing track, messages and transport are ignored
+    pub fn from_packets(packets: Vec<FlowwPacket>) -> Self{
+        let mut sheet = Self::new();
+        let mut current = None;
+        for packet in packets{
+            match packet{
+                FlowwPacket::Track(name) => {
+                    if !sheet.map.contains_key(&name) {
+                        sheet.add(Vec::new(), name.clone());
+                    }
+                    current = sheet.map.get(&name).copied();
+                },
+                FlowwPacket::Point(point) | FlowwPacket::RichPoint(RichPoint{ point, .. }) => {
+                    if let Some(index) = current{
+                        Arc::make_mut(&mut sheet.flowws[index]).push(point);
+                    }
+                },
+                FlowwPacket::Points(points) => {
+                    if let Some(index) = current{
+                        Arc::make_mut(&mut sheet.flowws[index]).extend(points);
+                    }
+                },
+                FlowwPacket::Clear(name) => {
+                    if let Some(floww) = sheet.get_floww_mut_by_name(&name){
+                        floww.clear();
+                    }
+                },
+                FlowwPacket::Deltas(deltas) => {
+                    if let Some(index) = current{
+                        Arc::make_mut(&mut sheet.flowws[index]).extend(deltas.decode());
+                    }
+                },
+                FlowwPacket::ClearAll => sheet.flowws.iter_mut().for_each(|f| *f = Arc::new(Vec::new())),
+                _ => {},
+            }
+        }
+        sheet
+    }
+
+    pub fn to_floww_packets(self) -> Vec<FlowwPacket>{
+        let mut res = Vec::new();
+        for (floww, name) in self.flowws.into_iter().zip(self.names){
+            res.push(FlowwPacket::Track(name));
+            for point in floww.iter(){
+                res.push(FlowwPacket::Point(*point));
+            }
+        }
+        res
+    }
+
+    // like to_floww_packets but every track is sent as one Points packet
+    pub fn to_bulk_packets(self) -> Vec<FlowwPacket>{
+        let mut res = Vec::new();
+        for (floww, name) in self.flowws.into_iter().zip(self.names){
+            res.push(FlowwPacket::Track(name));
+            res.push(FlowwPacket::Points(unshared(floww)));
+        }
+        res
+    }
+
+    // like to_bulk_packets in the compact encoding of delta_packets, times are rounded to tick
+    pub fn to_delta_packets(self, tick: f32) -> Vec<FlowwPacket>{
+        let mut res = Vec::new();
+        for (floww, name) in self.flowws.iter().zip(self.names){
+            res.push(FlowwPacket::Track(name));
+            res.extend(delta_packets(floww, tick));
+        }
+        res
+    }
+
+    // like to_floww_packets but leaves out tracks that mute and solo silence
+    pub fn to_audible_floww_packets(self) -> Vec<FlowwPacket>{
+        let any_solo = self.metas.iter().any(|m| m.solo);
+        let mut res = Vec::new();
+        let tracks = self.flowws.into_iter().zip(self.names).zip(self.metas);
+        for ((floww, name), meta) in tracks{
+            if !meta.is_audible(any_solo) { continue; }
+            res.push(FlowwPacket::Track(name));
+            for point in floww.iter(){
+                res.push(FlowwPacket::Point(*point));
+            }
+        }
+        res
+    }
+
+    // every .mid or .midi file becomes a track named after the file, sorted by name
+    pub fn from_midi_dir<P: AsRef<std::path::Path>>(path: P) -> Result<FlowwSheet, ApresError>{
+        let path = path.as_ref();
+        let mut files = std::fs::read_dir(path).map_err(|_| midi_dir_error(path))?
+            .filter_map(|e| e.ok().map(|e| e.path()))
+            .filter(|p| p.is_file() && p.extension().map(|e| {
+                let e = e.to_string_lossy().to_lowercase();
+                e == "mid" || e == "midi"
+            }).unwrap_or(false))
+            .collect::<Vec<_>>();
+        files.sort();
+        let mut sheet = FlowwSheet::new();
+        for file in files{
+            let name = file.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+            let floww = read_floww_from_midi(&file.to_string_lossy())?;
+            if sheet.contains(&name) {
+                sheet.get_floww_mut_by_name(&name).unwrap().merge(floww);
+            } else {
+                sheet.add(floww, name);
+            }
+        }
+        Ok(sheet)
+    }
+
+    // writes name.mid per track, path separators in names become underscores, nothing is written
+    // when two names end up as the same file
+    pub fn export_midi_dir<P: AsRef<std::path::Path>>(&self, path: P) -> Result<(), ApresError>{
+        let path = path.as_ref();
+        let mut files = Vec::new();
+        for (name, floww) in self.iter(){
+            let file = path.join(format!("{}.mid", name.replace(['/', '\\'], "_")));
+            if files.iter().any(|(f, _)| f == &file) {
+                return Err(midi_dir_error(&file));
+            }
+            files.push((file, floww));
+        }
+        std::fs::create_dir_all(path).map_err(|_| midi_dir_error(path))?;
+        for (file, floww) in files{
+            std::fs::write(&file, midi_bytes(&floww_to_midi(floww))).map_err(|_| midi_dir_error(&file))?;
+        }
+        Ok(())
+    }
+
+    pub fn serialize(self) -> Result<Vec<u8>, Box<bincode::ErrorKind>>{
+        let x = bincode::serialize(&self.flowws.iter().map(|f| f.as_slice()).collect::<Vec<_>>())?;
+        let y = bincode::serialize(&self.names)?;
+        Ok(x.conc(y))
+    }
+}
+
+// panics when there is no track with that name, use get_floww_ref_by_name to probe
+impl std::ops::Index<&str> for FlowwSheet{
+    type Output = Floww;
+
+    fn index(&self, name: &str) -> &Floww{
+        match self.map.get(name){
+            Some(index) => &self.flowws[*index],
+            None => panic!("floww: no track named {}", name),
+        }
+    }
+}
+
+fn unshared(floww: Arc<Floww>) -> Floww{
+    Arc::try_unwrap(floww).unwrap_or_else(|f| (*f).clone())
+}
+
+pub fn split_by_id(floww: Floww) -> HashMap<usize, Floww>{
+    let mut map: HashMap<usize, Floww> = HashMap::new();
+    for point in floww{
+        map.entry(point.0).or_default().push(point);
+    }
+    map
+}
+
+pub fn merge_ids(map: HashMap<usize, Floww>) -> Floww{
+    let mut lanes = map.into_iter().collect::<Vec<_>>();
+    lanes.sort_by_key(|(id, _)| *id);
+    let mut floww = Vec::new();
+    for (_, lane) in lanes{
+        floww.extend(lane);
+    }
+    floww.sorted()
+}
+
+// bars start at zero, times inside a bar are relative to its start, points before zero are dropped
+pub fn slice_bars(floww: &[Point], beats_per_bar: usize, beat_len: f32) -> Vec<Floww>{
+    let bar_len = beats_per_bar as f32 * beat_len;
+    let floww = floww.iter().filter(|p| p.1 >= 0.0).copied().collect::<Floww>().sorted();
+    floww.chunks_by_interval(bar_len).enumerate().map(|(i, bar)| {
+        let start = i as f32 * bar_len;
+        bar.iter().map(|p| (p.0, p.1 - start, p.2, p.3)).collect()
+    }).collect()
+}
+
+pub fn join_bars(bars: Vec<Floww>, beats_per_bar: usize, beat_len: f32) -> Floww{
+    let bar_len = beats_per_bar as f32 * beat_len;
+    let mut floww = Vec::new();
+    for (i, bar) in bars.into_iter().enumerate(){
+        let start = i as f32 * bar_len;
+        floww.extend(bar.into_iter().map(|p| (p.0, p.1 + start, p.2, p.3)));
+    }
+    floww.sorted()
+}
+
+pub fn midi_to_floww(midi: MIDI) -> Floww{
+    let ppqn = midi.get_ppqn() as f32;
+    let mut time_mult = 1.0; // 60bpm per default
+    let mut floww = Vec::new();
+    for track in midi.get_tracks(){
+        let mut time = 0.0;
+        for (tick, id) in track{
+            time += tick as f32 / ppqn * time_mult;
+            let ev = midi.get_event(id);
+            if let Some(NoteOn(_, note, vel)) = ev {
+                floww.push((note as usize, time, note as f32, vel as f32 / 127.0));
+            }
+            else if let Some(NoteOff(_, note, _)) = ev {
+                floww.push((note as usize, time, note as f32, 0.0));
+            }
+            else if let Some(SetTempo(t)) = ev {
+                time_mult = t as f32 / 1_000_000.0;
+            }
+        }
+    }
+    floww
+}
+
+pub fn read_floww_from_midi(path: &str) -> Result<Floww, ApresError>{
+    match MIDI::from_path(path){
+        Ok(midi) => { Ok(midi_to_floww(midi)) },
+        Err(e) => Err(e),
+    }
+}
+
+// inverse of midi_to_floww on a single track at 60bpm, ids are dropped since midi_to_floww
+// uses the note as id, times before zero are clamped to zero
+pub fn floww_to_midi(floww: &[Point]) -> MIDI{
+    let mut midi = MIDI::new();
+    let ppqn = midi.get_ppqn() as f32;
+    let mut floww = floww.to_vec();
+    crate::notes::sort_offs_first(&mut floww);
+    for p in floww{
+        let tick = (p.1.max(0.0) * ppqn).round() as usize;
+        let note = p.2.round().clamp(0.0, 127.0) as u8;
+        let vel = (p.3 * 127.0).round().clamp(0.0, 127.0) as u8;
+        let ev = if vel == 0 { NoteOff(0, note, 0) } else { NoteOn(0, note, vel) };
+        midi.insert_event(0, tick, ev);
+    }
+    midi
+}
+
+// a raw note on or note off message, for outputs that speak midi directly
+pub fn point_to_midi_bytes(point: &Point, channel: u8) -> [u8; 3]{
+    let note = point.2.round().clamp(0.0, 127.0) as u8;
+    let vel = (point.3 * 127.0).round().clamp(0.0, 127.0) as u8;
+    let status = if vel == 0 { 0x80 } else { 0x90 };
+    [status | (channel & 0x0f), note, vel]
+}
+
+pub fn write_floww_to_midi(floww: &[Point], path: &str){
+    floww_to_midi(floww).save(path);
+}
+
+// the bytes MIDI::save writes, which keeps them to itself and drops write errors
+fn midi_bytes(midi: &MIDI) -> Vec<u8>{
+    let mut res = b"MThd\0\0\0\x06".to_vec();
+    for word in [midi.get_format(), midi.count_tracks() as u16, midi.get_ppqn()]{
+        res.extend(word.to_be_bytes());
+    }
+    for track in midi.get_tracks(){
+        let mut bytes = Vec::new();
+        for (delay, id) in track{
+            if let Some(event) = midi.get_event(id){
+                push_variable_length(&mut bytes, delay);
+                bytes.extend(event.as_bytes());
+            }
+        }
+        bytes.push(0);
+        bytes.extend(apres::MIDIEvent::EndOfTrack.as_bytes());
+        res.extend(b"MTrk");
+        res.extend((bytes.len() as u32).to_be_bytes());
+        res.extend(bytes);
+    }
+    res
+}
+
+fn push_variable_length(bytes: &mut Vec<u8>, mut number: usize){
+    let mut groups = vec![(number & 0x7f) as u8];
+    number >>= 7;
+    while number > 0 {
+        groups.push((number & 0x7f) as u8 | 0x80);
+        number >>= 7;
+    }
+    bytes.extend(groups.iter().rev());
+}
+
+fn midi_dir_error(path: &std::path::Path) -> ApresError{
+    ApresError::PathNotFound(path.to_string_lossy().to_string())
+}
+
+// how many packets the streaming senders collect before they send on their own
+pub const DEFAULT_BATCH_SIZE: usize = 256;
+
+#[derive(Clone,PartialEq,Debug,Serialize, Deserialize)]
+pub enum ControlValue{
+    Int(i64),
+    Float(f32),
+    Bool(bool),
+    Text(String),
+}
+
+impl ControlValue{
+    // ints are converted, so a controller can send either
+    pub fn as_f32(&self) -> Option<f32>{
+        match self{
+            ControlValue::Int(x) => Some(*x as f32),
+            ControlValue::Float(x) => Some(*x),
+            _ => None,
+        }
+    }
+
+    // floats are only taken when they are whole
+    pub fn as_i64(&self) -> Option<i64>{
+        match self{
+            ControlValue::Int(x) => Some(*x),
+            ControlValue::Float(x) if x.fract() == 0.0 => Some(*x as i64),
+            _ => None,
+        }
+    }
+
+    pub fn as_bool(&self) -> Option<bool>{
+        if let ControlValue::Bool(b) = self { Some(*b) } else { None }
+    }
+
+    pub fn as_str(&self) -> Option<&str>{
+        if let ControlValue::Text(s) = self { Some(s) } else { None }
+    }
+}
+
+impl From<i64> for ControlValue{
+    fn from(x: i64) -> Self{
+        ControlValue::Int(x)
+    }
+}
+
+impl From<f32> for ControlValue{
+    fn from(x: f32) -> Self{
+        ControlValue::Float(x)
+    }
+}
+
+impl From<bool> for ControlValue{
+    fn from(b: bool) -> Self{
+        ControlValue::Bool(b)
+    }
+}
+
+impl From<&str> for ControlValue{
+    fn from(s: &str) -> Self{
+        ControlValue::Text(s.to_string())
+    }
+}
+
+impl From<String> for ControlValue{
+    fn from(s: String) -> Self{
+        ControlValue::Text(s)
+    }
+}
+
+pub const PARAM_PAN: u8 = 0;
+pub const PARAM_PROBABILITY: u8 = 1;
+pub const PARAM_ARTICULATION: u8 = 2;
+
+// a point with extra parameters, keys are free to use beyond the PARAM_ ones, consumers that
+// only know Point see the plain point and sheets keep only that
+#[derive(Clone,PartialEq,Debug,Serialize, Deserialize)]
+pub struct RichPoint{
+    #[serde(with = "wire::wire_point")]
+    pub point: Point,
+    pub params: Vec<(u8, f32)>,
+}
+
+impl RichPoint{
+    pub fn new(point: Point) -> Self{
+        Self{ point, params: Vec::new() }
+    }
+
+    // sets a parameter, replacing an earlier value of the same key
+    pub fn with(mut self, key: u8, value: f32) -> Self{
+        self.set(key, value);
+        self
+    }
+
+    pub fn set(&mut self, key: u8, value: f32){
+        if let Some(param) = self.params.iter_mut().find(|(k, _)| *k == key){
+            param.1 = value;
+        } else {
+            self.params.push((key, value));
+        }
+    }
+
+    pub fn param(&self, key: u8) -> Option<f32>{
+        self.params.iter().find(|(k, _)| *k == key).map(|(_, v)| *v)
+    }
+}
+
+#[derive(Clone,PartialEq,Debug,Serialize, Deserialize)]
+pub enum FlowwPacket{
+    // the position of a variant is its tag on the wire, new ones go at the end
+    Msg(String),
+    Track(String),
+    Point(#[serde(with = "wire::wire_point")] Point),
+    // transport control, Seek jumps the receiver while Position reports where the sender is
+    Start,
+    Stop,
+    Seek(f32),
+    Tempo(f32),
+    Position(f32),
+    // sent periodically with the id of the sender so receivers can tell it is still there
+    Heartbeat(String),
+    // a burst of points for the current track, the enum tag and length are paid once
+    Points(#[serde(with = "wire::wire_points")] Vec<Point>),
+    // registers a short id for a track name, after that TrackId switches tracks like Track does
+    DefineTrack(u32, String),
+    TrackId(u32),
+    // empties a track on the receiver, points that come after it in the stream are kept
+    Clear(String),
+    ClearAll,
+    // everything since the previous Flush is one consistent frame, see FrameBuffer
+    Flush,
+    // a named setting, typed so receivers don't have to parse it out of a Msg
+    Control{ key: String, value: ControlValue },
+    // the sender clock (seconds) when the packets after it were sent, see SenderClock
+    Timestamp(f32),
+    // the receiver puts what the sender calls from into its track to from now on
+    Alias{ from: String, to: String },
+    // a burst of points in the compact lossy encoding of delta_packets
+    Deltas(DeltaPoints),
+    RichPoint(RichPoint),
+}
+
+impl FlowwPacket{
+    pub fn is_transport(&self) -> bool{
+        matches!(self, FlowwPacket::Start | FlowwPacket::Stop | FlowwPacket::Seek(_)
+            | FlowwPacket::Tempo(_) | FlowwPacket::Position(_))
+    }
+
+    pub fn control<V: Into<ControlValue>>(key: &str, value: V) -> Self{
+        FlowwPacket::Control{ key: key.to_string(), value: value.into() }
+    }
+
+    pub fn as_control(&self) -> Option<(&str, &ControlValue)>{
+        if let FlowwPacket::Control{ key, value } = self { Some((key, value)) } else { None }
+    }
+
+    // the points a Point, Points or Deltas packet carries, empty for the others
+    pub fn points(&self) -> Cow<'_, [Point]>{
+        match self{
+            FlowwPacket::Point(point) => Cow::Borrowed(std::slice::from_ref(point)),
+            FlowwPacket::Points(points) => Cow::Borrowed(points),
+            FlowwPacket::Deltas(deltas) => Cow::Owned(deltas.decode()),
+            FlowwPacket::RichPoint(rich) => Cow::Borrowed(std::slice::from_ref(&rich.point)),
+            _ => Cow::Borrowed(&[]),
+        }
+    }
+
+    pub fn has_points(&self) -> bool{
+        matches!(self, FlowwPacket::Point(_) | FlowwPacket::Points(_) | FlowwPacket::Deltas(_) | FlowwPacket::RichPoint(_))
+    }
+
+    // how many points points would give, without decoding
+    pub fn point_count(&self) -> usize{
+        match self{
+            FlowwPacket::Point(_) | FlowwPacket::RichPoint(_) => 1,
+            FlowwPacket::Points(points) => points.len(),
+            FlowwPacket::Deltas(deltas) => deltas.len(),
+            _ => 0,
+        }
+    }
+}
+
+// both ends of track id interning, intern hands out ids in order of first use and defines every
+// id once, resolve learns the ids from the definitions and turns ids back into track names,
+// resolve also renames tracks that have an alias
+#[derive(Clone,PartialEq,Debug,Default)]
+pub struct TrackIds{
+    ids: HashMap<String, u32>,
+    names: HashMap<u32, String>,
+    aliases: HashMap<String, String>,
+    // an unknown id came in, its points are dropped until the next track that is known
+    lost: bool,
+}
+
+impl TrackIds{
+    pub fn new() -> Self{
+        Self::default()
+    }
+
+    pub fn id(&self, name: &str) -> Option<u32>{
+        self.ids.get(name).copied()
+    }
+
+    pub fn name(&self, id: u32) -> Option<&str>{
+        self.names.get(&id).map(|n| n.as_str())
+    }
+
+    pub fn define(&mut self, id: u32, name: String){
+        self.ids.insert(name.clone(), id);
+        self.names.insert(id, name);
+    }
+
+    // an alias to itself takes the alias away
+    pub fn alias(&mut self, from: String, to: String){
+        if from == to {
+            self.aliases.remove(&from);
+        } else {
+            self.aliases.insert(from, to);
+        }
+    }
+
+    // the name a track of the sender ends up under
+    pub fn canonical<'a>(&'a self, name: &'a str) -> &'a str{
+        self.aliases.get(name).map(|n| n.as_str()).unwrap_or(name)
+    }
+
+    // after a reconnect the other end has to be told the ids and aliases again
+    pub fn clear(&mut self){
+        self.ids.clear();
+        self.names.clear();
+        self.aliases.clear();
+        self.lost = false;
+    }
+
+    // Track packets become TrackId packets, preceded by a DefineTrack the first time
+    pub fn intern(&mut self, packets: Vec<FlowwPacket>) -> Vec<FlowwPacket>{
+        let mut res = Vec::with_capacity(packets.len());
+        for packet in packets{
+            match packet{
+                FlowwPacket::Track(name) => {
+                    let id = if let Some(id) = self.id(&name){
+                        id
+                    } else {
+                        let id = self.ids.len() as u32;
+                        res.push(FlowwPacket::DefineTrack(id, name.clone()));
+                        self.define(id, name);
+                        id
+                    };
+                    res.push(FlowwPacket::TrackId(id));
+                },
+                other => res.push(other),
+            }
+        }
+        res
+    }
+
+    // TrackId packets become Track packets, definitions are taken out, the points after an
+    // unknown id are dropped so they don't land on the previous track
+    pub fn resolve(&mut self, packets: Vec<FlowwPacket>) -> Vec<FlowwPacket>{
+        packets.into_iter().filter_map(|packet| match packet{
+            FlowwPacket::DefineTrack(id, name) => {
+                self.define(id, name);
+                None
+            },
+            FlowwPacket::Alias{ from, to } => {
+                self.alias(from, to);
+                None
+            },
+            FlowwPacket::TrackId(id) => {
+                let name = self.name(id).map(|n| self.canonical(n).to_string());
+                self.lost = name.is_none();
+                name.map(FlowwPacket::Track)
+            },
+            _ if self.lost && packet.has_points() => None,
+            FlowwPacket::Track(name) => {
+                self.lost = false;
+                Some(FlowwPacket::Track(self.canonical(&name).to_string()))
+            },
+            FlowwPacket::Clear(name) if self.aliases.contains_key(&name) => Some(FlowwPacket::Clear(self.canonical(&name).to_string())),
+            other => Some(other),
+        }).collect()
+    }
+}
+
+pub trait IntoFlowwPacket{
+    fn into_packet(self) -> FlowwPacket;
+}
+
+impl IntoFlowwPacket for Point{
+    fn into_packet(self) -> FlowwPacket{
+        FlowwPacket::Point(self)
+    }
+}
+
+impl IntoFlowwPacket for Floww{
+    fn into_packet(self) -> FlowwPacket{
+        FlowwPacket::Points(self)
+    }
+}
+
+pub trait IntoFlowwPackets{
+    fn into_packets(self) -> Vec<FlowwPacket>;
+}
+
+impl<T: IntoFlowwPacket> IntoFlowwPackets for Vec<T>{
+    fn into_packets(self) -> Vec<FlowwPacket>{
+        self.into_iter().map(|i| i.into_packet()).collect::<Vec<_>>()
+    }
+}
+
+pub trait Encodable{
+    fn encode(&self) -> Vec<u8>;
+    fn encoded(self) -> Vec<u8>;
+}
+
+impl Encodable for Vec<FlowwPacket>{
+    fn encode(&self) -> Vec<u8>{
+        bincode::serialize(self).unwrap()
+    }
+
+    fn encoded(self) -> Vec<u8>{
+        self.encode()
+    }
+}
+
+pub trait DecodeIntoFlowwPackets{
+    fn decoded(self) -> Result<Vec<FlowwPacket>, Box<ErrorKind>>;
+}
+
+impl<T: Read> DecodeIntoFlowwPackets for T{
+    fn decoded(self) -> Result<Vec<FlowwPacket>, Box<ErrorKind>>{
+        bincode::deserialize_from(self)
+    }
+}
+
+// what unpacket_report did with a batch, for keeping an eye on the health of a stream
+#[derive(Clone,PartialEq,Debug,Default)]
+pub struct UnpacketReport{
+    pub messages: Vec<String>,
+    // points written per track, points in front of the first track header count for the track at
+    // index 0, under an empty name when it isn't in the map
+    pub routed: Vec<(String, usize)>,
+    // points of unknown tracks or tracks mapped past the end of flowws
+    pub dropped: usize,
+    // every track header in the batch once, known or not
+    pub tracks: Vec<String>,
+}
+
+impl UnpacketReport{
+    pub fn routed_to(&self, track: &str) -> usize{
+        self.routed.iter().find(|(t, _)| t == track).map(|(_, n)| *n).unwrap_or(0)
+    }
+
+    pub fn total_routed(&self) -> usize{
+        self.routed.iter().map(|(_, n)| n).sum()
+    }
+}
+
+// transport packets are skipped, unpacket_with hands them back
+pub fn unpacket(flowws: &mut [Floww], map: &HashMap<String, usize>, packets: Vec<FlowwPacket>) -> Vec<String>{
+    unpacket_report(flowws, map, packets).messages
+}
+
+pub fn unpacket_report(flowws: &mut [Floww], map: &HashMap<String, usize>, packets: Vec<FlowwPacket>) -> UnpacketReport{
+    let mut report = UnpacketReport::default();
+    let first = map.iter().find(|(_, i)| **i == 0).map(|(name, _)| name.clone()).unwrap_or_default();
+    let mut current = Some((first, 0));
+    for packet in packets{
+        match packet{
+            FlowwPacket::Msg(msg) => {
+                report.messages.push(msg);
+            },
+            FlowwPacket::Track(name) => {
+                current = map.get(&name).map(|index| (name.clone(), *index));
+                if !report.tracks.contains(&name) {
+                    report.tracks.push(name);
+                }
+            },
+            packet if packet.has_points() => {
+                let n = packet.point_count();
+                let (name, floww) = match &current{
+                    Some((name, index)) if *index < flowws.len() => (name, &mut flowws[*index]),
+                    _ => {
+                        report.dropped += n;
+                        continue;
+                    },
+                };
+                match packet{
+                    FlowwPacket::Point(point) | FlowwPacket::RichPoint(RichPoint{ point, .. }) => floww.push(point),
+                    FlowwPacket::Points(points) => floww.extend(points),
+                    FlowwPacket::Deltas(deltas) => floww.extend(deltas.decode()),
+                    _ => {},
+                }
+                if let Some((_, count)) = report.routed.iter_mut().find(|(t, _)| t == name){
+                    *count += n;
+                } else {
+                    report.routed.push((name.clone(), n));
+                }
+            },
+            FlowwPacket::Clear(name) => {
+                if let Some(floww) = map.get(&name).and_then(|index| flowws.get_mut(*index)){
+                    floww.clear();
+                }
+            },
+            FlowwPacket::ClearAll => flowws.iter_mut().for_each(|f| f.clear()),
+            _ => {},
+        }
+    }
+    report
+}
+
+// why unpacket_strict refused a batch, every broken route is listed once
+#[derive(Clone,PartialEq,Debug,Default)]
+pub struct UnpacketError{
+    // track names that are not in the map
+    pub unknown_tracks: Vec<String>,
+    // indices the map points to that have no floww
+    pub out_of_range: Vec<usize>,
+    // points that would have been dropped
+    pub dropped: usize,
+}
+
+impl std::fmt::Display for UnpacketError{
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result{
+        write!(f, "{} points can't be routed, unknown tracks: {:?}, indices out of range: {:?}",
+            self.dropped, self.unknown_tracks, self.out_of_range)
+    }
+}
+
+impl std::error::Error for UnpacketError{}
+
+// like unpacket but nothing is written when a point has nowhere to go, points of unknown tracks
+// and tracks mapped past the end of flowws are the error
+pub fn unpacket_strict(flowws: &mut [Floww], map: &HashMap<String, usize>, packets: Vec<FlowwPacket>)
+    -> Result<Vec<String>, UnpacketError>{
+    let mut err = UnpacketError::default();
+    let mut current = Ok(0);
+    for packet in &packets{
+        match packet{
+            FlowwPacket::Track(name) => current = map.get(name).copied().ok_or(name),
+            packet if packet.has_points() => {
+                let n = packet.point_count();
+                match current{
+                    Ok(index) if index < flowws.len() => {},
+                    Ok(index) => {
+                        err.dropped += n;
+                        if !err.out_of_range.contains(&index) { err.out_of_range.push(index); }
+                    },
+                    Err(name) => {
+                        err.dropped += n;
+                        if !err.unknown_tracks.contains(name) { err.unknown_tracks.push(name.clone()); }
+                    },
+                }
+            },
+            _ => {},
+        }
+    }
+    if err.dropped > 0 { return Err(err); }
+    Ok(unpacket(flowws, map, packets))
+}
+
+// where points of a track that isn't in the map go
+#[derive(Clone,Copy,PartialEq,Eq,Debug)]
+pub enum UnknownTrackPolicy{
+    Drop,
+    // into the floww at this index
+    Overflow(usize),
+    // a new floww is appended and added to the map
+    Create,
+    // handed back to the caller per track name
+    Collect,
+}
+
+// everything unpacket_with doesn't put into the flowws, in the order it arrived
+#[derive(Clone,PartialEq,Debug,Default)]
+pub struct Unpacked{
+    pub messages: Vec<String>,
+    pub transport: Vec<FlowwPacket>,
+    // sender ids of heartbeat packets
+    pub heartbeats: Vec<String>,
+    pub controls: Vec<(String, ControlValue)>,
+    // sender times of timestamp packets
+    pub timestamps: Vec<f32>,
+    // points of unknown tracks under UnknownTrackPolicy::Collect
+    pub collected: Vec<(String, Floww)>,
+    // tracks that were emptied by Clear and ClearAll packets
+    pub cleared: Vec<String>,
+}
+
+impl Unpacked{
+    // the last value that came in for key
+    pub fn control(&self, key: &str) -> Option<&ControlValue>{
+        self.controls.iter().rev().find(|(k, _)| k == key).map(|(_, v)| v)
+    }
+}
+
+pub fn unpacket_with(flowws: &mut Vec<Floww>, map: &mut HashMap<String, usize>, packets: Vec<FlowwPacket>,
+    policy: UnknownTrackPolicy) -> Unpacked{
+    enum Target{ Index(usize), Collected(usize), Nowhere }
+    let mut current = Target::Index(0);
+    let mut res = Unpacked::default();
+    let collected = &mut res.collected;
+    for packet in packets{
+        match packet{
+            FlowwPacket::Msg(msg) => {
+                res.messages.push(msg);
+            },
+            FlowwPacket::Track(name) => {
+                current = if let Some(index) = map.get(&name){
+                    Target::Index(*index)
+                } else {
+                    match policy{
+                        UnknownTrackPolicy::Drop => Target::Nowhere,
+                        UnknownTrackPolicy::Overflow(index) => Target::Index(index),
+                        UnknownTrackPolicy::Create => {
+                            flowws.push(Vec::new());
+                            map.insert(name, flowws.len() - 1);
+                            Target::Index(flowws.len() - 1)
+                        },
+                        UnknownTrackPolicy::Collect => {
+                            if let Some(i) = collected.iter().position(|(n, _)| *n == name){
+                                Target::Collected(i)
+                            } else {
+                                collected.push((name, Vec::new()));
+                                Target::Collected(collected.len() - 1)
+                            }
+                        },
+                    }
+                };
+            },
+            FlowwPacket::Point(point) | FlowwPacket::RichPoint(RichPoint{ point, .. }) => {
+                match current{
+                    Target::Index(index) => if let Some(floww) = flowws.get_mut(index){
+                        floww.push(point);
+                    },
+                    Target::Collected(i) => collected[i].1.push(point),
+                    Target::Nowhere => {},
+                }
+            },
+            FlowwPacket::Points(points) => {
+                match current{
+                    Target::Index(index) => if let Some(floww) = flowws.get_mut(index){
+                        floww.extend(points);
+                    },
+                    Target::Collected(i) => collected[i].1.extend(points),
+                    Target::Nowhere => {},
+                }
+            },
+            FlowwPacket::Deltas(deltas) => {
+                match current{
+                    Target::Index(index) => if let Some(floww) = flowws.get_mut(index){
+                        floww.extend(deltas.decode());
+                    },
+                    Target::Collected(i) => collected[i].1.extend(deltas.decode()),
+                    Target::Nowhere => {},
+                }
+            },
+            FlowwPacket::Heartbeat(id) => res.heartbeats.push(id),
+            FlowwPacket::Control{ key, value } => res.controls.push((key, value)),
+            FlowwPacket::Timestamp(t) => res.timestamps.push(t),
+            FlowwPacket::Clear(name) => {
+                if let Some(floww) = map.get(&name).and_then(|index| flowws.get_mut(*index)){
+                    floww.clear();
+                }
+                if let Some((_, floww)) = collected.iter_mut().find(|(n, _)| *n == name){
+                    floww.clear();
+                }
+                if !res.cleared.contains(&name) {
+                    res.cleared.push(name);
+                }
+            },
+            FlowwPacket::ClearAll => {
+                flowws.iter_mut().for_each(|f| f.clear());
+                collected.iter_mut().for_each(|(_, f)| f.clear());
+                for name in map.keys().chain(collected.iter().map(|(n, _)| n)){
+                    if !res.cleared.contains(name) {
+                        res.cleared.push(name.clone());
+                    }
+                }
+            },
+            // these need the ids and aliases of earlier batches, TrackIds::resolve takes care of them first
+            FlowwPacket::DefineTrack(..) | FlowwPacket::TrackId(_) | FlowwPacket::Alias{ .. } => {},
+            // frames are put together before unpacketing
+            FlowwPacket::Flush => {},
+            transport => res.transport.push(transport),
+        }
+    }
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+    #[test]
+    fn it_works(){
+        assert_eq!(2 + 2, 4);
+    }
+
+    #[test]
+    fn encode_decode(){
+        let mut tracks = vec![vec![], vec![], vec![]];
         let map: HashMap<String, usize> = [
             ("snare".to_string(), 0),
             ("kick".to_string(), 1),
@@ -370,4 +2026,769 @@ mod tests {
         let j = i.encode().decoded().unwrap();
         assert_eq!(i, j);
     }
+
+    #[test]
+    fn points_between(){
+        let a = vec![(0, 0.0, 0.0, 0.0), (1, 0.5, 0.0, 0.0), (2, 1.0, 0.0, 0.0), (3, 1.5, 0.0, 0.0)];
+        assert_eq!(a.points_between(0.5, 1.5), &[(1, 0.5, 0.0, 0.0), (2, 1.0, 0.0, 0.0)]);
+        assert_eq!(a.points_between(-1.0, 0.1), &[(0, 0.0, 0.0, 0.0)]);
+        assert_eq!(a.points_between(2.0, 3.0), &[]);
+        assert_eq!(a.points_between(1.0, 0.0), &[]);
+    }
+
+    #[test]
+    fn split_merge_ids(){
+        let a = vec![(0, 0.0, 0.0, 1.0), (1, 0.0, 0.0, 1.0), (0, 0.5, 0.0, 1.0), (2, 1.0, 0.0, 1.0)];
+        let map = split_by_id(a.clone());
+        assert_eq!(map.len(), 3);
+        assert_eq!(map[&0], vec![(0, 0.0, 0.0, 1.0), (0, 0.5, 0.0, 1.0)]);
+        assert_eq!(map[&2], vec![(2, 1.0, 0.0, 1.0)]);
+        assert_eq!(merge_ids(map), a);
+    }
+
+    #[test]
+    fn reverse_time(){
+        let a = vec![(0, 1.0, 0.0, 0.0), (1, 1.5, 0.0, 0.0), (2, 3.0, 0.0, 0.0)];
+        assert_eq!(a.time_reversed(), vec![(2, 1.0, 0.0, 0.0), (1, 2.5, 0.0, 0.0), (0, 3.0, 0.0, 0.0)]);
+        assert_eq!(Floww::new().time_reversed(), vec![]);
+    }
+
+    #[test]
+    fn repeated(){
+        let a = vec![(0, 0.0, 0.0, 0.0), (1, 0.5, 0.0, 0.0)];
+        assert_eq!(a.clone().repeated(3, 2.0), vec![
+            (0, 0.0, 0.0, 0.0), (1, 0.5, 0.0, 0.0),
+            (0, 2.0, 0.0, 0.0), (1, 2.5, 0.0, 0.0),
+            (0, 4.0, 0.0, 0.0), (1, 4.5, 0.0, 0.0),
+        ]);
+        assert_eq!(a.clone().repeated(1, 2.0), a);
+        assert_eq!(a.repeat(2).len(), 4);
+        assert_eq!(a.repeated(0, 2.0), vec![]);
+    }
+
+    #[test]
+    fn humanize(){
+        let a = vec![(0, 1.0, 0.0, 0.5), (0, 2.0, 0.0, 0.0), (1, 3.0, 0.0, 1.0)];
+        let b = a.clone().humanized(0.1, 0.2, 7);
+        assert_eq!(b, a.clone().humanized(0.1, 0.2, 7));
+        assert_ne!(b, a.clone().humanized(0.1, 0.2, 8));
+        for (p, q) in a.iter().zip(b.iter()){
+            assert_eq!(p.0, q.0);
+            assert!((p.1 - q.1).abs() <= 0.1);
+            assert!((p.3 - q.3).abs() <= 0.2);
+        }
+        assert_eq!(b[1].3, 0.0);
+        assert_eq!(a.clone().humanized(0.0, 0.0, 1), a);
+    }
+
+    #[test]
+    fn swing(){
+        let a = vec![(0, 0.0, 0.0, 1.0), (0, 0.5, 0.0, 1.0), (0, 1.0, 0.0, 1.0), (0, 1.5, 0.0, 1.0)];
+        assert_eq!(a.clone().swung(0.5, 0.5), vec![
+            (0, 0.0, 0.0, 1.0), (0, 0.75, 0.0, 1.0), (0, 1.0, 0.0, 1.0), (0, 1.75, 0.0, 1.0)
+        ]);
+        assert_eq!(a.clone().swung(0.5, 0.0), a);
+        let wild = a.clone().swung(0.5, 3.0);
+        assert_eq!(wild, a.clone().swung(0.5, 1.0));
+        assert!(wild.windows(2).all(|w| w[0].1 <= w[1].1));
+        let back = vec![(0, 0.1, 0.0, 1.0), (0, 0.4, 0.0, 1.0)].swung(0.5, -2.0);
+        assert!(back.windows(2).all(|w| w[0].1 <= w[1].1));
+    }
+
+    #[test]
+    fn trim_crop(){
+        let a = vec![(0, 0.0, 0.0, 1.0), (1, 1.0, 0.0, 1.0), (2, 1.5, 0.0, 1.0), (3, 2.0, 0.0, 1.0)];
+        assert_eq!(a.clone().trimmed(1.0, 2.0), vec![(1, 1.0, 0.0, 1.0), (2, 1.5, 0.0, 1.0)]);
+        assert_eq!(a.clone().cropped(0.5, 2.0), vec![(1, 0.5, 0.0, 1.0), (2, 1.0, 0.0, 1.0)]);
+        assert_eq!(a.cropped(3.0, 4.0), vec![]);
+    }
+
+    #[test]
+    fn stretch_to(){
+        let a = vec![(0, 1.0, 0.0, 1.0), (1, 2.0, 0.0, 1.0), (2, 3.0, 0.0, 1.0)];
+        assert_eq!(a.stretched_to(4.0), vec![(0, 1.0, 0.0, 1.0), (1, 3.0, 0.0, 1.0), (2, 5.0, 0.0, 1.0)]);
+        let b = vec![(0, 1.0, 0.0, 1.0)];
+        assert_eq!(b.clone().stretched_to(4.0), b);
+    }
+
+    #[test]
+    fn invert_notes(){
+        let a = vec![(0, 0.0, 60.0, 1.0), (0, 1.0, 64.0, 1.0), (0, 2.0, 67.0, 1.0)];
+        assert_eq!(a.clone().notes_inverted(64.0), vec![(0, 0.0, 68.0, 1.0), (0, 1.0, 64.0, 1.0), (0, 2.0, 61.0, 1.0)]);
+        assert_eq!(a.clone().notes_inverted(62.0).notes_inverted(62.0), a);
+    }
+
+    #[test]
+    fn ratchet(){
+        let a = vec![(0, 0.0, 36.0, 1.0), (1, 1.0, 38.0, 1.0)];
+        assert_eq!(a.ratcheted(|p| p.0 == 1, 4, 0.5), vec![
+            (0, 0.0, 36.0, 1.0), (1, 1.0, 38.0, 1.0), (1, 1.125, 38.0, 0.75),
+            (1, 1.25, 38.0, 0.5), (1, 1.375, 38.0, 0.25),
+        ]);
+    }
+
+    #[test]
+    fn nan_safe_sort(){
+        let a = vec![(0, 1.0, 0.0, 0.0), (1, f32::NAN, 0.0, 0.0), (2, 0.0, 0.0, 0.0)];
+        assert_eq!(a.validate(), Err(InvalidTime::NaN(1)));
+        let b = a.sorted();
+        assert_eq!(b[0], (2, 0.0, 0.0, 0.0));
+        assert_eq!(b[1], (0, 1.0, 0.0, 0.0));
+        assert!(b[2].1.is_nan());
+        assert_eq!(vec![(0, -1.0, 0.0, 0.0)].validate(), Err(InvalidTime::Negative(0)));
+        assert_eq!(vec![(0, f32::INFINITY, 0.0, 0.0)].validate(), Err(InvalidTime::Infinite(0)));
+        assert_eq!(vec![(0, 0.0, 0.0, 0.0)].validate(), Ok(()));
+    }
+
+    #[test]
+    fn remap_ids(){
+        let a = vec![(0, 0.0, 0.0, 1.0), (1, 1.0, 0.0, 1.0), (2, 2.0, 0.0, 1.0)];
+        let map: HashMap<usize, usize> = [(0, 10), (2, 0)].iter().cloned().collect();
+        assert_eq!(a.clone().ids_remapped(&map), vec![(10, 0.0, 0.0, 1.0), (1, 1.0, 0.0, 1.0), (0, 2.0, 0.0, 1.0)]);
+        let mut b = a;
+        b.map_ids(|id| id * 2);
+        assert_eq!(b, vec![(0, 0.0, 0.0, 1.0), (2, 1.0, 0.0, 1.0), (4, 2.0, 0.0, 1.0)]);
+    }
+
+    #[test]
+    fn ghost_notes(){
+        let a = vec![(0, 0.0, 36.0, 1.0), (0, 0.25, 38.0, 0.1), (0, 0.3, 38.0, 0.0), (0, 0.5, 38.0, 0.8)];
+        assert_eq!(a.clone().removed_below_velocity(0.2), vec![(0, 0.0, 36.0, 1.0), (0, 0.5, 38.0, 0.8)]);
+        let mut b = a;
+        b.boost_below_velocity(0.2, 0.4);
+        assert_eq!(b, vec![(0, 0.0, 36.0, 1.0), (0, 0.25, 38.0, 0.4), (0, 0.3, 38.0, 0.0), (0, 0.5, 38.0, 0.8)]);
+    }
+
+    #[test]
+    fn quantize(){
+        let a = vec![(0, 0.1, 0.0, 1.0), (1, 0.3, 0.0, 1.0), (2, 0.76, 0.0, 1.0)];
+        assert_eq!(a.clone().quantized(0.25), vec![(0, 0.0, 0.0, 1.0), (1, 0.25, 0.0, 1.0), (2, 0.75, 0.0, 1.0)]);
+        assert_eq!(a.clone().quantized(0.0), a);
+    }
+
+    #[test]
+    fn bars(){
+        let a = vec![(0, 0.0, 0.0, 1.0), (1, 1.5, 0.0, 1.0), (2, 4.5, 0.0, 1.0), (3, 9.0, 0.0, 1.0)];
+        let bars = slice_bars(&a, 3, 1.0);
+        assert_eq!(bars, vec![
+            vec![(0, 0.0, 0.0, 1.0), (1, 1.5, 0.0, 1.0)],
+            vec![(2, 1.5, 0.0, 1.0)],
+            vec![],
+            vec![(3, 0.0, 0.0, 1.0)],
+        ]);
+        assert_eq!(join_bars(bars, 3, 1.0), a);
+    }
+
+    #[test]
+    fn rotate(){
+        let a = vec![(0, 0.0, 0.0, 1.0), (1, 1.0, 0.0, 1.0), (2, 3.0, 0.0, 1.0)];
+        assert_eq!(a.clone().rotated(1.5, 4.0), vec![(2, 0.5, 0.0, 1.0), (0, 1.5, 0.0, 1.0), (1, 2.5, 0.0, 1.0)]);
+        assert_eq!(a.clone().rotated(-1.0, 4.0), vec![(1, 0.0, 0.0, 1.0), (2, 2.0, 0.0, 1.0), (0, 3.0, 0.0, 1.0)]);
+        assert_eq!(a.clone().rotated(4.0, 4.0), a);
+        assert_eq!(vec![(0, 0.0, 0.0, 1.0)].rotated(-1e-8, 4.0), vec![(0, 0.0, 0.0, 1.0)]);
+    }
+
+    #[test]
+    fn mirror(){
+        let a = vec![(0, 0.0, 0.0, 1.0), (1, 1.0, 0.0, 1.0)];
+        assert_eq!(a.clone().mirrored(2.0), vec![(1, 3.0, 0.0, 1.0), (0, 4.0, 0.0, 1.0)]);
+        let b = a.clone().merged(a.mirrored(2.0));
+        assert_eq!(b, vec![(0, 0.0, 0.0, 1.0), (1, 1.0, 0.0, 1.0), (1, 3.0, 0.0, 1.0), (0, 4.0, 0.0, 1.0)]);
+    }
+
+    #[test]
+    fn thin(){
+        let a = (0..64).map(|i| (0, i as f32 * 0.25, 36.0, 1.0)).collect::<Floww>();
+        let b = a.clone().thinned(0.5, 11);
+        assert_eq!(b, a.clone().thinned(0.5, 11));
+        assert!(b.len() > 16 && b.len() < 48);
+        assert_eq!(a.clone().thinned(1.0, 11), a);
+        assert_eq!(a.clone().thinned(0.0, 11), vec![]);
+        let mut c = a.clone();
+        c.thin_steps(0.25, &[1.0, 0.0, 0.0, 0.0], 3);
+        assert_eq!(c, a.into_iter().step_by(4).collect::<Floww>());
+        let mut d = vec![(0, 0.0, 60.0, 1.0), (0, 0.5, 60.0, 0.0)];
+        d.thin(0.0, 1);
+        assert_eq!(d, vec![]);
+    }
+
+    #[test]
+    fn retime(){
+        let a = vec![(0, 0.0, 0.0, 1.0), (1, 1.0, 0.0, 1.0), (2, 2.0, 0.0, 1.0)];
+        assert_eq!(a.clone().retimed(60.0, 120.0), vec![(0, 0.0, 0.0, 1.0), (1, 0.5, 0.0, 1.0), (2, 1.0, 0.0, 1.0)]);
+        let mut b = a.clone();
+        b.retime_around(60.0, 120.0, 2.0);
+        assert_eq!(b, vec![(0, 1.0, 0.0, 1.0), (1, 1.5, 0.0, 1.0), (2, 2.0, 0.0, 1.0)]);
+        assert_eq!(a.clone().retimed(0.0, 120.0), a);
+    }
+
+    #[test]
+    fn flams_drags(){
+        let a = vec![(0, 1.0, 38.0, 1.0), (1, 2.0, 36.0, 1.0)];
+        let mut b = a.clone();
+        b.add_flams(|p| p.0 == 0, 0.03, 0.5);
+        assert_eq!(b, vec![(0, 0.97, 38.0, 0.5), (0, 1.0, 38.0, 1.0), (1, 2.0, 36.0, 1.0)]);
+        let mut c = a;
+        c.add_drags(|p| p.0 == 1, 0.25, 0.5);
+        assert_eq!(c, vec![(0, 1.0, 38.0, 1.0), (1, 1.5, 36.0, 0.5), (1, 1.75, 36.0, 0.5), (1, 2.0, 36.0, 1.0)]);
+    }
+
+    #[test]
+    fn fold_into(){
+        let a = vec![(0, 0.0, 0.0, 1.0), (1, 1.25, 0.0, 1.0), (2, 2.5, 0.0, 1.0), (3, 4.0, 0.0, 1.0)];
+        assert_eq!(a.folded_into(2.0), vec![(0, 0.0, 0.0, 1.0), (3, 0.0, 0.0, 1.0), (2, 0.5, 0.0, 1.0), (1, 1.25, 0.0, 1.0)]);
+    }
+
+    #[test]
+    fn fuse_with_overlap(){
+        let a = vec![(0, 0.0, 0.0, 1.0), (0, 1.0, 0.0, 1.0), (0, 2.0, 0.0, 1.0)];
+        let b = vec![(1, 0.0, 0.0, 1.0), (1, 0.5, 0.0, 1.0), (1, 2.0, 0.0, 1.0)];
+        assert_eq!(a.clone().fuse_with_overlap(b.clone(), 1.0), vec![
+            (0, 0.0, 0.0, 1.0), (0, 1.0, 0.0, 1.0), (1, 1.0, 0.0, MIN_VELOCITY),
+            (1, 1.5, 0.0, 0.5), (0, 2.0, 0.0, MIN_VELOCITY), (1, 3.0, 0.0, 1.0),
+        ]);
+        assert_eq!(a.clone().fuse_with_overlap(b.clone(), 0.0), a.fused(b.clone()));
+        assert_eq!(Floww::new().fuse_with_overlap(b.clone(), 1.0), b);
+    }
+
+    #[test]
+    fn merge_prioritized(){
+        let mut a = vec![(0, 0.0, 36.0, 0.5), (1, 1.0, 38.0, 1.0)];
+        let b = vec![(0, 0.01, 36.0, 0.9), (1, 1.0, 38.0, 0.2), (2, 0.5, 42.0, 1.0)];
+        a.merge_prioritized(b, 0.02, |ours, theirs| theirs.3 > ours.3);
+        assert_eq!(a, vec![(0, 0.01, 36.0, 0.9), (2, 0.5, 42.0, 1.0), (1, 1.0, 38.0, 1.0)]);
+    }
+
+    #[test]
+    fn sheet_remove(){
+        let mut sheet = FlowwSheet::new();
+        sheet.add(vec![(0, 0.0, 36.0, 1.0)], "kick".to_string());
+        sheet.add(vec![(0, 0.5, 38.0, 1.0)], "snare".to_string());
+        sheet.add(vec![(0, 0.25, 42.0, 1.0)], "hat".to_string());
+        assert_eq!(sheet.remove("snare"), Some(vec![(0, 0.5, 38.0, 1.0)]));
+        assert_eq!(sheet.remove("snare"), None);
+        assert_eq!(sheet.get_names(), vec!["kick".to_string(), "hat".to_string()]);
+        assert_eq!(sheet.get_floww_ref_by_name("hat"), &[(0, 0.25, 42.0, 1.0)]);
+        assert!(sheet.reset("hat", vec![]));
+        assert_eq!(sheet.get_floww_ref_by_name("kick"), &[(0, 0.0, 36.0, 1.0)]);
+    }
+
+    #[test]
+    fn sheet_rename(){
+        let mut sheet = FlowwSheet::new();
+        sheet.add(vec![(0, 0.0, 36.0, 1.0)], "bd".to_string());
+        sheet.add(vec![(0, 0.5, 38.0, 1.0)], "snare".to_string());
+        assert!(sheet.rename("bd", "kick"));
+        assert!(!sheet.rename("bd", "kick"));
+        assert!(!sheet.rename("kick", "snare"));
+        assert!(sheet.rename("kick", "kick"));
+        assert_eq!(sheet.get_names(), vec!["kick".to_string(), "snare".to_string()]);
+        assert_eq!(sheet.get_floww_ref_by_name("kick"), &[(0, 0.0, 36.0, 1.0)]);
+        assert_eq!(sheet.get_floww_ref_by_name("bd"), &[]);
+    }
+
+    #[test]
+    fn sheet_iter(){
+        let mut sheet = FlowwSheet::new();
+        sheet.add(vec![(0, 0.0, 36.0, 1.0)], "kick".to_string());
+        sheet.add(vec![(0, 0.5, 38.0, 1.0)], "snare".to_string());
+        for (name, floww) in sheet.iter_mut(){
+            if name == "snare" {
+                floww.shift_time(1.0);
+            }
+        }
+        let all = sheet.iter().map(|(n, f)| (n.to_string(), f.clone())).collect::<Vec<_>>();
+        assert_eq!(all, vec![
+            ("kick".to_string(), vec![(0, 0.0, 36.0, 1.0)]),
+            ("snare".to_string(), vec![(0, 1.5, 38.0, 1.0)]),
+        ]);
+    }
+
+    #[test]
+    fn sheet_get_mut(){
+        let mut sheet = FlowwSheet::new();
+        sheet.add(vec![(0, 0.1, 36.0, 1.0)], "kick".to_string());
+        sheet.get_floww_mut_by_name("kick").unwrap().push((0, 0.6, 36.0, 1.0));
+        sheet.get_floww_mut_by_name("kick").unwrap().quantize(0.5);
+        assert_eq!(sheet.get_floww_ref_by_name("kick"), &[(0, 0.0, 36.0, 1.0), (0, 0.5, 36.0, 1.0)]);
+        assert!(sheet.get_floww_mut_by_name("snare").is_none());
+    }
+
+    #[test]
+    fn sheet_merge(){
+        let mut a = FlowwSheet::new();
+        a.add(vec![(0, 0.0, 36.0, 1.0)], "kick".to_string());
+        let mut b = FlowwSheet::new();
+        b.add(vec![(0, 0.5, 36.0, 1.0)], "kick".to_string());
+        b.add(vec![(0, 0.5, 38.0, 1.0)], "snare".to_string());
+        let mut merged = a.clone();
+        merged.merge(b.clone(), ConflictPolicy::Merge);
+        assert_eq!(merged.get_names(), vec!["kick".to_string(), "snare".to_string()]);
+        assert_eq!(merged.get_floww_ref_by_name("kick"), &[(0, 0.0, 36.0, 1.0), (0, 0.5, 36.0, 1.0)]);
+        let mut renamed = a.clone();
+        renamed.merge(b.clone(), ConflictPolicy::Rename);
+        renamed.merge(b.clone(), ConflictPolicy::Rename);
+        assert_eq!(renamed.get_names(), vec!["kick", "kick_2", "snare", "kick_3", "snare_2"]);
+        let mut ours = a.clone();
+        ours.merge(b.clone(), ConflictPolicy::KeepOurs);
+        assert_eq!(ours.get_floww_ref_by_name("kick"), &[(0, 0.0, 36.0, 1.0)]);
+        let mut theirs = a;
+        theirs.merge(b, ConflictPolicy::TakeTheirs);
+        assert_eq!(theirs.get_floww_ref_by_name("kick"), &[(0, 0.5, 36.0, 1.0)]);
+    }
+
+    #[test]
+    fn sheet_reorder(){
+        let mut sheet = FlowwSheet::new();
+        sheet.add(vec![(0, 0.0, 42.0, 1.0)], "hat".to_string());
+        sheet.add(vec![(0, 0.0, 36.0, 1.0)], "kick".to_string());
+        sheet.add(vec![(0, 0.0, 38.0, 1.0)], "snare".to_string());
+        assert!(sheet.move_track("snare", 0));
+        assert_eq!(sheet.get_names(), vec!["snare", "hat", "kick"]);
+        assert!(sheet.move_track("snare", 10));
+        assert_eq!(sheet.get_names(), vec!["hat", "kick", "snare"]);
+        assert!(!sheet.move_track("crash", 0));
+        sheet.move_track("hat", 2);
+        sheet.sort_tracks_by_name();
+        assert_eq!(sheet.get_names(), vec!["hat", "kick", "snare"]);
+        assert_eq!(sheet.get_floww_ref_by_name("kick"), &[(0, 0.0, 36.0, 1.0)]);
+    }
+
+    #[test]
+    fn sheet_track_meta(){
+        let mut sheet = FlowwSheet::new();
+        sheet.add(vec![(0, 0.0, 36.0, 1.0)], "kick".to_string());
+        sheet.add(vec![(0, 0.5, 38.0, 1.0)], "snare".to_string());
+        sheet.add(vec![(0, 0.25, 42.0, 1.0)], "hat".to_string());
+        assert_eq!(sheet.get_meta("kick"), Some(&TrackMeta::default()));
+        assert!(sheet.set_mute("hat", true));
+        assert!(sheet.set_gain("snare", 0.5));
+        assert!(!sheet.set_mute("crash", true));
+        assert_eq!(sheet.get_meta("snare").unwrap().gain, 0.5);
+        assert!(!sheet.is_audible("hat"));
+        assert_eq!(sheet.clone().to_audible_floww_packets(), vec![
+            FlowwPacket::Track("kick".to_string()), FlowwPacket::Point((0, 0.0, 36.0, 1.0)),
+            FlowwPacket::Track("snare".to_string()), FlowwPacket::Point((0, 0.5, 38.0, 1.0)),
+        ]);
+        sheet.set_solo("hat", true);
+        assert!(sheet.is_audible("hat"));
+        assert!(!sheet.is_audible("kick"));
+        sheet.move_track("hat", 0);
+        assert!(sheet.get_meta("hat").unwrap().solo);
+        assert_eq!(sheet.clone().to_audible_floww_packets(), vec![
+            FlowwPacket::Track("hat".to_string()), FlowwPacket::Point((0, 0.25, 42.0, 1.0)),
+        ]);
+        sheet.remove("hat");
+        assert_eq!(sheet.get_meta("snare").unwrap().gain, 0.5);
+    }
+
+    #[test]
+    fn sheet_groups(){
+        let mut sheet = FlowwSheet::new();
+        sheet.add(vec![(0, 0.0, 36.0, 1.0)], "kick".to_string());
+        sheet.add(vec![(0, 0.5, 38.0, 1.0)], "snare".to_string());
+        sheet.add(vec![(0, 0.0, 60.0, 1.0)], "keys".to_string());
+        sheet.group("drums", &["kick", "snare", "crash"]);
+        assert_eq!(sheet.group_members("drums"), Some(&["kick".to_string(), "snare".to_string()][..]));
+        assert!(sheet.shift_group("drums", 1.0));
+        assert!(sheet.mute_group("drums", true));
+        assert_eq!(sheet.get_floww_ref_by_name("snare"), &[(0, 1.5, 38.0, 1.0)]);
+        assert_eq!(sheet.get_floww_ref_by_name("keys"), &[(0, 0.0, 60.0, 1.0)]);
+        assert!(!sheet.is_audible("kick"));
+        assert!(sheet.is_audible("keys"));
+        assert!(sheet.scale_group("drums", 2.0));
+        assert_eq!(sheet.get_floww_ref_by_name("kick"), &[(0, 2.0, 36.0, 1.0)]);
+        sheet.rename("kick", "bd");
+        sheet.remove("snare");
+        assert_eq!(sheet.group_members("drums"), Some(&["bd".to_string()][..]));
+        assert!(sheet.ungroup("drums"));
+        assert!(!sheet.shift_group("drums", 1.0));
+    }
+
+    #[test]
+    fn sheet_wide_transforms(){
+        let mut sheet = FlowwSheet::new();
+        sheet.add(vec![(0, 0.1, 36.0, 1.0), (0, 0.9, 36.0, 0.0)], "kick".to_string());
+        sheet.add(vec![(0, 0.6, 60.0, 1.0)], "keys".to_string());
+        sheet.scale_all(2.0);
+        sheet.shift_all(0.5);
+        sheet.quantize_all(1.0);
+        assert_eq!(sheet.get_floww_ref_by_name("kick"), &[(0, 1.0, 36.0, 1.0), (0, 2.0, 36.0, 0.0)]);
+        assert_eq!(sheet.get_floww_ref_by_name("keys"), &[(0, 2.0, 60.0, 1.0)]);
+    }
+
+    #[test]
+    fn sheet_from_packets(){
+        let mut sheet = FlowwSheet::new();
+        sheet.add(vec![(0, 0.0, 36.0, 1.0)], "kick".to_string());
+        sheet.add(vec![(0, 0.5, 60.0, 1.0), (0, 1.0, 60.0, 0.0)], "keys".to_string());
+        let back = FlowwSheet::from_packets(sheet.clone().to_floww_packets());
+        assert_eq!(back.get_names(), sheet.get_names());
+        assert_eq!(back.get_floww_ref_by_name("keys"), sheet.get_floww_ref_by_name("keys"));
+        let packets = vec![
+            FlowwPacket::Point((0, 0.0, 1.0, 1.0)), FlowwPacket::Track("a".to_string()),
+            FlowwPacket::Point((0, 1.0, 1.0, 1.0)), FlowwPacket::Msg("hi".to_string()),
+            FlowwPacket::Track("b".to_string()), FlowwPacket::Track("a".to_string()),
+            FlowwPacket::Point((0, 2.0, 1.0, 1.0)),
+        ];
+        let s = FlowwSheet::from_packets(packets);
+        assert_eq!(s.get_names(), vec!["a".to_string(), "b".to_string()]);
+        assert_eq!(s.get_floww_ref_by_name("a"), &[(0, 1.0, 1.0, 1.0), (0, 2.0, 1.0, 1.0)]);
+    }
+
+    #[test]
+    fn sheet_container(){
+        let mut sheet = FlowwSheet::new();
+        assert!(sheet.is_empty());
+        sheet.add(vec![(0, 0.0, 36.0, 1.0)], "kick".to_string());
+        sheet.add(Vec::new(), "keys".to_string());
+        assert_eq!(sheet.len(), 2);
+        assert!(sheet.contains("keys"));
+        assert!(!sheet.contains("bass"));
+        assert_eq!(sheet.get_by_index(1), Some(("keys", &Vec::new())));
+        assert_eq!(sheet.get_by_index(2), None);
+        assert_eq!(sheet["kick"], vec![(0, 0.0, 36.0, 1.0)]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn sheet_index_missing(){
+        let sheet = FlowwSheet::new();
+        let _ = &sheet["kick"];
+    }
+
+    #[test]
+    fn sheet_tags(){
+        let mut sheet = FlowwSheet::new();
+        sheet.add(Vec::new(), "kick".to_string());
+        sheet.add(Vec::new(), "keys".to_string());
+        sheet.add(Vec::new(), "snare".to_string());
+        assert!(sheet.add_tag("kick", "percussion"));
+        assert!(sheet.add_tag("snare", "percussion"));
+        assert!(sheet.add_tag("snare", "percussion"));
+        assert!(sheet.add_tag("keys", "melodic"));
+        assert!(!sheet.add_tag("bass", "melodic"));
+        assert_eq!(sheet.tracks_with_tag("percussion"), vec!["kick", "snare"]);
+        assert_eq!(sheet.get_meta("snare").unwrap().tags, vec!["percussion".to_string()]);
+        assert!(sheet.remove_tag("kick", "percussion"));
+        assert!(!sheet.remove_tag("kick", "percussion"));
+        assert_eq!(sheet.tracks_with_tag("percussion"), vec!["snare"]);
+    }
+
+    #[test]
+    fn sheet_midi_dir(){
+        let dir = std::env::temp_dir().join(format!("floww_midi_dir_{}", std::process::id()));
+        let mut sheet = FlowwSheet::new();
+        sheet.add(vec![(36, 0.0, 36.0, 1.0), (36, 0.5, 36.0, 0.0)], "kick".to_string());
+        sheet.add(vec![(60, 0.25, 60.0, 1.0), (60, 1.0, 60.0, 0.0)], "keys".to_string());
+        sheet.export_midi_dir(&dir).unwrap();
+        let saved = dir.join("saved.midi");
+        floww_to_midi(&sheet["kick"]).save(&saved.to_string_lossy());
+        assert_eq!(std::fs::read(&saved).unwrap(), std::fs::read(dir.join("kick.mid")).unwrap());
+        std::fs::remove_file(&saved).unwrap();
+        std::fs::write(dir.join("notes.txt"), "not midi").unwrap();
+        let back = FlowwSheet::from_midi_dir(&dir).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(back.get_names(), vec!["keys".to_string(), "kick".to_string()]);
+        assert_eq!(back["kick"], sheet["kick"]);
+        assert_eq!(back["keys"], sheet["keys"]);
+        assert!(FlowwSheet::from_midi_dir(&dir).is_err());
+        sheet.add(Vec::new(), "a/b".to_string());
+        sheet.add(Vec::new(), "a_b".to_string());
+        assert!(sheet.export_midi_dir(&dir).is_err());
+        assert!(!dir.exists());
+        // a directory where kick.mid should go makes the write fail
+        std::fs::create_dir_all(dir.join("kick.mid")).unwrap();
+        let mut kick = FlowwSheet::new();
+        kick.add(Vec::new(), "kick".to_string());
+        let res = kick.export_midi_dir(&dir);
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert!(res.is_err());
+    }
+
+    #[test]
+    fn sheet_offsets(){
+        let mut sheet = FlowwSheet::new();
+        sheet.add(vec![(0, 0.1, 36.0, 1.0), (0, 1.1, 36.0, 1.0)], "kick".to_string());
+        assert!(sheet.set_offset("kick", -0.05));
+        assert!(!sheet.set_offset("bass", 0.1));
+        let r = sheet.rendered("kick");
+        assert!((r[0].1 - 0.05).abs() < 1e-6 && (r[1].1 - 1.05).abs() < 1e-6);
+        assert_eq!(sheet["kick"], vec![(0, 0.1, 36.0, 1.0), (0, 1.1, 36.0, 1.0)]);
+        assert_eq!(sheet.rendered("bass"), vec![]);
+    }
+
+    #[test]
+    fn sheet_names(){
+        let mut sheet = FlowwSheet::new();
+        sheet.add(Vec::new(), "kick".to_string());
+        sheet.add(Vec::new(), "keys".to_string());
+        assert_eq!(sheet.names(), &["kick".to_string(), "keys".to_string()]);
+        assert_eq!(sheet.names(), sheet.get_names().as_slice());
+    }
+
+    #[test]
+    fn unpacket_policies(){
+        let packets = vec![
+            FlowwPacket::Track("kick".to_string()), FlowwPacket::Point((0, 0.0, 36.0, 1.0)),
+            FlowwPacket::Track("new".to_string()), FlowwPacket::Point((0, 1.0, 60.0, 1.0)),
+            FlowwPacket::Msg("hi".to_string()),
+            FlowwPacket::Track("kick".to_string()), FlowwPacket::Point((0, 2.0, 36.0, 1.0)),
+            FlowwPacket::Track("new".to_string()), FlowwPacket::Point((0, 3.0, 60.0, 1.0)),
+        ];
+        let base = || (vec![Vec::new(), Vec::new()], vec![("kick".to_string(), 0)].into_iter().collect::<HashMap<_, _>>());
+        let (mut flowws, mut map) = base();
+        let res = unpacket_with(&mut flowws, &mut map, packets.clone(), UnknownTrackPolicy::Drop);
+        assert_eq!(res.messages, vec!["hi".to_string()]);
+        assert!(res.collected.is_empty());
+        assert_eq!(flowws[0].len(), 2);
+        let (mut flowws, mut map) = base();
+        unpacket_with(&mut flowws, &mut map, packets.clone(), UnknownTrackPolicy::Overflow(1));
+        assert_eq!(flowws[1], vec![(0, 1.0, 60.0, 1.0), (0, 3.0, 60.0, 1.0)]);
+        let (mut flowws, mut map) = base();
+        let res = unpacket_with(&mut flowws, &mut map, packets.clone(), UnknownTrackPolicy::Collect);
+        assert_eq!(res.collected, vec![("new".to_string(), vec![(0, 1.0, 60.0, 1.0), (0, 3.0, 60.0, 1.0)])]);
+        let (mut flowws, mut map) = base();
+        unpacket_with(&mut flowws, &mut map, packets.clone(), UnknownTrackPolicy::Create);
+        assert_eq!(map.get("new"), Some(&2));
+        assert_eq!(flowws[2].len(), 2);
+        let mut sheet = FlowwSheet::new();
+        sheet.add(Vec::new(), "kick".to_string());
+        sheet.unpacket(packets, UnknownTrackPolicy::Create);
+        assert_eq!(sheet.names(), &["kick".to_string(), "new".to_string()]);
+        assert_eq!(sheet["new"], vec![(0, 1.0, 60.0, 1.0), (0, 3.0, 60.0, 1.0)]);
+    }
+
+    #[test]
+    fn sheet_snapshots(){
+        let mut sheet = FlowwSheet::new();
+        sheet.add(vec![(0, 0.0, 36.0, 1.0)], "kick".to_string());
+        sheet.add(vec![(0, 0.0, 60.0, 1.0)], "keys".to_string());
+        let snap = sheet.snapshot();
+        assert!(Arc::ptr_eq(&sheet.flowws[0], &snap.sheet.flowws[0]));
+        sheet.get_floww_mut_by_name("kick").unwrap().push((0, 1.0, 36.0, 1.0));
+        sheet.set_mute("keys", true);
+        sheet.add(Vec::new(), "bass".to_string());
+        assert!(!Arc::ptr_eq(&sheet.flowws[0], &snap.sheet.flowws[0]));
+        assert!(Arc::ptr_eq(&sheet.flowws[1], &snap.sheet.flowws[1]));
+        sheet.restore(&snap);
+        assert_eq!(sheet.names(), &["kick".to_string(), "keys".to_string()]);
+        assert_eq!(sheet["kick"], vec![(0, 0.0, 36.0, 1.0)]);
+        assert!(sheet.is_audible("keys"));
+    }
+
+    #[test]
+    fn sheet_clear_truncate(){
+        let mut sheet = FlowwSheet::new();
+        sheet.add(vec![(0, 0.0, 36.0, 1.0), (0, 1.0, 36.0, 1.0), (0, 2.0, 36.0, 1.0)], "kick".to_string());
+        sheet.add(vec![(0, 0.0, 60.0, 1.0)], "keys".to_string());
+        assert!(sheet.truncate_track("kick", 1.0));
+        assert_eq!(sheet["kick"], vec![(0, 1.0, 36.0, 1.0), (0, 2.0, 36.0, 1.0)]);
+        assert!(sheet.clear_track("keys"));
+        assert!(sheet.contains("keys") && sheet["keys"].is_empty());
+        assert!(!sheet.clear_track("bass"));
+        assert!(!sheet.truncate_track("bass", 1.0));
+    }
+
+    #[test]
+    fn transport_packets(){
+        let packets = vec![
+            FlowwPacket::Start, FlowwPacket::Track("kick".to_string()), FlowwPacket::Tempo(120.0),
+            FlowwPacket::Point((0, 0.0, 36.0, 1.0)), FlowwPacket::Seek(2.0), FlowwPacket::Position(2.5),
+            FlowwPacket::Stop,
+        ];
+        assert!(FlowwPacket::Seek(1.0).is_transport());
+        assert!(!FlowwPacket::Msg("hi".to_string()).is_transport());
+        let mut flowws = vec![Vec::new()];
+        let mut map = vec![("kick".to_string(), 0)].into_iter().collect::<HashMap<_, _>>();
+        let res = unpacket_with(&mut flowws, &mut map, packets.clone(), UnknownTrackPolicy::Drop);
+        assert_eq!(res.transport, vec![
+            FlowwPacket::Start, FlowwPacket::Tempo(120.0), FlowwPacket::Seek(2.0), FlowwPacket::Position(2.5),
+            FlowwPacket::Stop,
+        ]);
+        assert_eq!(flowws[0], vec![(0, 0.0, 36.0, 1.0)]);
+        let mut tracks = vec![Vec::new()];
+        assert!(unpacket(&mut tracks, &map, packets.clone()).is_empty());
+        assert_eq!(tracks[0].len(), 1);
+        assert_eq!(packets.encode().as_slice().decoded().unwrap(), packets);
+    }
+
+    #[test]
+    fn point_midi_bytes(){
+        assert_eq!(point_to_midi_bytes(&(0, 0.0, 60.0, 1.0), 2), [0x92, 60, 127]);
+        assert_eq!(point_to_midi_bytes(&(0, 0.0, 200.0, 0.0), 17), [0x81, 127, 0]);
+        assert_eq!(point_to_midi_bytes(&(0, 0.0, -3.0, 0.5), 0), [0x90, 0, 64]);
+    }
+    #[test]
+    fn bulk_points(){
+        let points: Floww = (0..16).map(|i| (0, i as f32 * 0.25, 36.0, 1.0)).collect();
+        let mut sheet = FlowwSheet::new();
+        sheet.add(points.clone(), "kick".to_string());
+        let single = sheet.clone().to_floww_packets();
+        let bulk = sheet.to_bulk_packets();
+        assert_eq!(bulk[1].points(), &points[..]);
+        assert!(bincode::serialize(&bulk).unwrap().len() < bincode::serialize(&single).unwrap().len());
+        let mut a = FlowwSheet::new();
+        let mut b = FlowwSheet::new();
+        a.unpacket(single, UnknownTrackPolicy::Create);
+        b.unpacket(bulk, UnknownTrackPolicy::Create);
+        assert_eq!(a.get_floww_ref_by_name("kick"), b.get_floww_ref_by_name("kick"));
+        assert!(FlowwPacket::Msg("hi".to_string()).points().is_empty());
+    }
+    #[test]
+    fn packet_tags(){
+        let tag = |p: FlowwPacket| bincode::serialize(&p).unwrap()[..4].to_vec();
+        assert_eq!(tag(FlowwPacket::Point((0, 0.0, 0.0, 0.0))), [2, 0, 0, 0]);
+        assert_eq!(tag(FlowwPacket::Start), [3, 0, 0, 0]);
+        assert_eq!(tag(FlowwPacket::Heartbeat(String::new())), [8, 0, 0, 0]);
+        assert_eq!(tag(FlowwPacket::Points(Vec::new())), [9, 0, 0, 0]);
+    }
+    #[test]
+    fn track_ids(){
+        let batch = |note| vec![
+            FlowwPacket::Track("kick".to_string()), FlowwPacket::Point((0, 0.0, note, 1.0)),
+            FlowwPacket::Track("snare".to_string()), FlowwPacket::Point((0, 0.0, note, 1.0)),
+        ];
+        let mut tx = TrackIds::new();
+        let first = tx.intern(batch(36.0));
+        assert_eq!(first[0], FlowwPacket::DefineTrack(0, "kick".to_string()));
+        assert_eq!(first.len(), 6);
+        let second = tx.intern(batch(38.0));
+        assert_eq!(second[0], FlowwPacket::TrackId(0));
+        assert_eq!(second[2], FlowwPacket::TrackId(1));
+        assert!(bincode::serialize(&second).unwrap().len() < bincode::serialize(&batch(38.0)).unwrap().len());
+        let mut sheet = FlowwSheet::new();
+        sheet.unpacket(first, UnknownTrackPolicy::Create);
+        sheet.unpacket(second, UnknownTrackPolicy::Create);
+        sheet.unpacket(vec![FlowwPacket::TrackId(7), FlowwPacket::Point((0, 1.0, 1.0, 1.0))], UnknownTrackPolicy::Create);
+        sheet.unpacket(vec![FlowwPacket::Point((0, 2.0, 1.0, 1.0)), FlowwPacket::TrackId(0), FlowwPacket::Point((0, 3.0, 1.0, 1.0))], UnknownTrackPolicy::Create);
+        assert_eq!(sheet.get_floww_ref_by_name("kick"), &[(0, 0.0, 36.0, 1.0), (0, 0.0, 38.0, 1.0), (0, 3.0, 1.0, 1.0)]);
+        assert_eq!(sheet.get_floww_ref_by_name("snare").len(), 2);
+        assert_eq!(sheet.len(), 2);
+    }
+    #[test]
+    fn control_packets(){
+        let mut sheet = FlowwSheet::new();
+        let res = sheet.unpacket(vec![
+            FlowwPacket::control("bpm", 120i64), FlowwPacket::control("name", "verse"),
+            FlowwPacket::control("bpm", 128.0), FlowwPacket::control("loop", true),
+        ], UnknownTrackPolicy::Drop);
+        assert_eq!(res.controls.len(), 4);
+        assert_eq!(res.control("bpm").and_then(|v| v.as_i64()), Some(128));
+        assert_eq!(res.control("bpm").and_then(|v| v.as_bool()), None);
+        assert_eq!(res.control("name").and_then(|v| v.as_str()), Some("verse"));
+        assert_eq!(res.control("loop").and_then(|v| v.as_bool()), Some(true));
+        assert_eq!(ControlValue::Int(3).as_f32(), Some(3.0));
+        assert_eq!(ControlValue::Float(2.5).as_i64(), None);
+        let packet = FlowwPacket::control("gain", 0.5);
+        assert_eq!(packet.as_control(), Some(("gain", &ControlValue::Float(0.5))));
+        assert!(res.control("nope").is_none());
+    }
+    #[test]
+    fn strict_unpacket(){
+        let mut flowws = vec![Vec::new()];
+        let mut map = HashMap::new();
+        map.insert("kick".to_string(), 0);
+        map.insert("gone".to_string(), 3);
+        let ok = vec![
+            FlowwPacket::Point((0, 0.0, 36.0, 1.0)), FlowwPacket::Msg("hi".to_string()),
+            FlowwPacket::Track("kick".to_string()), FlowwPacket::Points(vec![(0, 1.0, 36.0, 1.0)]),
+        ];
+        assert_eq!(unpacket_strict(&mut flowws, &map, ok), Ok(vec!["hi".to_string()]));
+        assert_eq!(flowws[0].len(), 2);
+        let bad = vec![
+            FlowwPacket::Track("kick".to_string()), FlowwPacket::Point((0, 2.0, 36.0, 1.0)),
+            FlowwPacket::Track("snare".to_string()), FlowwPacket::Point((0, 2.0, 38.0, 1.0)),
+            FlowwPacket::Track("gone".to_string()), FlowwPacket::Points(vec![(0, 2.0, 1.0, 1.0); 2]),
+            FlowwPacket::Track("snare".to_string()), FlowwPacket::Point((0, 3.0, 38.0, 1.0)),
+        ];
+        let err = unpacket_strict(&mut flowws, &map, bad).unwrap_err();
+        assert_eq!(err.unknown_tracks, vec!["snare".to_string()]);
+        assert_eq!(err.out_of_range, vec![3]);
+        assert_eq!(err.dropped, 4);
+        assert!(err.to_string().starts_with("4 points"));
+        assert_eq!(flowws[0].len(), 2);
+    }
+    #[test]
+    fn unpacket_reports(){
+        let mut flowws = vec![Vec::new(), Vec::new()];
+        let mut map = HashMap::new();
+        map.insert("kick".to_string(), 0);
+        map.insert("snare".to_string(), 1);
+        let report = unpacket_report(&mut flowws, &map, vec![
+            FlowwPacket::Point((0, 0.0, 36.0, 1.0)), FlowwPacket::Msg("hi".to_string()),
+            FlowwPacket::Track("snare".to_string()), FlowwPacket::Points(vec![(0, 1.0, 38.0, 1.0); 3]),
+            FlowwPacket::Track("hat".to_string()), FlowwPacket::Point((0, 1.0, 42.0, 1.0)),
+            FlowwPacket::Track("kick".to_string()), FlowwPacket::Point((0, 2.0, 36.0, 1.0)),
+        ]);
+        assert_eq!(report.messages, vec!["hi".to_string()]);
+        assert_eq!(report.routed, vec![("kick".to_string(), 2), ("snare".to_string(), 3)]);
+        assert_eq!(report.routed_to("hat"), 0);
+        assert_eq!(report.total_routed(), 5);
+        assert_eq!(report.dropped, 1);
+        assert_eq!(report.tracks, vec!["snare".to_string(), "hat".to_string(), "kick".to_string()]);
+        assert_eq!((flowws[0].len(), flowws[1].len()), (2, 3));
+    }
+    #[test]
+    fn track_aliases(){
+        let mut sheet = FlowwSheet::new();
+        sheet.add(Vec::new(), "kick".to_string());
+        sheet.add(Vec::new(), "snare".to_string());
+        sheet.alias_track("sd", "snare");
+        sheet.unpacket(vec![
+            FlowwPacket::Alias{ from: "bd".to_string(), to: "kick".to_string() },
+            FlowwPacket::Track("bd".to_string()), FlowwPacket::Point((0, 0.0, 36.0, 1.0)),
+            FlowwPacket::DefineTrack(0, "sd".to_string()), FlowwPacket::TrackId(0), FlowwPacket::Point((0, 0.5, 38.0, 1.0)),
+        ], UnknownTrackPolicy::Drop);
+        sheet.unpacket(vec![FlowwPacket::Track("bd".to_string()), FlowwPacket::Point((0, 1.0, 36.0, 1.0))], UnknownTrackPolicy::Create);
+        assert_eq!(sheet.get_floww_ref_by_name("kick").len(), 2);
+        assert_eq!(sheet.get_floww_ref_by_name("snare"), &[(0, 0.5, 38.0, 1.0)]);
+        assert!(!sheet.contains("bd"));
+        sheet.unpacket(vec![
+            FlowwPacket::Alias{ from: "bd".to_string(), to: "bd".to_string() },
+            FlowwPacket::Track("bd".to_string()), FlowwPacket::Point((0, 2.0, 36.0, 1.0)),
+        ], UnknownTrackPolicy::Create);
+        assert!(sheet.contains("bd"));
+    }
+    #[test]
+    fn rich_points(){
+        let rich = RichPoint::new((0, 1.0, 60.0, 0.5)).with(PARAM_PAN, -1.0).with(PARAM_PROBABILITY, 0.5).with(PARAM_PAN, 0.25);
+        assert_eq!(rich.params, vec![(PARAM_PAN, 0.25), (PARAM_PROBABILITY, 0.5)]);
+        assert_eq!(rich.param(PARAM_ARTICULATION), None);
+        let packet = FlowwPacket::RichPoint(rich);
+        assert_eq!(packet.points()[..], [(0, 1.0, 60.0, 0.5)]);
+        assert_eq!(packet.point_count(), 1);
+        let out = Transpose(12.0).process(vec![packet]);
+        assert!(matches!(&out[0], FlowwPacket::RichPoint(r) if r.point.2 == 72.0 && r.param(PARAM_PAN) == Some(0.25)));
+        let mut sheet = FlowwSheet::new();
+        sheet.add(Vec::new(), "keys".to_string());
+        sheet.unpacket(out, UnknownTrackPolicy::Overflow(0));
+        assert_eq!(sheet.get_floww_ref_by_name("keys"), &[(0, 1.0, 72.0, 0.5)]);
+    }
+    #[test]
+    fn clear_packets(){
+        let mut sheet = FlowwSheet::new();
+        sheet.add(vec![(0, 0.0, 36.0, 1.0)], "kick".to_string());
+        sheet.add(vec![(0, 0.0, 60.0, 1.0)], "keys".to_string());
+        let res = sheet.unpacket(vec![
+            FlowwPacket::Track("kick".to_string()), FlowwPacket::Point((0, 1.0, 36.0, 1.0)),
+            FlowwPacket::Clear("kick".to_string()), FlowwPacket::Point((0, 2.0, 36.0, 1.0)),
+            FlowwPacket::Clear("nope".to_string()),
+        ], UnknownTrackPolicy::Drop);
+        assert_eq!(res.cleared, vec!["kick".to_string(), "nope".to_string()]);
+        assert_eq!(sheet.get_floww_ref_by_name("kick"), &[(0, 2.0, 36.0, 1.0)]);
+        assert_eq!(sheet.get_floww_ref_by_name("keys").len(), 1);
+        sheet.unpacket(vec![
+            FlowwPacket::Track("new".to_string()), FlowwPacket::Point((0, 0.0, 1.0, 1.0)),
+            FlowwPacket::ClearAll, FlowwPacket::Point((0, 3.0, 1.0, 1.0)),
+        ], UnknownTrackPolicy::Create);
+        assert!(sheet.get_floww_ref_by_name("kick").is_empty());
+        assert!(sheet.get_floww_ref_by_name("keys").is_empty());
+        assert_eq!(sheet.get_floww_ref_by_name("new"), &[(0, 3.0, 1.0, 1.0)]);
+        let sheet = FlowwSheet::from_packets(vec![
+            FlowwPacket::Track("a".to_string()), FlowwPacket::Point((0, 0.0, 1.0, 1.0)),
+            FlowwPacket::Clear("a".to_string()),
+        ]);
+        assert!(sheet.get_floww_ref_by_name("a").is_empty());
+    }
 }
@@ -0,0 +1,123 @@
+use crate::{ Point, Scheduler };
+
+use rusty_link::{ AblLink, SessionState };
+
+// the time closest to the given one that has the given phase within a bar of quantum beats
+pub fn link_aligned(time: f32, phase: f32, quantum: f32) -> f32{
+    if quantum <= 0.0 { return time; }
+    let aligned = (time / quantum).floor() * quantum + phase;
+    if aligned - time > quantum * 0.5 {
+        aligned - quantum
+    } else if time - aligned > quantum * 0.5 {
+        aligned + quantum
+    } else {
+        aligned
+    }
+}
+
+// locks a scheduler running on beats to a link session, the link beat drives the scheduler
+// clock and when the two drift apart more than a bar the scheduler is moved onto the session
+// phase, start and stop are shared with the peers
+pub struct LinkSync{
+    link: AblLink,
+    state: SessionState,
+    quantum: f64,
+    last_beat: Option<f64>,
+}
+
+impl LinkSync{
+    pub fn new(bpm: f32, quantum: f32) -> Self{
+        let link = AblLink::new(bpm as f64);
+        link.enable(true);
+        link.enable_start_stop_sync(true);
+        Self{ link, state: SessionState::new(), quantum: quantum.max(1.0) as f64, last_beat: None }
+    }
+
+    pub fn enable(&self, enable: bool){
+        self.link.enable(enable);
+    }
+
+    pub fn is_enabled(&self) -> bool{
+        self.link.is_enabled()
+    }
+
+    pub fn num_peers(&self) -> u64{
+        self.link.num_peers()
+    }
+
+    pub fn quantum(&self) -> f32{
+        self.quantum as f32
+    }
+
+    pub fn set_quantum(&mut self, quantum: f32){
+        self.quantum = quantum.max(1.0) as f64;
+    }
+
+    pub fn tempo(&mut self) -> f32{
+        self.link.capture_app_session_state(&mut self.state);
+        self.state.tempo() as f32
+    }
+
+    pub fn set_tempo(&mut self, bpm: f32){
+        self.link.capture_app_session_state(&mut self.state);
+        self.state.set_tempo(bpm as f64, self.link.clock_micros());
+        self.link.commit_app_session_state(&self.state);
+    }
+
+    // starts the session on the next bar when there are peers
+    pub fn start(&mut self){
+        self.set_playing(true);
+    }
+
+    pub fn stop(&mut self){
+        self.set_playing(false);
+    }
+
+    fn set_playing(&mut self, playing: bool){
+        self.link.capture_app_session_state(&mut self.state);
+        self.state.set_is_playing_and_request_beat_at_time(playing, self.link.clock_micros(), 0.0, self.quantum);
+        self.link.commit_app_session_state(&self.state);
+    }
+
+    pub fn beat(&mut self) -> f32{
+        self.link.capture_app_session_state(&mut self.state);
+        self.state.beat_at_time(self.link.clock_micros(), self.quantum) as f32
+    }
+
+    // call regularly from the thread that plays, ticks the scheduler by the beats the session
+    // moved since the last call
+    pub fn sync<F: FnMut(&str, &Point)>(&mut self, scheduler: &mut Scheduler, f: F){
+        self.link.capture_app_session_state(&mut self.state);
+        let now = self.link.clock_micros();
+        let beat = self.state.beat_at_time(now, self.quantum);
+        if self.link.is_start_stop_sync_enabled() {
+            if self.state.is_playing() && !scheduler.is_playing() {
+                scheduler.play();
+                self.last_beat = None;
+            } else if !self.state.is_playing() {
+                scheduler.stop();
+            }
+        }
+        let last = self.last_beat.replace(beat);
+        if !scheduler.is_playing() { return; }
+        match last{
+            Some(last) if beat >= last && beat - last <= self.quantum => scheduler.tick((beat - last) as f32, f),
+            _ => {
+                let phase = self.state.phase_at_time(now, self.quantum) as f32;
+                scheduler.seek(link_aligned(scheduler.time(), phase, self.quantum as f32));
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+    #[test]
+    fn link_alignment(){
+        assert_eq!(link_aligned(1.0, 1.5, 4.0), 1.5);
+        assert_eq!(link_aligned(3.75, 0.25, 4.0), 4.25);
+        assert_eq!(link_aligned(4.5, 3.5, 4.0), 3.5);
+        assert_eq!(link_aligned(2.0, 0.0, 0.0), 2.0);
+    }
+}
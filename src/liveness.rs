@@ -0,0 +1,185 @@
+use crate::{ FlowwPacket, FlowwSheet };
+
+// hands out a heartbeat packet whenever interval passed since the last one
+#[derive(Clone,PartialEq,Debug)]
+pub struct Heartbeat{
+    id: String,
+    interval: f32,
+    last: Option<f32>,
+}
+
+impl Heartbeat{
+    pub fn new(id: &str, interval: f32) -> Self{
+        Self{ id: id.to_string(), interval, last: None }
+    }
+
+    pub fn id(&self) -> &str{
+        &self.id
+    }
+
+    pub fn poll(&mut self, now: f32) -> Option<FlowwPacket>{
+        match self.last{
+            Some(last) if now - last < self.interval => None,
+            _ => {
+                self.last = Some(now);
+                Some(FlowwPacket::Heartbeat(self.id.clone()))
+            },
+        }
+    }
+}
+
+#[derive(Clone,PartialEq,Debug)]
+struct SenderState{
+    id: String,
+    last_seen: f32,
+    tracks: Vec<String>,
+    // set once mute_dead muted its tracks, with the tracks it muted
+    down: bool,
+    muted: Vec<String>,
+}
+
+// last seen time per sender, a sender that wasn't heard from for longer than timeout counts as
+// dead and its tracks can be muted until it comes back
+#[derive(Clone,PartialEq,Debug)]
+pub struct Liveness{
+    timeout: f32,
+    senders: Vec<SenderState>,
+}
+
+impl Liveness{
+    pub fn new(timeout: f32) -> Self{
+        Self{ timeout, senders: Vec::new() }
+    }
+
+    fn sender_mut(&mut self, id: &str) -> Option<&mut SenderState>{
+        self.senders.iter_mut().find(|s| s.id == id)
+    }
+
+    pub fn seen(&mut self, sender: &str, now: f32){
+        if let Some(s) = self.sender_mut(sender){
+            s.last_seen = s.last_seen.max(now);
+        } else {
+            self.senders.push(SenderState{
+                id: sender.to_string(), last_seen: now, tracks: Vec::new(), down: false, muted: Vec::new(),
+            });
+        }
+    }
+
+    // false for senders that were never seen
+    pub fn claim(&mut self, sender: &str, track: &str) -> bool{
+        if let Some(s) = self.sender_mut(sender){
+            if !s.tracks.iter().any(|t| t == track) {
+                s.tracks.push(track.to_string());
+            }
+            true
+        } else {
+            false
+        }
+    }
+
+    // a heartbeat names the sender of everything after it in the batch, tracks it sends are
+    // claimed by it and any of its packets counts as a sign of life
+    pub fn observe(&mut self, packets: &[FlowwPacket], now: f32){
+        let mut current: Option<String> = None;
+        for packet in packets{
+            if let FlowwPacket::Heartbeat(id) = packet{
+                current = Some(id.clone());
+            }
+            if let Some(id) = &current{
+                self.seen(id, now);
+                if let FlowwPacket::Track(name) = packet{
+                    self.claim(id, name);
+                }
+            }
+        }
+    }
+
+    pub fn senders(&self) -> Vec<&str>{
+        self.senders.iter().map(|s| s.id.as_str()).collect()
+    }
+
+    pub fn tracks(&self, sender: &str) -> Option<&[String]>{
+        self.senders.iter().find(|s| s.id == sender).map(|s| s.tracks.as_slice())
+    }
+
+    pub fn last_seen(&self, sender: &str) -> Option<f32>{
+        self.senders.iter().find(|s| s.id == sender).map(|s| s.last_seen)
+    }
+
+    pub fn is_alive(&self, sender: &str, now: f32) -> bool{
+        self.last_seen(sender).map(|t| now - t <= self.timeout).unwrap_or(false)
+    }
+
+    pub fn dead(&self, now: f32) -> Vec<&str>{
+        self.senders.iter().filter(|s| now - s.last_seen > self.timeout).map(|s| s.id.as_str()).collect()
+    }
+
+    pub fn forget(&mut self, sender: &str) -> bool{
+        let len = self.senders.len();
+        self.senders.retain(|s| s.id != sender);
+        self.senders.len() != len
+    }
+
+    // mutes the tracks of senders that died and unmutes them when the sender is back, tracks
+    // that were muted already are left alone, returns the senders that went down or came up
+    pub fn mute_dead(&mut self, sheet: &mut FlowwSheet, now: f32) -> Vec<String>{
+        let timeout = self.timeout;
+        let mut changed = Vec::new();
+        for s in &mut self.senders{
+            let alive = now - s.last_seen <= timeout;
+            if !alive && !s.down {
+                s.muted = s.tracks.iter()
+                    .filter(|t| sheet.get_meta(t).map(|m| !m.mute).unwrap_or(false))
+                    .cloned().collect();
+                for t in &s.muted{
+                    sheet.set_mute(t, true);
+                }
+                s.down = true;
+                changed.push(s.id.clone());
+            } else if alive && s.down {
+                for t in s.muted.drain(..){
+                    sheet.set_mute(&t, false);
+                }
+                s.down = false;
+                changed.push(s.id.clone());
+            }
+        }
+        changed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+    #[test]
+    fn liveness(){
+        let mut hb = Heartbeat::new("laptop", 1.0);
+        assert_eq!(hb.poll(0.0), Some(FlowwPacket::Heartbeat("laptop".to_string())));
+        assert_eq!(hb.poll(0.5), None);
+        assert!(hb.poll(1.0).is_some());
+        let mut sheet = FlowwSheet::new();
+        sheet.add(vec![(0, 0.0, 36.0, 1.0)], "kick".to_string());
+        sheet.add(vec![(0, 0.0, 38.0, 1.0)], "snare".to_string());
+        sheet.set_mute("snare", true);
+        let mut live = Liveness::new(2.0);
+        live.observe(&[
+            FlowwPacket::Track("kick".to_string()), FlowwPacket::Heartbeat("laptop".to_string()),
+            FlowwPacket::Track("kick".to_string()), FlowwPacket::Track("snare".to_string()),
+        ], 1.0);
+        assert!(!live.claim("phone", "kick"));
+        assert_eq!(live.tracks("laptop"), Some(&["kick".to_string(), "snare".to_string()][..]));
+        assert_eq!(live.last_seen("laptop"), Some(1.0));
+        assert!(live.is_alive("laptop", 3.0));
+        assert!(live.mute_dead(&mut sheet, 3.0).is_empty());
+        assert_eq!(live.dead(3.5), vec!["laptop"]);
+        assert_eq!(live.mute_dead(&mut sheet, 3.5), vec!["laptop".to_string()]);
+        assert!(!sheet.is_audible("kick"));
+        assert!(live.mute_dead(&mut sheet, 4.0).is_empty());
+        live.observe(&[FlowwPacket::Heartbeat("laptop".to_string())], 5.0);
+        assert_eq!(live.mute_dead(&mut sheet, 5.0), vec!["laptop".to_string()]);
+        assert!(sheet.is_audible("kick"));
+        assert!(!sheet.is_audible("snare"));
+        assert!(live.forget("laptop"));
+        assert!(live.senders().is_empty());
+    }
+}
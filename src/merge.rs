@@ -0,0 +1,133 @@
+use crate::{ Point, Floww, FlowwSheet, FlowwDiff, diff_points };
+use crate::notes::sort_offs_first;
+
+// edits closer together than this are seen as the same point moved
+const MERGE_TOLERANCE: f32 = 0.05;
+
+// every conflict is resolved in favour of ours, the list tells what was overruled
+#[derive(Clone,PartialEq,Debug)]
+pub enum Conflict{
+    // a base point was changed differently on both sides, None means removed
+    Point{ track: String, base: Point, ours: Option<Point>, theirs: Option<Point> },
+    // one side removed a track that the other side changed
+    RemovedTrack{ track: String, by_ours: bool },
+}
+
+// what became of every base point on one side, None when it was removed
+fn point_fates(base: &[Point], diff: &FlowwDiff) -> Vec<Option<Point>>{
+    let mut removed = diff.removed.clone();
+    let mut moved = diff.moved.clone();
+    base.iter().map(|p| {
+        if let Some(i) = removed.iter().position(|r| r == p){
+            removed.swap_remove(i);
+            None
+        } else if let Some(i) = moved.iter().position(|(old, _)| old == p){
+            Some(moved.swap_remove(i).1)
+        } else {
+            Some(*p)
+        }
+    }).collect()
+}
+
+// points added on both sides end up once
+pub fn merge3_points(base: &[Point], ours: &[Point], theirs: &[Point], track: &str, conflicts: &mut Vec<Conflict>) -> Floww{
+    let diff_ours = diff_points(base, ours, MERGE_TOLERANCE);
+    let diff_theirs = diff_points(base, theirs, MERGE_TOLERANCE);
+    let fates = point_fates(base, &diff_ours).into_iter().zip(point_fates(base, &diff_theirs));
+    let mut res = Vec::new();
+    for (b, (o, t)) in base.iter().zip(fates){
+        let pick = if o == Some(*b) {
+            t
+        } else if t == Some(*b) || t == o {
+            o
+        } else {
+            conflicts.push(Conflict::Point{ track: track.to_string(), base: *b, ours: o, theirs: t });
+            o
+        };
+        res.extend(pick);
+    }
+    let mut added = diff_ours.added;
+    res.extend(added.iter().copied());
+    for p in diff_theirs.added{
+        if let Some(i) = added.iter().position(|a| *a == p){
+            added.swap_remove(i);
+        } else {
+            res.push(p);
+        }
+    }
+    sort_offs_first(&mut res);
+    res
+}
+
+impl FlowwSheet{
+    // tracks keep the order of ours with the new tracks of theirs after them,
+    // metadata and groups come from the side the track is taken from
+    pub fn merge3(base: &FlowwSheet, ours: &FlowwSheet, theirs: &FlowwSheet) -> (FlowwSheet, Vec<Conflict>){
+        let mut res = FlowwSheet::new();
+        let mut conflicts = Vec::new();
+        for ((name, o), meta) in ours.names.iter().zip(ours.flowws.iter()).zip(ours.metas.iter()){
+            let floww = match (base.map.get(name), theirs.map.get(name)){
+                (Some(b), Some(t)) => merge3_points(&base.flowws[*b], o, &theirs.flowws[*t], name, &mut conflicts),
+                (None, Some(t)) => merge3_points(&[], o, &theirs.flowws[*t], name, &mut conflicts),
+                (Some(b), None) => {
+                    if base.flowws[*b] == *o { continue; }
+                    conflicts.push(Conflict::RemovedTrack{ track: name.clone(), by_ours: false });
+                    (**o).clone()
+                },
+                (None, None) => (**o).clone(),
+            };
+            res.add_with_meta(floww, name.clone(), meta.clone());
+        }
+        for ((name, t), meta) in theirs.names.iter().zip(theirs.flowws.iter()).zip(theirs.metas.iter()){
+            if ours.map.contains_key(name) { continue; }
+            if let Some(b) = base.map.get(name){
+                if base.flowws[*b] != *t {
+                    conflicts.push(Conflict::RemovedTrack{ track: name.clone(), by_ours: true });
+                }
+                continue;
+            }
+            res.add_with_meta((**t).clone(), name.clone(), meta.clone());
+        }
+        let groups = theirs.groups.iter().filter(|(g, _)| !ours.groups.contains_key(*g)).chain(ours.groups.iter());
+        for (group, members) in groups{
+            let members = members.iter().map(|m| m.as_str()).collect::<Vec<_>>();
+            res.group(group, &members);
+        }
+        (res, conflicts)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+    #[test]
+    fn merge3(){
+        let mut base = FlowwSheet::new();
+        base.add(vec![(0, 0.0, 36.0, 1.0), (0, 1.0, 36.0, 1.0), (0, 2.0, 36.0, 1.0)], "kick".to_string());
+        base.add(vec![(0, 0.0, 60.0, 1.0)], "keys".to_string());
+        base.add(vec![(0, 0.0, 40.0, 1.0)], "bass".to_string());
+        let mut ours = base.clone();
+        let mut theirs = base.clone();
+        ours.get_floww_mut_by_name("kick").unwrap()[1].3 = 0.5;
+        ours.get_floww_mut_by_name("kick").unwrap()[2].3 = 0.5;
+        ours.get_floww_mut_by_name("kick").unwrap().push((0, 3.0, 36.0, 1.0));
+        ours.remove("keys");
+        ours.add(vec![(0, 0.0, 72.0, 1.0)], "lead".to_string());
+        theirs.get_floww_mut_by_name("kick").unwrap().remove(0);
+        theirs.get_floww_mut_by_name("kick").unwrap()[1].3 = 0.25;
+        theirs.get_floww_mut_by_name("kick").unwrap().push((0, 3.0, 36.0, 1.0));
+        theirs.get_floww_mut_by_name("bass").unwrap().push((0, 1.0, 40.0, 1.0));
+        let (merged, conflicts) = FlowwSheet::merge3(&base, &ours, &theirs);
+        assert_eq!(merged.get_names(), vec!["kick".to_string(), "bass".to_string(), "lead".to_string()]);
+        assert_eq!(merged["kick"], vec![(0, 1.0, 36.0, 0.5), (0, 2.0, 36.0, 0.5), (0, 3.0, 36.0, 1.0)]);
+        assert_eq!(merged["bass"], vec![(0, 0.0, 40.0, 1.0), (0, 1.0, 40.0, 1.0)]);
+        assert_eq!(conflicts, vec![Conflict::Point{
+            track: "kick".to_string(), base: (0, 2.0, 36.0, 1.0),
+            ours: Some((0, 2.0, 36.0, 0.5)), theirs: Some((0, 2.0, 36.0, 0.25)),
+        }]);
+        theirs.get_floww_mut_by_name("keys").unwrap().push((0, 1.0, 60.0, 1.0));
+        let (merged, conflicts) = FlowwSheet::merge3(&base, &ours, &theirs);
+        assert!(!merged.contains("keys"));
+        assert_eq!(conflicts[1], Conflict::RemovedTrack{ track: "keys".to_string(), by_ours: true });
+    }
+}
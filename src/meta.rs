@@ -0,0 +1,119 @@
+use crate::{ Floww };
+
+use serde::{ Serialize, Deserialize };
+
+use std::collections::{ HashMap };
+
+// (key, value) pairs, flags like "ghost" or "accent" have an empty value
+pub type PointMeta = Vec<(String, String)>;
+
+// sparse metadata next to a floww, keyed by point index, keep it in sync by sorting and merging
+// through it instead of through the floww
+#[derive(Clone,PartialEq,Debug,Default,Serialize,Deserialize)]
+pub struct FlowwMeta{
+    map: HashMap<usize, PointMeta>,
+}
+
+impl FlowwMeta{
+    pub fn new() -> Self{
+        Self::default()
+    }
+
+    pub fn set(&mut self, index: usize, key: &str, value: &str){
+        let meta = self.map.entry(index).or_default();
+        if let Some(kv) = meta.iter_mut().find(|(k, _)| k == key){
+            kv.1 = value.to_string();
+        } else {
+            meta.push((key.to_string(), value.to_string()));
+        }
+    }
+
+    pub fn mark(&mut self, index: usize, key: &str){
+        self.set(index, key, "");
+    }
+
+    pub fn get(&self, index: usize, key: &str) -> Option<&str>{
+        self.map.get(&index)?.iter().find(|(k, _)| k == key).map(|(_, v)| v.as_str())
+    }
+
+    pub fn has(&self, index: usize, key: &str) -> bool{
+        self.get(index, key).is_some()
+    }
+
+    pub fn remove(&mut self, index: usize, key: &str) -> Option<String>{
+        let meta = self.map.get_mut(&index)?;
+        let pos = meta.iter().position(|(k, _)| k == key)?;
+        let (_, v) = meta.remove(pos);
+        if meta.is_empty() {
+            self.map.remove(&index);
+        }
+        Some(v)
+    }
+
+    pub fn of(&self, index: usize) -> &[(String, String)]{
+        self.map.get(&index).map(|m| m.as_slice()).unwrap_or(&[])
+    }
+
+    pub fn indices_with(&self, key: &str) -> Vec<usize>{
+        let mut res = self.map.iter().filter(|(_, m)| m.iter().any(|(k, _)| k == key))
+            .map(|(i, _)| *i).collect::<Vec<_>>();
+        res.sort_unstable();
+        res
+    }
+
+    pub fn is_empty(&self) -> bool{
+        self.map.is_empty()
+    }
+
+    // order[new index] = old index, entries of indices not in order are dropped
+    pub fn permute(&mut self, order: &[usize]){
+        let mut old = std::mem::take(&mut self.map);
+        for (new, o) in order.iter().enumerate(){
+            if let Some(m) = old.remove(o){
+                self.map.insert(new, m);
+            }
+        }
+    }
+
+    // sorts like TimedVec::sort and moves the metadata along
+    pub fn sort(&mut self, floww: &mut Floww){
+        let mut order = (0..floww.len()).collect::<Vec<_>>();
+        order.sort_by(|a, b| floww[*a].1.total_cmp(&floww[*b].1));
+        let old = std::mem::take(floww);
+        floww.extend(order.iter().map(|i| old[*i]));
+        self.permute(&order);
+    }
+
+    // like TimedVec::merge, the metadata of other comes along
+    pub fn merge(&mut self, floww: &mut Floww, other: Floww, other_meta: FlowwMeta){
+        let l = floww.len();
+        floww.extend(other);
+        for (i, m) in other_meta.map{
+            self.map.insert(i + l, m);
+        }
+        self.sort(floww);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+    #[test]
+    fn meta(){
+        let mut f = vec![(0, 1.0, 38.0, 0.2), (0, 0.0, 38.0, 1.0)];
+        let mut m = FlowwMeta::new();
+        m.mark(0, "ghost");
+        m.set(1, "accent", "2");
+        m.sort(&mut f);
+        assert_eq!(f, vec![(0, 0.0, 38.0, 1.0), (0, 1.0, 38.0, 0.2)]);
+        assert!(m.has(1, "ghost"));
+        assert_eq!(m.get(0, "accent"), Some("2"));
+        let mut om = FlowwMeta::new();
+        om.mark(0, "flam");
+        m.merge(&mut f, vec![(0, 0.5, 38.0, 1.0)], om);
+        assert_eq!(m.indices_with("flam"), vec![1]);
+        assert_eq!(m.indices_with("ghost"), vec![2]);
+        assert_eq!(m.remove(1, "flam"), Some("".to_string()));
+        assert_eq!(m.of(1), &[]);
+    }
+}
@@ -0,0 +1,187 @@
+use crate::{ FlowwPacket, FlowwSheet, Unpacked, UnknownTrackPolicy, TrackIds };
+
+use std::collections::HashMap;
+
+#[derive(Clone,PartialEq,Debug,Default)]
+pub struct SenderStats{
+    pub batches: u64,
+    pub packets: u64,
+    pub points: u64,
+    pub messages: u64,
+    // batches that never arrived, only known for transports with sequence numbers
+    pub lost: u64,
+    pub first_seen: f32,
+    pub last_seen: f32,
+    // sheet tracks this sender wrote to
+    pub tracks: Vec<String>,
+}
+
+// merges the streams of several senders into one sheet, every batch comes in with the id of
+// its sender, with prefixing on the tracks of each sender are kept apart as "<sender>/<track>"
+pub struct MergeReceiver{
+    sheet: FlowwSheet,
+    prefix: bool,
+    senders: Vec<(String, SenderStats)>,
+    // every sender hands out its own track ids
+    ids: HashMap<String, TrackIds>,
+}
+
+impl Default for MergeReceiver{
+    fn default() -> Self{
+        Self::new()
+    }
+}
+
+impl MergeReceiver{
+    pub fn new() -> Self{
+        Self::with_sheet(FlowwSheet::new())
+    }
+
+    pub fn with_sheet(sheet: FlowwSheet) -> Self{
+        Self{ sheet, prefix: false, senders: Vec::new(), ids: HashMap::new() }
+    }
+
+    pub fn with_prefix(mut self, prefix: bool) -> Self{
+        self.prefix = prefix;
+        self
+    }
+
+    pub fn sheet(&self) -> &FlowwSheet{
+        &self.sheet
+    }
+
+    pub fn sheet_mut(&mut self) -> &mut FlowwSheet{
+        &mut self.sheet
+    }
+
+    pub fn into_sheet(self) -> FlowwSheet{
+        self.sheet
+    }
+
+    pub fn senders(&self) -> Vec<&str>{
+        self.senders.iter().map(|(id, _)| id.as_str()).collect()
+    }
+
+    pub fn stats(&self, sender: &str) -> Option<&SenderStats>{
+        self.senders.iter().find(|(id, _)| id == sender).map(|(_, s)| s)
+    }
+
+    fn stats_mut(&mut self, sender: &str, time: f32) -> &mut SenderStats{
+        let i = match self.senders.iter().position(|(id, _)| id == sender){
+            Some(i) => i,
+            None => {
+                self.senders.push((sender.to_string(), SenderStats{ first_seen: time, last_seen: time, ..SenderStats::default() }));
+                self.senders.len() - 1
+            },
+        };
+        &mut self.senders[i].1
+    }
+
+    // the packets as they would go into the sheet, tracks renamed when prefixing, a ClearAll
+    // only clears the tracks of its own sender so it becomes a Clear for each of them
+    pub fn tag(&self, sender: &str, packets: Vec<FlowwPacket>) -> Vec<FlowwPacket>{
+        let name = |track: String| if self.prefix { format!("{}/{}", sender, track) } else { track };
+        let mut tracks = self.stats(sender).map(|s| s.tracks.clone()).unwrap_or_default();
+        let mut res = Vec::with_capacity(packets.len());
+        for packet in packets{
+            match packet{
+                FlowwPacket::Track(track) => {
+                    let track = name(track);
+                    if !tracks.contains(&track) {
+                        tracks.push(track.clone());
+                    }
+                    res.push(FlowwPacket::Track(track));
+                },
+                FlowwPacket::Clear(track) => res.push(FlowwPacket::Clear(name(track))),
+                FlowwPacket::ClearAll => res.extend(tracks.iter().map(|t| FlowwPacket::Clear(t.clone()))),
+                other => res.push(other),
+            }
+        }
+        res
+    }
+
+    // unknown tracks are created, what doesn't go into the sheet is handed back
+    pub fn accept(&mut self, sender: &str, packets: Vec<FlowwPacket>, time: f32) -> Unpacked{
+        let packets = self.ids.entry(sender.to_string()).or_default().resolve(packets);
+        let packets = self.tag(sender, packets);
+        let stats = self.stats_mut(sender, time);
+        stats.batches += 1;
+        stats.packets += packets.len() as u64;
+        stats.last_seen = stats.last_seen.max(time);
+        for packet in &packets{
+            match packet{
+                _ if packet.has_points() => stats.points += packet.point_count() as u64,
+                FlowwPacket::Msg(_) => stats.messages += 1,
+                FlowwPacket::Track(name) if !stats.tracks.contains(name) => stats.tracks.push(name.clone()),
+                _ => {},
+            }
+        }
+        self.sheet.unpacket(packets, UnknownTrackPolicy::Create)
+    }
+
+    pub fn record_lost(&mut self, sender: &str, batches: u64){
+        if let Some((_, stats)) = self.senders.iter_mut().find(|(id, _)| id == sender){
+            stats.lost += batches;
+        }
+    }
+
+    // the sender is identified by its address, gaps in the sequence count as lost
+    #[cfg(feature = "net")]
+    pub fn accept_udp(&mut self, batch: crate::UdpBatch, time: f32) -> Unpacked{
+        let sender = batch.from.to_string();
+        let res = self.accept(&sender, batch.packets, time);
+        self.record_lost(&sender, batch.missed.end - batch.missed.start);
+        res
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+    #[test]
+    fn merge_receiver(){
+        let mut mr = MergeReceiver::new().with_prefix(true);
+        let batch = |note| vec![
+            FlowwPacket::Track("keys".to_string()), FlowwPacket::Point((0, 0.0, note, 1.0)),
+            FlowwPacket::Msg("hi".to_string()),
+        ];
+        let res = mr.accept("anna", batch(60.0), 1.0);
+        assert_eq!(res.messages, vec!["hi".to_string()]);
+        mr.accept("ben", batch(48.0), 1.5);
+        mr.accept("anna", vec![FlowwPacket::Track("keys".to_string()), FlowwPacket::Point((0, 1.0, 62.0, 0.0))], 2.0);
+        mr.record_lost("anna", 2);
+        assert_eq!(mr.senders(), vec!["anna", "ben"]);
+        assert_eq!(mr.sheet().get_floww_ref_by_name("anna/keys").len(), 2);
+        assert_eq!(mr.sheet().get_floww_ref_by_name("ben/keys"), &[(0, 0.0, 48.0, 1.0)]);
+        let anna = mr.stats("anna").unwrap();
+        assert_eq!((anna.batches, anna.packets, anna.points, anna.messages, anna.lost), (2, 5, 2, 1, 2));
+        assert_eq!((anna.first_seen, anna.last_seen), (1.0, 2.0));
+        assert_eq!(anna.tracks, vec!["anna/keys".to_string()]);
+        assert!(mr.stats("carl").is_none());
+        let mut shared = MergeReceiver::new();
+        shared.accept("anna", batch(60.0), 0.0);
+        shared.accept("ben", batch(48.0), 0.0);
+        assert_eq!(shared.into_sheet().get_floww_ref_by_name("keys").len(), 2);
+    }
+    #[test]
+    fn merge_receiver_clear(){
+        let mut mr = MergeReceiver::new().with_prefix(true);
+        let batch = |track: &str| vec![FlowwPacket::Track(track.to_string()), FlowwPacket::Point((0, 0.0, 60.0, 1.0))];
+        mr.accept("anna", batch("keys"), 0.0);
+        mr.accept("anna", batch("bass"), 0.0);
+        mr.accept("ben", batch("keys"), 0.0);
+        mr.sheet_mut().add(vec![(0, 0.0, 1.0, 1.0)], "keys".to_string());
+        let res = mr.accept("anna", vec![FlowwPacket::Clear("keys".to_string())], 1.0);
+        assert_eq!(res.cleared, vec!["anna/keys".to_string()]);
+        assert!(mr.sheet().get_floww_ref_by_name("anna/keys").is_empty());
+        assert_eq!(mr.sheet().get_floww_ref_by_name("anna/bass").len(), 1);
+        assert_eq!(mr.sheet().get_floww_ref_by_name("keys").len(), 1);
+        mr.accept("ben", vec![FlowwPacket::Track("lead".to_string()), FlowwPacket::Point((0, 0.0, 72.0, 1.0))], 1.0);
+        let res = mr.accept("ben", vec![FlowwPacket::ClearAll], 2.0);
+        assert_eq!(res.cleared, vec!["ben/keys".to_string(), "ben/lead".to_string()]);
+        assert!(mr.sheet().get_floww_ref_by_name("ben/keys").is_empty());
+        assert!(mr.sheet().get_floww_ref_by_name("ben/lead").is_empty());
+        assert_eq!(mr.sheet().get_floww_ref_by_name("anna/bass").len(), 1);
+        assert_eq!(mr.sheet().get_floww_ref_by_name("keys").len(), 1);
+    }
+}
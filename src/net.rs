@@ -0,0 +1,394 @@
+use crate::{ FlowwPacket, DEFAULT_BATCH_SIZE };
+
+use serde::{ Serialize, Deserialize };
+
+use std::io::{ self, Read, Write, BufReader, BufWriter };
+use std::net::{ TcpStream, TcpListener, SocketAddr, ToSocketAddrs };
+
+// frames bigger than this are taken as a broken stream instead of allocated
+pub const MAX_FRAME_LEN: usize = 1 << 26;
+
+pub(crate) fn invalid_data<E: std::fmt::Display>(e: E) -> io::Error{
+    io::Error::new(io::ErrorKind::InvalidData, format!("floww: {}", e))
+}
+
+// a frame is the u32 little endian length of the encoded batch, then the batch
+pub fn write_frame<W: Write>(w: &mut W, packets: &[FlowwPacket]) -> io::Result<()>{
+    let bytes = bincode::serialize(packets).map_err(invalid_data)?;
+    if bytes.len() > MAX_FRAME_LEN {
+        return Err(invalid_data("frame too large"));
+    }
+    w.write_all(&(bytes.len() as u32).to_le_bytes())?;
+    w.write_all(&bytes)?;
+    w.flush()
+}
+
+// None when the stream ended cleanly between frames
+pub fn read_frame<R: Read>(r: &mut R) -> io::Result<Option<Vec<FlowwPacket>>>{
+    let mut len = [0u8; 4];
+    let mut got = 0;
+    while got < 4{
+        match r.read(&mut len[got..]){
+            Ok(0) if got == 0 => return Ok(None),
+            Ok(0) => return Err(io::ErrorKind::UnexpectedEof.into()),
+            Ok(n) => got += n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {},
+            Err(e) => return Err(e),
+        }
+    }
+    let len = u32::from_le_bytes(len) as usize;
+    if len > MAX_FRAME_LEN {
+        return Err(invalid_data("frame too large"));
+    }
+    let mut bytes = vec![0u8; len];
+    r.read_exact(&mut bytes)?;
+    bincode::deserialize(&bytes).map(Some).map_err(invalid_data)
+}
+
+pub const PROTOCOL_VERSION: u32 = 1;
+
+// what this end of the protocol understands besides plain points and messages
+pub const PROTOCOL_FEATURES: &[&str] = &[
+    "transport", "heartbeat", "timed", "points", "track_ids", "clear", "flush", "control", "timestamp", "alias",
+    "deltas", "rich_point",
+];
+
+const HELLO_MAGIC: [u8; 4] = *b"FLWW";
+
+// the first thing both ends send when a handshake is used, the connecting side goes first
+#[derive(Clone,PartialEq,Debug,Serialize,Deserialize)]
+pub struct Hello{
+    pub version: u32,
+    pub features: Vec<String>,
+    pub tracks: Vec<String>,
+}
+
+impl Hello{
+    pub fn new(tracks: Vec<String>) -> Self{
+        Self{
+            version: PROTOCOL_VERSION,
+            features: PROTOCOL_FEATURES.iter().map(|f| f.to_string()).collect(),
+            tracks,
+        }
+    }
+}
+
+// what both ends agreed on, with the tracks the peer announced
+#[derive(Clone,PartialEq,Debug)]
+pub struct Session{
+    pub version: u32,
+    pub features: Vec<String>,
+    pub peer_tracks: Vec<String>,
+}
+
+impl Session{
+    pub fn has_feature(&self, feature: &str) -> bool{
+        self.features.iter().any(|f| f == feature)
+    }
+}
+
+pub fn write_hello<W: Write>(w: &mut W, hello: &Hello) -> io::Result<()>{
+    let bytes = bincode::serialize(hello).map_err(invalid_data)?;
+    w.write_all(&HELLO_MAGIC)?;
+    w.write_all(&(bytes.len() as u32).to_le_bytes())?;
+    w.write_all(&bytes)?;
+    w.flush()
+}
+
+// anything that doesn't start with the magic is refused before its length is trusted
+pub fn read_hello<R: Read>(r: &mut R) -> io::Result<Hello>{
+    let mut head = [0u8; 8];
+    r.read_exact(&mut head)?;
+    if head[..4] != HELLO_MAGIC {
+        return Err(invalid_data("peer didn't send a floww handshake"));
+    }
+    let len = u32::from_le_bytes([head[4], head[5], head[6], head[7]]) as usize;
+    if len > MAX_FRAME_LEN {
+        return Err(invalid_data("handshake too large"));
+    }
+    let mut bytes = vec![0u8; len];
+    r.read_exact(&mut bytes)?;
+    bincode::deserialize(&bytes).map_err(invalid_data)
+}
+
+// versions have to match, features are what both ends support
+pub fn negotiate(ours: &Hello, theirs: &Hello) -> io::Result<Session>{
+    if ours.version != theirs.version {
+        return Err(invalid_data(format!("peer speaks protocol version {}, this end speaks {}",
+            theirs.version, ours.version)));
+    }
+    Ok(Session{
+        version: ours.version,
+        features: ours.features.iter().filter(|f| theirs.features.contains(f)).cloned().collect(),
+        peer_tracks: theirs.tracks.clone(),
+    })
+}
+
+// packets are batched until the batch is full or flush is called, a failed write reconnects once
+pub struct FlowwSender{
+    addrs: Vec<SocketAddr>,
+    stream: Option<BufWriter<TcpStream>>,
+    batch: Vec<FlowwPacket>,
+    batch_size: usize,
+    hello: Option<Hello>,
+    session: Option<Session>,
+    // the last Track or TrackId pushed, resent when an automatic flush split its points
+    header: Option<FlowwPacket>,
+    resume: bool,
+}
+
+impl FlowwSender{
+    pub fn connect<A: ToSocketAddrs>(addr: A) -> io::Result<Self>{
+        Self::connect_inner(addr, None)
+    }
+
+    // shakes hands on every (re)connect, fails when the receiver is incompatible
+    pub fn connect_with_hello<A: ToSocketAddrs>(addr: A, hello: Hello) -> io::Result<Self>{
+        Self::connect_inner(addr, Some(hello))
+    }
+
+    fn connect_inner<A: ToSocketAddrs>(addr: A, hello: Option<Hello>) -> io::Result<Self>{
+        let addrs = addr.to_socket_addrs()?.collect::<Vec<_>>();
+        let mut sender = Self{
+            addrs, stream: None, batch: Vec::new(), batch_size: DEFAULT_BATCH_SIZE, hello, session: None,
+            header: None, resume: false,
+        };
+        sender.reconnect()?;
+        Ok(sender)
+    }
+
+    pub fn with_batch_size(mut self, batch_size: usize) -> Self{
+        self.batch_size = batch_size.max(1);
+        self
+    }
+
+    pub fn reconnect(&mut self) -> io::Result<()>{
+        self.stream = None;
+        self.session = None;
+        let stream = TcpStream::connect(&self.addrs[..])?;
+        stream.set_nodelay(true)?;
+        if let Some(hello) = &self.hello{
+            write_hello(&mut &stream, hello)?;
+            self.session = Some(negotiate(hello, &read_hello(&mut &stream)?)?);
+        }
+        self.stream = Some(BufWriter::new(stream));
+        Ok(())
+    }
+
+    pub fn is_connected(&self) -> bool{
+        self.stream.is_some()
+    }
+
+    // None without a handshake
+    pub fn session(&self) -> Option<&Session>{
+        self.session.as_ref()
+    }
+
+    pub fn push(&mut self, packet: FlowwPacket) -> io::Result<()>{
+        if let FlowwPacket::Track(_) | FlowwPacket::TrackId(_) = packet{
+            self.header = Some(packet.clone());
+            self.resume = false;
+        } else if self.resume && packet.has_points() {
+            self.resume = false;
+            if let Some(header) = self.header.clone(){
+                self.batch.push(header);
+            }
+        }
+        self.batch.push(packet);
+        if self.batch.len() >= self.batch_size {
+            self.flush()?;
+            self.resume = true;
+        }
+        Ok(())
+    }
+
+    pub fn send(&mut self, packets: Vec<FlowwPacket>) -> io::Result<()>{
+        if let Some(header) = packets.iter().rev().find(|p| matches!(p, FlowwPacket::Track(_) | FlowwPacket::TrackId(_))){
+            self.header = Some(header.clone());
+            self.resume = false;
+        }
+        self.batch.extend(packets);
+        self.flush()
+    }
+
+    // on error the batch is kept so nothing is lost when the caller retries
+    pub fn flush(&mut self) -> io::Result<()>{
+        if self.batch.is_empty() { return Ok(()); }
+        let first = match &mut self.stream{
+            Some(stream) => write_frame(stream, &self.batch),
+            None => Err(io::ErrorKind::NotConnected.into()),
+        };
+        if first.is_err() {
+            self.reconnect()?;
+            if let Some(stream) = &mut self.stream{
+                write_frame(stream, &self.batch)?;
+            }
+        }
+        self.batch.clear();
+        Ok(())
+    }
+}
+
+// serves one sender at a time, when it disconnects the next connection is accepted
+pub struct FlowwReceiver{
+    listener: TcpListener,
+    stream: Option<BufReader<TcpStream>>,
+    hello: Option<Hello>,
+    session: Option<Session>,
+}
+
+impl FlowwReceiver{
+    pub fn bind<A: ToSocketAddrs>(addr: A) -> io::Result<Self>{
+        Ok(Self{ listener: TcpListener::bind(addr)?, stream: None, hello: None, session: None })
+    }
+
+    // every sender has to shake hands first, an incompatible one still gets our hello so it
+    // can fail too and recv returns the error
+    pub fn with_hello(mut self, hello: Hello) -> Self{
+        self.hello = Some(hello);
+        self
+    }
+
+    pub fn session(&self) -> Option<&Session>{
+        self.session.as_ref()
+    }
+
+    fn accept(&mut self) -> io::Result<TcpStream>{
+        let (stream, _) = self.listener.accept()?;
+        stream.set_nodelay(true)?;
+        self.session = None;
+        if let Some(hello) = &self.hello{
+            let theirs = read_hello(&mut &stream)?;
+            write_hello(&mut &stream, hello)?;
+            self.session = Some(negotiate(hello, &theirs)?);
+        }
+        Ok(stream)
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr>{
+        self.listener.local_addr()
+    }
+
+    pub fn is_connected(&self) -> bool{
+        self.stream.is_some()
+    }
+
+    // blocks until a batch arrives
+    pub fn recv(&mut self) -> io::Result<Vec<FlowwPacket>>{
+        loop{
+            let stream = match &mut self.stream{
+                Some(stream) => stream,
+                None => {
+                    let stream = self.accept()?;
+                    self.stream.insert(BufReader::new(stream))
+                },
+            };
+            match read_frame(stream){
+                Ok(Some(batch)) => return Ok(batch),
+                Ok(None) => self.stream = None,
+                Err(e) if e.kind() == io::ErrorKind::InvalidData => {
+                    self.stream = None;
+                    return Err(e);
+                },
+                Err(_) => self.stream = None,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+    #[test]
+    fn frames(){
+        let packets = vec![FlowwPacket::Track("kick".to_string()), FlowwPacket::Point((0, 0.0, 36.0, 1.0))];
+        let mut buf = Vec::new();
+        write_frame(&mut buf, &packets).unwrap();
+        write_frame(&mut buf, &[]).unwrap();
+        let mut r = &buf[..];
+        assert_eq!(read_frame(&mut r).unwrap(), Some(packets));
+        assert_eq!(read_frame(&mut r).unwrap(), Some(vec![]));
+        assert_eq!(read_frame(&mut r).unwrap(), None);
+        let mut cut = &buf[..6];
+        assert!(read_frame(&mut cut).is_err());
+    }
+
+    #[test]
+    fn tcp(){
+        let mut receiver = FlowwReceiver::bind("127.0.0.1:0").unwrap();
+        let addr = receiver.local_addr().unwrap();
+        let handle = std::thread::spawn(move || {
+            let mut a = FlowwSender::connect(addr).unwrap().with_batch_size(2);
+            a.push(FlowwPacket::Track("kick".to_string())).unwrap();
+            a.push(FlowwPacket::Point((0, 0.0, 36.0, 1.0))).unwrap();
+            a.push(FlowwPacket::Msg("rest".to_string())).unwrap();
+            a.flush().unwrap();
+            drop(a);
+            let mut b = FlowwSender::connect(addr).unwrap();
+            b.send(vec![FlowwPacket::Msg("second".to_string())]).unwrap();
+        });
+        assert_eq!(receiver.recv().unwrap().len(), 2);
+        assert_eq!(receiver.recv().unwrap(), vec![FlowwPacket::Msg("rest".to_string())]);
+        assert_eq!(receiver.recv().unwrap(), vec![FlowwPacket::Msg("second".to_string())]);
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn split_batches(){
+        let mut receiver = FlowwReceiver::bind("127.0.0.1:0").unwrap();
+        let addr = receiver.local_addr().unwrap();
+        let handle = std::thread::spawn(move || {
+            let mut a = FlowwSender::connect(addr).unwrap().with_batch_size(2);
+            a.push(FlowwPacket::Track("kick".to_string())).unwrap();
+            a.push(FlowwPacket::Point((0, 0.0, 36.0, 1.0))).unwrap();
+            a.push(FlowwPacket::Track("keys".to_string())).unwrap();
+            for i in 0..3{
+                a.push(FlowwPacket::Point((0, i as f32, 60.0, 1.0))).unwrap();
+            }
+            a.flush().unwrap();
+        });
+        let (mut kick, mut keys) = (0, 0);
+        for _ in 0..4{
+            let batch = receiver.recv().unwrap();
+            assert!(matches!(batch[0], FlowwPacket::Track(_)));
+            // every batch on its own, as a receiver that starts listening halfway sees it
+            let mut sheet = FlowwSheet::new();
+            sheet.add(Vec::new(), "kick".to_string());
+            sheet.add(Vec::new(), "keys".to_string());
+            sheet.unpacket(batch, UnknownTrackPolicy::Drop);
+            kick += sheet.get_floww_ref_by_name("kick").len();
+            keys += sheet.get_floww_ref_by_name("keys").len();
+        }
+        handle.join().unwrap();
+        assert_eq!((kick, keys), (1, 3));
+    }
+
+    #[test]
+    fn handshake(){
+        let hello = Hello::new(vec!["kick".to_string()]);
+        let mut buf = Vec::new();
+        write_hello(&mut buf, &hello).unwrap();
+        assert_eq!(read_hello(&mut &buf[..]).unwrap(), hello);
+        let mut garbage = &b"GET / HTTP/1.1\r\n"[..];
+        assert_eq!(read_hello(&mut garbage).unwrap_err().kind(), std::io::ErrorKind::InvalidData);
+        let old = Hello{ version: 0, features: vec!["transport".to_string(), "midi".to_string()], tracks: vec![] };
+        assert!(negotiate(&hello, &old).unwrap_err().to_string().contains("version 0"));
+        let session = negotiate(&hello, &Hello{ version: PROTOCOL_VERSION, ..old.clone() }).unwrap();
+        assert_eq!(session.features, vec!["transport".to_string()]);
+        assert!(!session.has_feature("heartbeat"));
+
+        let mut receiver = FlowwReceiver::bind("127.0.0.1:0").unwrap().with_hello(Hello::new(vec!["keys".to_string()]));
+        let addr = receiver.local_addr().unwrap();
+        let handle = std::thread::spawn(move || {
+            let mut a = FlowwSender::connect_with_hello(addr, Hello::new(vec!["kick".to_string()])).unwrap();
+            assert_eq!(a.session().unwrap().peer_tracks, vec!["keys".to_string()]);
+            a.send(vec![FlowwPacket::Msg("hi".to_string())]).unwrap();
+            drop(a);
+            assert!(FlowwSender::connect_with_hello(addr, old).is_err());
+        });
+        assert_eq!(receiver.recv().unwrap(), vec![FlowwPacket::Msg("hi".to_string())]);
+        assert_eq!(receiver.session().unwrap().peer_tracks, vec!["kick".to_string()]);
+        assert!(receiver.recv().is_err());
+        handle.join().unwrap();
+    }
+}
@@ -0,0 +1,371 @@
+use crate::{ Point, Floww };
+
+use std::collections::{ HashMap, VecDeque };
+
+// (note on index, note off index)
+pub type Span = (usize, usize);
+
+// assumes sorted, a note on (vel > 0) is closed by the first following note off (vel 0)
+// with the same id and note, unmatched points are not part of any span
+pub fn note_spans(floww: &[Point]) -> Vec<Span>{
+    let mut open: HashMap<(usize, u32), VecDeque<usize>> = HashMap::new();
+    let mut spans = Vec::new();
+    for (i, p) in floww.iter().enumerate(){
+        let key = (p.0, p.2.to_bits());
+        if p.3 > 0.0 {
+            open.entry(key).or_default().push_back(i);
+        } else if let Some(on) = open.get_mut(&key).and_then(|q| q.pop_front()){
+            spans.push((on, i));
+        }
+    }
+    spans.sort_unstable();
+    spans
+}
+
+// like sort, but at equal times note offs come before note ons so retriggers don't get stuck
+pub(crate) fn sort_offs_first(floww: &mut Floww){
+    floww.sort_by(|a, b| a.1.total_cmp(&b.1).then((a.3 > 0.0).cmp(&(b.3 > 0.0))));
+}
+
+#[derive(Clone,Copy,PartialEq,Eq,Debug)]
+pub enum PolyphonyPolicy{
+    // notes that start when all voices are busy are removed
+    DropNewest,
+    // the longest sounding note is cut short to free its voice
+    StealOldest,
+}
+
+#[derive(Clone,Copy,PartialEq,Eq,Debug)]
+pub enum StrumDirection{
+    // lowest note first
+    Up,
+    // highest note first
+    Down,
+}
+
+#[derive(Clone,Copy,PartialEq,Eq,Debug)]
+pub enum MonoPolicy{
+    HighestNote,
+    LastNote,
+    LoudestNote,
+}
+
+// at most one paired note sounds at a time: a note starting while another sounds either cuts it
+// or is dropped, depending on the policy, unpaired points are kept as is
+pub fn to_monophonic(floww: &[Point], policy: MonoPolicy) -> Floww{
+    let mut res = floww.to_vec();
+    sort_offs_first(&mut res);
+    let mut remove = vec![false; res.len()];
+    let mut current: Option<Span> = None;
+    for (on, off) in note_spans(&res){
+        let t = res[on].1;
+        if let Some((con, coff)) = current{
+            if res[coff].1 > t {
+                let replace = match policy{
+                    MonoPolicy::HighestNote => res[on].2 > res[con].2,
+                    MonoPolicy::LastNote => true,
+                    MonoPolicy::LoudestNote => res[on].3 > res[con].3,
+                };
+                if !replace {
+                    remove[on] = true;
+                    remove[off] = true;
+                    continue;
+                }
+                if res[con].1 >= t {
+                    remove[con] = true;
+                    remove[coff] = true;
+                } else {
+                    res[coff].1 = t;
+                }
+            }
+        }
+        current = Some((on, off));
+    }
+    retain_unmarked(&mut res, &remove);
+    sort_offs_first(&mut res);
+    res
+}
+
+// every note goes to the free voice whose previous note is closest in pitch, unused voices come
+// after used ones, notes that find no free voice are dropped, always returns max_voices flowws
+pub fn split_voices(floww: &[Point], max_voices: usize) -> Vec<Floww>{
+    let mut sorted = floww.to_vec();
+    sort_offs_first(&mut sorted);
+    let mut offs = vec![None; sorted.len()];
+    for (on, off) in note_spans(&sorted){
+        offs[on] = Some(off);
+    }
+    let mut voices = vec![Vec::new(); max_voices];
+    // (last onset, busy until, last note)
+    let mut state: Vec<Option<(f32, f32, f32)>> = vec![None; max_voices];
+    for (i, p) in sorted.iter().enumerate().filter(|(_, p)| p.3 > 0.0){
+        let t = p.1;
+        let end = offs[i].map(|off| sorted[off].1).unwrap_or(t);
+        let choice = (0..max_voices).filter(|v| match state[*v]{
+            Some((onset, busy, _)) => onset < t && busy <= t,
+            None => true,
+        }).min_by(|a, b| {
+            let dist = |v: usize| state[v].map(|s| (s.2 - p.2).abs()).unwrap_or(f32::INFINITY);
+            dist(*a).total_cmp(&dist(*b))
+        });
+        if let Some(v) = choice {
+            state[v] = Some((t, end, p.2));
+            voices[v].push(*p);
+            if let Some(off) = offs[i]{
+                voices[v].push(sorted[off]);
+            }
+        }
+    }
+    voices.iter_mut().for_each(sort_offs_first);
+    voices
+}
+
+// removes the points whose flag is set
+pub(crate) fn retain_unmarked(floww: &mut Floww, remove: &[bool]){
+    let mut i = 0;
+    floww.retain(|_| {
+        i += 1;
+        !remove[i - 1]
+    });
+}
+
+pub trait NoteVec{
+    fn note_spans(&self) -> Vec<Span>;
+    fn legato(&mut self, gap: f32);
+    fn fix_overlaps(&mut self);
+    fn limit_polyphony(&mut self, max_voices: usize, policy: PolyphonyPolicy);
+    fn strum(&mut self, spread: f32, direction: StrumDirection);
+    fn scale_gate(&mut self, factor: f32);
+}
+
+impl NoteVec for Floww{
+    fn note_spans(&self) -> Vec<Span>{
+        note_spans(self)
+    }
+
+    // every note is held until gap before the next later onset, the last notes are kept as is
+    fn legato(&mut self, gap: f32){
+        let spans = note_spans(self);
+        let mut onsets = spans.iter().map(|(on, _)| self[*on].1).collect::<Vec<_>>();
+        onsets.sort_by(|a, b| a.total_cmp(b));
+        for (on, off) in spans{
+            let t = self[on].1;
+            let i = onsets.partition_point(|o| *o <= t);
+            if let Some(next) = onsets.get(i){
+                self[off].1 = (next - gap).max(t);
+            }
+        }
+        sort_offs_first(self);
+    }
+
+    // a note that is retriggered while still sounding is cut at the retrigger
+    fn fix_overlaps(&mut self){
+        let spans = note_spans(self);
+        let mut last: HashMap<(usize, u32), Span> = HashMap::new();
+        for (on, off) in spans{
+            let key = (self[on].0, self[on].2.to_bits());
+            let t = self[on].1;
+            if let Some((_, prev_off)) = last.get(&key){
+                if self[*prev_off].1 > t {
+                    self[*prev_off].1 = t;
+                }
+            }
+            last.insert(key, (on, off));
+        }
+        sort_offs_first(self);
+    }
+
+    // only paired notes take up a voice
+    fn limit_polyphony(&mut self, max_voices: usize, policy: PolyphonyPolicy){
+        let spans = note_spans(self);
+        let mut remove = vec![false; self.len()];
+        let mut active: Vec<Span> = Vec::new();
+        for (on, off) in spans{
+            let t = self[on].1;
+            active.retain(|(_, o)| self[*o].1 > t);
+            if active.len() < max_voices {
+                active.push((on, off));
+                continue;
+            }
+            match policy{
+                PolyphonyPolicy::DropNewest => {
+                    remove[on] = true;
+                    remove[off] = true;
+                },
+                PolyphonyPolicy::StealOldest => {
+                    if active.is_empty() {
+                        remove[on] = true;
+                        remove[off] = true;
+                        continue;
+                    }
+                    let (_, oldest_off) = active.remove(0);
+                    self[oldest_off].1 = t;
+                    active.push((on, off));
+                },
+            }
+        }
+        retain_unmarked(self, &remove);
+        sort_offs_first(self);
+    }
+
+    // note ons at the same time are offset by spread per note, their note offs move along
+    fn strum(&mut self, spread: f32, direction: StrumDirection){
+        let mut offs = vec![None; self.len()];
+        for (on, off) in note_spans(self){
+            offs[on] = Some(off);
+        }
+        let mut groups: HashMap<u32, Vec<usize>> = HashMap::new();
+        for (i, p) in self.iter().enumerate().filter(|(_, p)| p.3 > 0.0){
+            groups.entry(p.1.to_bits()).or_default().push(i);
+        }
+        for (_, mut group) in groups{
+            group.sort_by(|a, b| self[*a].2.total_cmp(&self[*b].2));
+            if direction == StrumDirection::Down {
+                group.reverse();
+            }
+            for (k, i) in group.into_iter().enumerate(){
+                let shift = spread * k as f32;
+                self[i].1 += shift;
+                if let Some(off) = offs[i]{
+                    self[off].1 += shift;
+                }
+            }
+        }
+        sort_offs_first(self);
+    }
+
+    // onsets stay, note lengths are multiplied by factor
+    fn scale_gate(&mut self, factor: f32){
+        let factor = factor.max(0.0);
+        for (on, off) in note_spans(self){
+            self[off].1 = self[on].1 + (self[off].1 - self[on].1) * factor;
+        }
+        sort_offs_first(self);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+    #[test]
+    fn spans(){
+        let f = vec![
+            (0, 0.0, 60.0, 1.0), (0, 0.0, 64.0, 1.0), (0, 0.5, 60.0, 0.0),
+            (1, 1.0, 60.0, 0.0), (0, 1.0, 64.0, 0.0), (0, 2.0, 67.0, 1.0),
+        ];
+        assert_eq!(f.note_spans(), vec![(0, 2), (1, 4)]);
+    }
+
+    #[test]
+    fn legato(){
+        let mut f = vec![
+            (0, 0.0, 60.0, 1.0), (0, 0.25, 60.0, 0.0),
+            (0, 1.0, 62.0, 1.0), (0, 1.25, 62.0, 0.0),
+            (0, 2.0, 64.0, 1.0), (0, 2.25, 64.0, 0.0),
+        ];
+        f.legato(0.1);
+        assert_eq!(f, vec![
+            (0, 0.0, 60.0, 1.0), (0, 0.9, 60.0, 0.0),
+            (0, 1.0, 62.0, 1.0), (0, 1.9, 62.0, 0.0),
+            (0, 2.0, 64.0, 1.0), (0, 2.25, 64.0, 0.0),
+        ]);
+    }
+
+    #[test]
+    fn fix_overlaps(){
+        let mut f = vec![
+            (0, 0.0, 60.0, 1.0), (0, 1.0, 60.0, 0.5), (0, 1.5, 60.0, 0.0), (0, 2.0, 60.0, 0.0),
+        ];
+        f.fix_overlaps();
+        assert_eq!(f, vec![
+            (0, 0.0, 60.0, 1.0), (0, 1.0, 60.0, 0.0), (0, 1.0, 60.0, 0.5), (0, 2.0, 60.0, 0.0),
+        ]);
+    }
+
+    #[test]
+    fn limit_polyphony(){
+        let f = vec![
+            (0, 0.0, 60.0, 1.0), (0, 0.5, 64.0, 1.0), (0, 1.0, 67.0, 1.0),
+            (0, 2.0, 60.0, 0.0), (0, 2.0, 64.0, 0.0), (0, 2.0, 67.0, 0.0),
+            (0, 3.0, 72.0, 1.0), (0, 4.0, 72.0, 0.0),
+        ];
+        let mut a = f.clone();
+        a.limit_polyphony(2, PolyphonyPolicy::DropNewest);
+        assert_eq!(a, vec![
+            (0, 0.0, 60.0, 1.0), (0, 0.5, 64.0, 1.0),
+            (0, 2.0, 60.0, 0.0), (0, 2.0, 64.0, 0.0),
+            (0, 3.0, 72.0, 1.0), (0, 4.0, 72.0, 0.0),
+        ]);
+        let mut b = f.clone();
+        b.limit_polyphony(2, PolyphonyPolicy::StealOldest);
+        assert_eq!(b, vec![
+            (0, 0.0, 60.0, 1.0), (0, 0.5, 64.0, 1.0), (0, 1.0, 60.0, 0.0), (0, 1.0, 67.0, 1.0),
+            (0, 2.0, 64.0, 0.0), (0, 2.0, 67.0, 0.0),
+            (0, 3.0, 72.0, 1.0), (0, 4.0, 72.0, 0.0),
+        ]);
+        let mut c = f;
+        c.limit_polyphony(0, PolyphonyPolicy::StealOldest);
+        assert_eq!(c, vec![]);
+    }
+
+    #[test]
+    fn strum(){
+        let f = vec![
+            (0, 0.0, 64.0, 1.0), (0, 0.0, 60.0, 1.0), (0, 0.0, 67.0, 1.0),
+            (0, 1.0, 60.0, 0.0), (0, 1.0, 64.0, 0.0), (0, 1.0, 67.0, 0.0),
+        ];
+        let mut a = f.clone();
+        a.strum(0.25, StrumDirection::Up);
+        assert_eq!(a, vec![
+            (0, 0.0, 60.0, 1.0), (0, 0.25, 64.0, 1.0), (0, 0.5, 67.0, 1.0),
+            (0, 1.0, 60.0, 0.0), (0, 1.25, 64.0, 0.0), (0, 1.5, 67.0, 0.0),
+        ]);
+        let mut b = f;
+        b.strum(0.25, StrumDirection::Down);
+        assert_eq!(b[0], (0, 0.0, 67.0, 1.0));
+        assert_eq!(b[2], (0, 0.5, 60.0, 1.0));
+    }
+
+    #[test]
+    fn monophonic(){
+        let f = vec![
+            (0, 0.0, 60.0, 0.5), (0, 0.0, 64.0, 1.0), (0, 0.5, 67.0, 0.8),
+            (0, 1.0, 60.0, 0.0), (0, 1.0, 64.0, 0.0), (0, 1.0, 67.0, 0.0),
+        ];
+        assert_eq!(to_monophonic(&f, MonoPolicy::LastNote), vec![
+            (0, 0.0, 64.0, 1.0), (0, 0.5, 64.0, 0.0), (0, 0.5, 67.0, 0.8), (0, 1.0, 67.0, 0.0),
+        ]);
+        assert_eq!(to_monophonic(&f, MonoPolicy::HighestNote), vec![
+            (0, 0.0, 64.0, 1.0), (0, 0.5, 64.0, 0.0), (0, 0.5, 67.0, 0.8), (0, 1.0, 67.0, 0.0),
+        ]);
+        assert_eq!(to_monophonic(&f, MonoPolicy::LoudestNote), vec![
+            (0, 0.0, 64.0, 1.0), (0, 1.0, 64.0, 0.0),
+        ]);
+    }
+
+    #[test]
+    fn voices(){
+        let f = vec![
+            (0, 0.0, 60.0, 1.0), (0, 0.0, 72.0, 1.0), (0, 0.0, 84.0, 1.0),
+            (0, 1.0, 60.0, 0.0), (0, 1.0, 72.0, 0.0), (0, 1.0, 84.0, 0.0),
+            (0, 1.0, 71.0, 1.0), (0, 1.0, 59.0, 1.0), (0, 2.0, 71.0, 0.0), (0, 2.0, 59.0, 0.0),
+        ];
+        let v = split_voices(&f, 2);
+        assert_eq!(v, vec![
+            vec![(0, 0.0, 60.0, 1.0), (0, 1.0, 60.0, 0.0), (0, 1.0, 59.0, 1.0), (0, 2.0, 59.0, 0.0)],
+            vec![(0, 0.0, 72.0, 1.0), (0, 1.0, 72.0, 0.0), (0, 1.0, 71.0, 1.0), (0, 2.0, 71.0, 0.0)],
+        ]);
+        assert_eq!(split_voices(&f, 0), Vec::<Floww>::new());
+    }
+
+    #[test]
+    fn gate(){
+        let f = vec![(0, 0.0, 60.0, 1.0), (0, 1.0, 60.0, 0.0), (0, 1.0, 62.0, 1.0), (0, 1.5, 62.0, 0.0)];
+        let mut a = f.clone();
+        a.scale_gate(0.5);
+        assert_eq!(a, vec![(0, 0.0, 60.0, 1.0), (0, 0.5, 60.0, 0.0), (0, 1.0, 62.0, 1.0), (0, 1.25, 62.0, 0.0)]);
+        let mut b = f;
+        b.scale_gate(2.0);
+        assert_eq!(b, vec![(0, 0.0, 60.0, 1.0), (0, 1.0, 62.0, 1.0), (0, 2.0, 60.0, 0.0), (0, 2.0, 62.0, 0.0)]);
+    }
+}
@@ -0,0 +1,220 @@
+use crate::{ FlowwPacket, RichPoint, TrackIds, ControlValue };
+
+use rosc::{ OscMessage, OscPacket, OscBundle, OscType, OscTime, OscError };
+
+// points become /floww/<track>/point with (int id, float time, float note, float vel), rich points
+// add an (int key, float value) pair per parameter,
+// messages become /floww/msg and heartbeats /floww/heartbeat with one string, points before any track use /floww/point,
+// points after an unknown track id are skipped,
+// transport goes to /floww/start, /floww/stop and /floww/seek, tempo, position with one float
+pub fn packets_to_osc(packets: &[FlowwPacket]) -> Vec<OscMessage>{
+    let mut addr = Some("/floww/point".to_string());
+    let mut ids = TrackIds::new();
+    let mut res = Vec::new();
+    for packet in packets{
+        match packet{
+            FlowwPacket::Msg(msg) => res.push(OscMessage{
+                addr: "/floww/msg".to_string(),
+                args: vec![OscType::String(msg.clone())],
+            }),
+            FlowwPacket::Track(name) => addr = Some(format!("/floww/{}/point", name)),
+            FlowwPacket::RichPoint(RichPoint{ point: (id, time, note, vel), params }) => if let Some(addr) = &addr{
+                let mut args = vec![OscType::Int(*id as i32), OscType::Float(*time), OscType::Float(*note), OscType::Float(*vel)];
+                for (key, value) in params{
+                    args.push(OscType::Int(*key as i32));
+                    args.push(OscType::Float(*value));
+                }
+                res.push(OscMessage{ addr: addr.clone(), args });
+            },
+            FlowwPacket::Point(_) | FlowwPacket::Points(_) | FlowwPacket::Deltas(_) => if let Some(addr) = &addr{
+                for (id, time, note, vel) in packet.points().iter(){
+                    res.push(OscMessage{
+                        addr: addr.clone(),
+                        args: vec![OscType::Int(*id as i32), OscType::Float(*time), OscType::Float(*note), OscType::Float(*vel)],
+                    });
+                }
+            },
+            FlowwPacket::Start => res.push(OscMessage{ addr: "/floww/start".to_string(), args: vec![] }),
+            FlowwPacket::Stop => res.push(OscMessage{ addr: "/floww/stop".to_string(), args: vec![] }),
+            FlowwPacket::Seek(t) => res.push(OscMessage{ addr: "/floww/seek".to_string(), args: vec![OscType::Float(*t)] }),
+            FlowwPacket::Tempo(bpm) => res.push(OscMessage{ addr: "/floww/tempo".to_string(), args: vec![OscType::Float(*bpm)] }),
+            FlowwPacket::Position(t) => res.push(OscMessage{ addr: "/floww/position".to_string(), args: vec![OscType::Float(*t)] }),
+            FlowwPacket::Heartbeat(id) => res.push(OscMessage{
+                addr: "/floww/heartbeat".to_string(),
+                args: vec![OscType::String(id.clone())],
+            }),
+            FlowwPacket::Clear(name) => res.push(OscMessage{
+                addr: "/floww/clear".to_string(),
+                args: vec![OscType::String(name.clone())],
+            }),
+            FlowwPacket::ClearAll => res.push(OscMessage{ addr: "/floww/clear_all".to_string(), args: vec![] }),
+            FlowwPacket::Timestamp(t) => res.push(OscMessage{ addr: "/floww/timestamp".to_string(), args: vec![OscType::Float(*t)] }),
+            FlowwPacket::Alias{ from, to } => res.push(OscMessage{
+                addr: "/floww/alias".to_string(),
+                args: vec![OscType::String(from.clone()), OscType::String(to.clone())],
+            }),
+            FlowwPacket::Flush => res.push(OscMessage{ addr: "/floww/flush".to_string(), args: vec![] }),
+            FlowwPacket::Control{ key, value } => res.push(OscMessage{
+                addr: format!("/floww/control/{}", key),
+                args: vec![match value{
+                    ControlValue::Int(x) => OscType::Long(*x),
+                    ControlValue::Float(x) => OscType::Float(*x),
+                    ControlValue::Bool(b) => OscType::Bool(*b),
+                    ControlValue::Text(s) => OscType::String(s.clone()),
+                }],
+            }),
+            FlowwPacket::DefineTrack(id, name) => ids.define(*id, name.clone()),
+            FlowwPacket::TrackId(id) => addr = ids.name(*id).map(|name| format!("/floww/{}/point", name)),
+        }
+    }
+    res
+}
+
+fn osc_number(arg: &OscType) -> Option<f64>{
+    match arg{
+        OscType::Int(x) => Some(*x as f64),
+        OscType::Long(x) => Some(*x as f64),
+        OscType::Float(x) => Some(*x as f64),
+        OscType::Double(x) => Some(*x),
+        _ => None,
+    }
+}
+
+// numbers of any osc type are accepted since controllers tend to send everything as floats,
+// a track packet is emitted whenever the track changes, other addresses are skipped
+pub fn osc_to_packets(msgs: &[OscMessage]) -> Vec<FlowwPacket>{
+    let mut current: Option<String> = None;
+    let mut res = Vec::new();
+    for msg in msgs{
+        if msg.addr == "/floww/msg" || msg.addr == "/floww/heartbeat" || msg.addr == "/floww/clear" {
+            if let Some(OscType::String(s)) = msg.args.first(){
+                res.push(match msg.addr.as_str(){
+                    "/floww/msg" => FlowwPacket::Msg(s.clone()),
+                    "/floww/heartbeat" => FlowwPacket::Heartbeat(s.clone()),
+                    _ => FlowwPacket::Clear(s.clone()),
+                });
+            }
+            continue;
+        }
+        if msg.addr == "/floww/alias" {
+            if let [OscType::String(from), OscType::String(to)] = &msg.args[..]{
+                res.push(FlowwPacket::Alias{ from: from.clone(), to: to.clone() });
+            }
+            continue;
+        }
+        if let Some(key) = msg.addr.strip_prefix("/floww/control/"){
+            let value = match msg.args.first(){
+                Some(OscType::Int(x)) => ControlValue::Int(*x as i64),
+                Some(OscType::Long(x)) => ControlValue::Int(*x),
+                Some(OscType::Float(x)) => ControlValue::Float(*x),
+                Some(OscType::Double(x)) => ControlValue::Float(*x as f32),
+                Some(OscType::Bool(b)) => ControlValue::Bool(*b),
+                Some(OscType::String(s)) => ControlValue::Text(s.clone()),
+                _ => continue,
+            };
+            res.push(FlowwPacket::Control{ key: key.to_string(), value });
+            continue;
+        }
+        let arg = msg.args.first().and_then(osc_number).map(|x| x as f32);
+        let transport = match (msg.addr.as_str(), arg){
+            ("/floww/start", _) => Some(FlowwPacket::Start),
+            ("/floww/stop", _) => Some(FlowwPacket::Stop),
+            ("/floww/clear_all", _) => Some(FlowwPacket::ClearAll),
+            ("/floww/flush", _) => Some(FlowwPacket::Flush),
+            ("/floww/timestamp", Some(t)) => Some(FlowwPacket::Timestamp(t)),
+            ("/floww/seek", Some(t)) => Some(FlowwPacket::Seek(t)),
+            ("/floww/tempo", Some(bpm)) => Some(FlowwPacket::Tempo(bpm)),
+            ("/floww/position", Some(t)) => Some(FlowwPacket::Position(t)),
+            _ => None,
+        };
+        if let Some(transport) = transport{
+            res.push(transport);
+            continue;
+        }
+        let track = if msg.addr == "/floww/point" {
+            None
+        } else if let Some(track) = msg.addr.strip_prefix("/floww/").and_then(|a| a.strip_suffix("/point")){
+            Some(track.to_string())
+        } else {
+            continue;
+        };
+        let nums = msg.args.iter().map(osc_number).collect::<Option<Vec<_>>>();
+        let nums = match nums{
+            Some(nums) if nums.len() >= 4 && nums.len() % 2 == 0 => nums,
+            _ => continue,
+        };
+        if track.is_some() && track != current {
+            res.push(FlowwPacket::Track(track.clone().unwrap_or_default()));
+            current = track;
+        }
+        let point = (nums[0].max(0.0) as usize, nums[1] as f32, nums[2] as f32, nums[3] as f32);
+        if nums.len() == 4 {
+            res.push(FlowwPacket::Point(point));
+        } else {
+            let params = nums[4..].chunks(2).map(|kv| (kv[0].clamp(0.0, 255.0) as u8, kv[1] as f32)).collect();
+            res.push(FlowwPacket::RichPoint(RichPoint{ point, params }));
+        }
+    }
+    res
+}
+
+// one bundle with an immediate time tag, ready to be sent as a udp datagram
+pub fn encode_osc(packets: &[FlowwPacket]) -> Result<Vec<u8>, OscError>{
+    let content = packets_to_osc(packets).into_iter().map(OscPacket::Message).collect();
+    rosc::encoder::encode(&OscPacket::Bundle(OscBundle{ timetag: OscTime{ seconds: 0, fractional: 1 }, content }))
+}
+
+fn flatten_osc(packet: OscPacket, msgs: &mut Vec<OscMessage>){
+    match packet{
+        OscPacket::Message(msg) => msgs.push(msg),
+        OscPacket::Bundle(bundle) => bundle.content.into_iter().for_each(|p| flatten_osc(p, msgs)),
+    }
+}
+
+// takes a single message or a (nested) bundle
+pub fn decode_osc(bytes: &[u8]) -> Result<Vec<FlowwPacket>, OscError>{
+    let (_, packet) = rosc::decoder::decode_udp(bytes)?;
+    let mut msgs = Vec::new();
+    flatten_osc(packet, &mut msgs);
+    Ok(osc_to_packets(&msgs))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+    use rosc::{ OscMessage, OscType };
+    #[test]
+    fn osc(){
+        let packets = vec![
+            FlowwPacket::Track("kick".to_string()), FlowwPacket::Point((1, 0.5, 36.0, 1.0)),
+            FlowwPacket::Msg("hi".to_string()), FlowwPacket::Point((1, 1.0, 36.0, 0.0)),
+            FlowwPacket::Track("keys".to_string()), FlowwPacket::Point((0, 0.0, 60.0, 0.5)),
+            FlowwPacket::Start, FlowwPacket::Tempo(120.0), FlowwPacket::Seek(4.0),
+            FlowwPacket::Heartbeat("laptop".to_string()), FlowwPacket::Clear("keys".to_string()),
+            FlowwPacket::control("swing", true), FlowwPacket::control("bpm", 120.5), FlowwPacket::Flush,
+            FlowwPacket::Alias{ from: "bd".to_string(), to: "kick".to_string() },
+            FlowwPacket::RichPoint(RichPoint::new((0, 2.0, 60.0, 1.0)).with(PARAM_PAN, -0.5)),
+        ];
+        let msgs = packets_to_osc(&packets);
+        assert_eq!(msgs.len(), 14);
+        assert_eq!(msgs[0].addr, "/floww/kick/point");
+        assert_eq!(osc_to_packets(&msgs), packets);
+        assert_eq!(decode_osc(&encode_osc(&packets).unwrap()).unwrap(), packets);
+        let from_controller = vec![
+            OscMessage{ addr: "/floww/pad/point".to_string(), args: vec![
+                OscType::Float(2.0), OscType::Double(0.25), OscType::Long(40), OscType::Float(0.5),
+            ] },
+            OscMessage{ addr: "/other".to_string(), args: vec![] },
+            OscMessage{ addr: "/floww/pad/point".to_string(), args: vec![OscType::Float(1.0)] },
+        ];
+        assert_eq!(osc_to_packets(&from_controller), vec![
+            FlowwPacket::Track("pad".to_string()), FlowwPacket::Point((2, 0.25, 40.0, 0.5)),
+        ]);
+        let by_id = packets_to_osc(&[
+            FlowwPacket::DefineTrack(0, "kick".to_string()), FlowwPacket::TrackId(0), FlowwPacket::Point((1, 0.5, 36.0, 1.0)),
+            FlowwPacket::TrackId(3), FlowwPacket::Point((1, 1.0, 36.0, 1.0)), FlowwPacket::Msg("hi".to_string()),
+            FlowwPacket::TrackId(0), FlowwPacket::Point((1, 1.5, 36.0, 0.0)),
+        ]);
+        assert_eq!(by_id.iter().map(|m| m.addr.as_str()).collect::<Vec<_>>(), vec!["/floww/kick/point", "/floww/msg", "/floww/kick/point"]);
+    }
+}
@@ -0,0 +1,110 @@
+use crate::{ Point, RichPoint, FlowwPacket, MIN_VELOCITY };
+
+// a step between receiving batches and unpacketing them, filters can change, drop or add packets
+pub trait PacketFilter{
+    fn process(&mut self, packets: Vec<FlowwPacket>) -> Vec<FlowwPacket>;
+}
+
+// a closure maps packets one by one, None drops it
+impl<F: FnMut(FlowwPacket) -> Option<FlowwPacket>> PacketFilter for F{
+    fn process(&mut self, packets: Vec<FlowwPacket>) -> Vec<FlowwPacket>{
+        packets.into_iter().filter_map(self).collect()
+    }
+}
+
+// moves every point by this many semitones
+#[derive(Clone,Copy,PartialEq,Debug)]
+pub struct Transpose(pub f32);
+
+// applies f to the points of Point, Points, Deltas and RichPoint packets, Deltas come out as Points
+fn map_points<F: Fn(Point) -> Point>(packets: Vec<FlowwPacket>, f: F) -> Vec<FlowwPacket>{
+    packets.into_iter().map(|packet| match packet{
+        FlowwPacket::Point(p) => FlowwPacket::Point(f(p)),
+        FlowwPacket::RichPoint(r) => FlowwPacket::RichPoint(RichPoint{ point: f(r.point), ..r }),
+        FlowwPacket::Points(ps) => FlowwPacket::Points(ps.into_iter().map(&f).collect()),
+        FlowwPacket::Deltas(d) => FlowwPacket::Points(d.decode().into_iter().map(&f).collect()),
+        other => other,
+    }).collect()
+}
+
+impl PacketFilter for Transpose{
+    fn process(&mut self, packets: Vec<FlowwPacket>) -> Vec<FlowwPacket>{
+        map_points(packets, |(id, time, note, vel)| (id, time, note + self.0, vel))
+    }
+}
+
+// scales the velocity of note ons, they stay within [MIN_VELOCITY, 1] so an on never turns into an off
+#[derive(Clone,Copy,PartialEq,Debug)]
+pub struct VelocityScale(pub f32);
+
+impl PacketFilter for VelocityScale{
+    fn process(&mut self, packets: Vec<FlowwPacket>) -> Vec<FlowwPacket>{
+        map_points(packets, |(id, time, note, vel)| if vel > 0.0 {
+            (id, time, note, (vel * self.0).clamp(MIN_VELOCITY, 1.0))
+        } else {
+            (id, time, note, vel)
+        })
+    }
+}
+
+// runs the filters in the order they were added
+#[derive(Default)]
+pub struct Pipeline{
+    filters: Vec<Box<dyn PacketFilter + Send>>,
+}
+
+impl Pipeline{
+    pub fn new() -> Self{
+        Self::default()
+    }
+
+    pub fn then<F: PacketFilter + Send + 'static>(mut self, filter: F) -> Self{
+        self.filters.push(Box::new(filter));
+        self
+    }
+
+    pub fn len(&self) -> usize{
+        self.filters.len()
+    }
+
+    pub fn is_empty(&self) -> bool{
+        self.filters.is_empty()
+    }
+}
+
+impl PacketFilter for Pipeline{
+    fn process(&mut self, packets: Vec<FlowwPacket>) -> Vec<FlowwPacket>{
+        self.filters.iter_mut().fold(packets, |packets, filter| filter.process(packets))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+    #[test]
+    fn pipeline(){
+        let mut pipe = Pipeline::new()
+            .then(Transpose(2.0))
+            .then(VelocityScale(0.8))
+            .then(|p| if let FlowwPacket::Msg(_) = p { None } else { Some(p) });
+        assert_eq!(pipe.len(), 3);
+        let out = pipe.process(vec![
+            FlowwPacket::Track("keys".to_string()), FlowwPacket::Msg("dropped".to_string()),
+            FlowwPacket::Point((0, 0.0, 60.0, 0.5)), FlowwPacket::Point((0, 1.0, 60.0, 0.0)),
+        ]);
+        assert_eq!(out, vec![
+            FlowwPacket::Track("keys".to_string()),
+            FlowwPacket::Point((0, 0.0, 62.0, 0.4)), FlowwPacket::Point((0, 1.0, 62.0, 0.0)),
+        ]);
+        assert_eq!(VelocityScale(3.0).process(vec![FlowwPacket::Point((0, 0.0, 60.0, 0.5))]),
+            vec![FlowwPacket::Point((0, 0.0, 60.0, 1.0))]);
+        let mut sheet = FlowwSheet::new();
+        sheet.add(Vec::new(), "keys".to_string());
+        sheet.unpacket(pipe.process(out), UnknownTrackPolicy::Drop);
+        assert_eq!(sheet.get_floww_ref_by_name("keys")[0].2, 64.0);
+        assert!(Pipeline::new().is_empty());
+        let silent = VelocityScale(0.0).process(vec![FlowwPacket::Point((0, 0.0, 60.0, 0.5)), FlowwPacket::Point((0, 1.0, 60.0, 0.0))]);
+        let ons = silent.iter().map(|p| point_to_midi_bytes(&p.points()[0], 0)).collect::<Vec<_>>();
+        assert_eq!(ons, vec![[0x90, 60, 1], [0x80, 60, 0]]);
+    }
+}
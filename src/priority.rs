@@ -0,0 +1,109 @@
+use crate::{ FlowwPacket };
+
+// what a transport may let go of first when it can't keep up
+#[derive(Clone,Copy,PartialEq,Eq,PartialOrd,Ord,Debug,Hash)]
+pub enum Priority{
+    Droppable,
+    Normal,
+    Critical,
+}
+
+impl FlowwPacket{
+    // state and transport must arrive and so must note offs or notes hang, note ons, messages
+    // and heartbeats are normal, nothing is droppable on its own, see Priorities
+    pub fn priority(&self) -> Priority{
+        match self{
+            FlowwPacket::Msg(_) | FlowwPacket::Heartbeat(_) | FlowwPacket::Timestamp(_) => Priority::Normal,
+            _ if self.has_points() => if self.points().iter().any(|p| p.3 <= 0.0) {
+                Priority::Critical
+            } else {
+                Priority::Normal
+            },
+            _ => Priority::Critical,
+        }
+    }
+}
+
+// the sender side choice of what can go, points of droppable tracks (meters, visuals) are
+// droppable apart from their note offs
+#[derive(Clone,PartialEq,Eq,Debug,Default)]
+pub struct Priorities{
+    droppable: Vec<String>,
+}
+
+impl Priorities{
+    pub fn new() -> Self{
+        Self::default()
+    }
+
+    pub fn droppable(mut self, track: &str) -> Self{
+        self.set_droppable(track, true);
+        self
+    }
+
+    pub fn set_droppable(&mut self, track: &str, droppable: bool){
+        self.droppable.retain(|t| t != track);
+        if droppable {
+            self.droppable.push(track.to_string());
+        }
+    }
+
+    pub fn is_droppable(&self, track: &str) -> bool{
+        self.droppable.iter().any(|t| t == track)
+    }
+
+    pub fn classify(&self, track: Option<&str>, packet: &FlowwPacket) -> Priority{
+        let priority = packet.priority();
+        match track{
+            Some(track) if priority == Priority::Normal && packet.has_points() && self.is_droppable(track) => Priority::Droppable,
+            _ => priority,
+        }
+    }
+
+    // the priority of every packet, following the track headers
+    pub fn classify_all(&self, packets: &[FlowwPacket]) -> Vec<Priority>{
+        let mut track = None;
+        packets.iter().map(|packet| {
+            if let FlowwPacket::Track(name) = packet{
+                track = Some(name.as_str());
+            }
+            self.classify(track, packet)
+        }).collect()
+    }
+
+    // the packets with at least priority min, in order
+    pub fn shed(&self, packets: &[FlowwPacket], min: Priority) -> Vec<FlowwPacket>{
+        packets.iter().zip(self.classify_all(packets))
+            .filter(|(_, p)| *p >= min)
+            .map(|(packet, _)| packet.clone())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+    #[test]
+    fn priorities(){
+        assert_eq!(FlowwPacket::Stop.priority(), Priority::Critical);
+        assert_eq!(FlowwPacket::Point((0, 0.0, 60.0, 1.0)).priority(), Priority::Normal);
+        assert_eq!(FlowwPacket::Point((0, 0.0, 60.0, 0.0)).priority(), Priority::Critical);
+        assert_eq!(FlowwPacket::Points(vec![(0, 0.0, 60.0, 1.0), (0, 1.0, 60.0, 0.0)]).priority(), Priority::Critical);
+        let prio = Priorities::new().droppable("meter").droppable("viz");
+        let mut both = prio.clone();
+        both.set_droppable("viz", false);
+        assert!(!both.is_droppable("viz"));
+        let packets = vec![
+            FlowwPacket::Track("meter".to_string()), FlowwPacket::Point((0, 0.0, 1.0, 0.7)),
+            FlowwPacket::Point((0, 0.5, 1.0, 0.0)), FlowwPacket::Msg("hi".to_string()),
+            FlowwPacket::Track("keys".to_string()), FlowwPacket::Point((0, 0.0, 60.0, 1.0)),
+        ];
+        assert_eq!(prio.classify_all(&packets), vec![
+            Priority::Critical, Priority::Droppable, Priority::Critical, Priority::Normal, Priority::Critical, Priority::Normal,
+        ]);
+        assert_eq!(prio.shed(&packets, Priority::Normal).len(), 5);
+        assert_eq!(prio.shed(&packets, Priority::Critical), vec![
+            FlowwPacket::Track("meter".to_string()), FlowwPacket::Point((0, 0.5, 1.0, 0.0)), FlowwPacket::Track("keys".to_string()),
+        ]);
+    }
+}
@@ -0,0 +1,165 @@
+use crate::{ Point, RichPoint, FlowwSheet, FlowwPacket, TrackIds };
+
+use std::collections::HashMap;
+use std::time::Instant;
+
+// captures a live stream into a sheet, every point is stamped with the time it came in on the
+// recorder clock (seconds since it was created), only armed tracks are recorded and with a punch
+// range only what comes in between punch in and punch out
+pub struct Recorder{
+    sheet: FlowwSheet,
+    start: Instant,
+    punch: Option<(f32, f32)>,
+    armed: HashMap<String, bool>,
+    armed_by_default: bool,
+    // track of the packets coming in
+    current: Option<String>,
+    // notes that were recorded as on and still wait for their off, as (track, id, note)
+    open: Vec<(String, usize, f32)>,
+    last_time: f32,
+    ids: TrackIds,
+}
+
+impl Default for Recorder{
+    fn default() -> Self{
+        Self::new()
+    }
+}
+
+impl Recorder{
+    pub fn new() -> Self{
+        Self::with_sheet(FlowwSheet::new())
+    }
+
+    // records on top of an existing sheet
+    pub fn with_sheet(sheet: FlowwSheet) -> Self{
+        Self{
+            sheet, start: Instant::now(), punch: None, armed: HashMap::new(), armed_by_default: true,
+            current: None, open: Vec::new(), last_time: 0.0, ids: TrackIds::new(),
+        }
+    }
+
+    pub fn now(&self) -> f32{
+        self.start.elapsed().as_secs_f32()
+    }
+
+    // the clock starts at 0 again
+    pub fn reset_clock(&mut self){
+        self.start = Instant::now();
+        self.last_time = 0.0;
+    }
+
+    // ranges that are empty or inverted record everything
+    pub fn set_punch(&mut self, punch: Option<(f32, f32)>){
+        self.punch = punch.filter(|(start, end)| end > start);
+    }
+
+    pub fn punch(&self) -> Option<(f32, f32)>{
+        self.punch
+    }
+
+    pub fn arm(&mut self, track: &str, armed: bool){
+        self.armed.insert(track.to_string(), armed);
+    }
+
+    // also overrides the tracks that were armed one by one
+    pub fn arm_all(&mut self, armed: bool){
+        self.armed.clear();
+        self.armed_by_default = armed;
+    }
+
+    pub fn is_armed(&self, track: &str) -> bool{
+        self.armed.get(track).copied().unwrap_or(self.armed_by_default)
+    }
+
+    pub fn sheet(&self) -> &FlowwSheet{
+        &self.sheet
+    }
+
+    fn in_punch(&self, time: f32) -> bool{
+        self.punch.map(|(start, end)| time >= start && time < end).unwrap_or(true)
+    }
+
+    fn push(&mut self, track: &str, point: Point){
+        if !self.sheet.contains(track) {
+            self.sheet.add(Vec::new(), track.to_string());
+        }
+        if let Some(floww) = self.sheet.get_floww_mut_by_name(track){
+            floww.push(point);
+        }
+    }
+
+    pub fn record(&mut self, packets: Vec<FlowwPacket>){
+        let now = self.now();
+        self.record_at(packets, now);
+    }
+
+    // the time of the points is replaced by time, an off is only kept for a recorded on, when it
+    // comes after punch out it lands on punch out
+    pub fn record_at(&mut self, packets: Vec<FlowwPacket>, time: f32){
+        self.last_time = self.last_time.max(time);
+        for packet in self.ids.resolve(packets){
+            match packet{
+                FlowwPacket::Track(name) => self.current = Some(name),
+                FlowwPacket::Point(point) | FlowwPacket::RichPoint(RichPoint{ point, .. }) => self.record_point(point, time),
+                FlowwPacket::Points(points) => points.into_iter().for_each(|p| self.record_point(p, time)),
+                FlowwPacket::Deltas(deltas) => deltas.decode().into_iter().for_each(|p| self.record_point(p, time)),
+                _ => {},
+            }
+        }
+    }
+
+    fn record_point(&mut self, (id, _, note, vel): Point, time: f32){
+        let track = match &self.current{
+            Some(track) => track.clone(),
+            None => return,
+        };
+        if vel > 0.0 {
+            if self.is_armed(&track) && self.in_punch(time) {
+                self.push(&track, (id, time, note, vel));
+                self.open.push((track, id, note));
+            }
+        } else if let Some(i) = self.open.iter().position(|(t, i, n)| *t == track && *i == id && *n == note){
+            self.open.remove(i);
+            let time = self.punch.map(|(_, end)| time.min(end)).unwrap_or(time);
+            self.push(&track, (id, time, note, 0.0));
+        }
+    }
+
+    // notes that are still on are closed at the last time something was recorded
+    pub fn finish(mut self) -> FlowwSheet{
+        let time = self.punch.map(|(_, end)| self.last_time.min(end)).unwrap_or(self.last_time);
+        for (track, id, note) in std::mem::take(&mut self.open){
+            self.push(&track, (id, time, note, 0.0));
+        }
+        self.sheet
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+    #[test]
+    fn recorder(){
+        let mut rec = Recorder::new();
+        rec.arm("noise", false);
+        rec.set_punch(Some((1.0, 3.0)));
+        let on = |note| FlowwPacket::Point((0, 99.0, note, 1.0));
+        let off = |note| FlowwPacket::Point((0, 99.0, note, 0.0));
+        rec.record_at(vec![FlowwPacket::Track("keys".to_string()), on(60.0)], 0.5);
+        rec.record_at(vec![off(60.0), on(62.0), FlowwPacket::Track("noise".to_string()), on(1.0)], 1.0);
+        rec.record_at(vec![FlowwPacket::Track("keys".to_string()), on(64.0), FlowwPacket::Msg("hi".to_string())], 2.0);
+        rec.record_at(vec![off(62.0)], 3.5);
+        assert!(!rec.sheet().contains("noise"));
+        assert!(rec.is_armed("keys"));
+        let sheet = rec.finish();
+        assert_eq!(sheet.get_floww_ref_by_name("keys"), &[
+            (0, 1.0, 62.0, 1.0), (0, 2.0, 64.0, 1.0), (0, 3.0, 62.0, 0.0), (0, 3.0, 64.0, 0.0),
+        ]);
+        let mut rec = Recorder::new();
+        rec.arm_all(false);
+        rec.record(vec![FlowwPacket::Track("keys".to_string()), on(60.0)]);
+        assert!(rec.sheet().is_empty());
+        assert!(rec.now() >= 0.0);
+    }
+}
@@ -0,0 +1,212 @@
+use crate::{ FlowwPacket, BatchSend, ChannelSender };
+
+use bincode::ErrorKind;
+use serde::{ Serialize, Deserialize };
+
+use std::io;
+use std::time::{ Duration, Instant };
+
+// batches with the time they came in, in seconds since the log was started
+#[derive(Clone,PartialEq,Debug,Default,Serialize,Deserialize)]
+pub struct PacketLog{
+    pub entries: Vec<(f32, Vec<FlowwPacket>)>,
+}
+
+impl PacketLog{
+    pub fn new() -> Self{
+        Self::default()
+    }
+
+    // times that go back are moved up to the last one so the log stays in order
+    pub fn push(&mut self, time: f32, batch: Vec<FlowwPacket>){
+        let time = self.entries.last().map(|(t, _)| time.max(*t)).unwrap_or(time);
+        self.entries.push((time, batch));
+    }
+
+    pub fn len(&self) -> usize{
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool{
+        self.entries.is_empty()
+    }
+
+    // from the first to the last batch
+    pub fn duration(&self) -> f32{
+        match (self.entries.first(), self.entries.last()){
+            (Some((a, _)), Some((b, _))) => b - a,
+            _ => 0.0,
+        }
+    }
+
+    pub fn to_bytes(&self) -> Result<Vec<u8>, Box<ErrorKind>>{
+        bincode::serialize(self)
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, Box<ErrorKind>>{
+        bincode::deserialize(bytes)
+    }
+}
+
+// stamps batches with the wall clock while capturing a session
+pub struct PacketLogger{
+    log: PacketLog,
+    start: Instant,
+}
+
+impl Default for PacketLogger{
+    fn default() -> Self{
+        Self::new()
+    }
+}
+
+impl PacketLogger{
+    pub fn new() -> Self{
+        Self{ log: PacketLog::new(), start: Instant::now() }
+    }
+
+    pub fn log(&mut self, batch: &[FlowwPacket]){
+        let now = self.start.elapsed().as_secs_f32();
+        self.log.push(now, batch.to_vec());
+    }
+
+    pub fn into_log(self) -> PacketLog{
+        self.log
+    }
+}
+
+// anything batches can be replayed into
+pub trait PacketSink{
+    fn send_batch(&mut self, batch: Vec<FlowwPacket>) -> io::Result<()>;
+}
+
+impl<F: FnMut(Vec<FlowwPacket>) -> io::Result<()>> PacketSink for F{
+    fn send_batch(&mut self, batch: Vec<FlowwPacket>) -> io::Result<()>{
+        self(batch)
+    }
+}
+
+impl<S: BatchSend> PacketSink for ChannelSender<S>{
+    fn send_batch(&mut self, batch: Vec<FlowwPacket>) -> io::Result<()>{
+        self.send(batch)
+    }
+}
+
+#[cfg(feature = "net")]
+impl PacketSink for crate::FlowwSender{
+    fn send_batch(&mut self, batch: Vec<FlowwPacket>) -> io::Result<()>{
+        self.send(batch)
+    }
+}
+
+#[cfg(feature = "net")]
+impl PacketSink for crate::UdpSender{
+    fn send_batch(&mut self, batch: Vec<FlowwPacket>) -> io::Result<()>{
+        self.send(&batch).map(|_| ())
+    }
+}
+
+#[cfg(feature = "ws")]
+impl PacketSink for crate::WsServer{
+    fn send_batch(&mut self, batch: Vec<FlowwPacket>) -> io::Result<()>{
+        self.broadcast(&batch).map(|_| ())
+    }
+}
+
+// plays a log back with the gaps between the batches it was recorded with, divided by speed,
+// a speed that isn't above 0 and finite sends everything without waiting
+pub struct Replayer{
+    log: PacketLog,
+    speed: f32,
+    next: usize,
+}
+
+impl Replayer{
+    pub fn new(log: PacketLog) -> Self{
+        Self{ log, speed: 1.0, next: 0 }
+    }
+
+    pub fn with_speed(mut self, speed: f32) -> Self{
+        self.speed = speed;
+        self
+    }
+
+    pub fn log(&self) -> &PacketLog{
+        &self.log
+    }
+
+    pub fn is_done(&self) -> bool{
+        self.next >= self.log.len()
+    }
+
+    pub fn rewind(&mut self){
+        self.next = 0;
+    }
+
+    // when the next batch is due, in seconds of playback
+    pub fn next_due(&self) -> Option<f32>{
+        let start = self.log.entries.first()?.0;
+        let (time, _) = self.log.entries.get(self.next)?;
+        if self.speed > 0.0 && self.speed.is_finite() {
+            Some((time - start) / self.speed)
+        } else {
+            Some(0.0)
+        }
+    }
+
+    // sends what is due after elapsed seconds of playback, for driving it from an outside clock
+    pub fn poll<S: PacketSink>(&mut self, elapsed: f32, sink: &mut S) -> io::Result<usize>{
+        let mut n = 0;
+        while let Some(due) = self.next_due(){
+            if due > elapsed { break; }
+            sink.send_batch(self.log.entries[self.next].1.clone())?;
+            self.next += 1;
+            n += 1;
+        }
+        Ok(n)
+    }
+
+    // blocks until the rest of the log was sent
+    pub fn play<S: PacketSink>(&mut self, sink: &mut S) -> io::Result<()>{
+        let offset = self.next_due().unwrap_or(0.0);
+        let start = Instant::now();
+        while let Some(due) = self.next_due(){
+            let elapsed = start.elapsed().as_secs_f32() + offset;
+            if due > elapsed {
+                std::thread::sleep(Duration::from_secs_f32(due - elapsed));
+            }
+            self.poll(due, sink)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+    #[test]
+    fn replay(){
+        let mut log = PacketLog::new();
+        log.push(10.0, vec![FlowwPacket::Start]);
+        log.push(10.5, vec![FlowwPacket::Track("kick".to_string()), FlowwPacket::Point((0, 0.0, 36.0, 1.0))]);
+        log.push(10.25, vec![FlowwPacket::Stop]);
+        assert_eq!(log.duration(), 0.5);
+        let log = PacketLog::from_bytes(&log.to_bytes().unwrap()).unwrap();
+        let mut got = Vec::new();
+        let mut sink = |batch: Vec<FlowwPacket>| { got.push(batch); Ok(()) };
+        let mut rep = Replayer::new(log.clone()).with_speed(2.0);
+        assert_eq!(rep.poll(0.0, &mut sink).unwrap(), 1);
+        assert_eq!(rep.next_due(), Some(0.25));
+        assert_eq!(rep.poll(0.2, &mut sink).unwrap(), 0);
+        assert_eq!(rep.poll(0.25, &mut sink).unwrap(), 2);
+        assert!(rep.is_done());
+        rep.rewind();
+        let mut rep = rep.with_speed(0.0);
+        rep.play(&mut sink).unwrap();
+        assert_eq!(got.len(), 6);
+        assert_eq!(got[5], vec![FlowwPacket::Stop]);
+        let (mut tx, mut rx) = floww_channel();
+        Replayer::new(log).with_speed(100.0).play(&mut tx).unwrap();
+        assert_eq!(rx.recv().unwrap(), vec![FlowwPacket::Start]);
+    }
+}
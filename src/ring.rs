@@ -0,0 +1,228 @@
+use crate::{ FlowwPacket, Priority, Priorities };
+
+use std::cell::UnsafeCell;
+use std::mem::MaybeUninit;
+use std::sync::Arc;
+use std::sync::atomic::{ AtomicUsize, Ordering };
+
+// head and tail only ever grow (wrapping), their difference is the number of stored items
+struct Ring<T>{
+    buf: Box<[UnsafeCell<MaybeUninit<T>>]>,
+    head: AtomicUsize,
+    tail: AtomicUsize,
+}
+
+// the producer only writes free slots and the consumer only reads filled ones
+unsafe impl<T: Send> Sync for Ring<T>{}
+
+impl<T> Ring<T>{
+    // head first, tail can only have grown since so the difference never underflows
+    fn len(&self) -> usize{
+        let head = self.head.load(Ordering::Acquire);
+        self.tail.load(Ordering::Acquire).wrapping_sub(head)
+    }
+}
+
+impl<T> Drop for Ring<T>{
+    fn drop(&mut self){
+        let (head, tail) = (*self.head.get_mut(), *self.tail.get_mut());
+        let cap = self.buf.len();
+        let mut i = head;
+        while i != tail{
+            unsafe { (*self.buf[i % cap].get()).assume_init_drop(); }
+            i = i.wrapping_add(1);
+        }
+    }
+}
+
+pub struct RingProducer<T>{
+    ring: Arc<Ring<T>>,
+}
+
+pub struct RingConsumer<T>{
+    ring: Arc<Ring<T>>,
+}
+
+// fixed capacity single producer single consumer queue, push and pop never allocate or lock
+// so the consumer can live in an audio callback, works for Point and FlowwPacket alike
+pub fn ring_buffer<T>(capacity: usize) -> (RingProducer<T>, RingConsumer<T>){
+    let buf = (0..capacity).map(|_| UnsafeCell::new(MaybeUninit::uninit())).collect();
+    let ring = Arc::new(Ring{ buf, head: AtomicUsize::new(0), tail: AtomicUsize::new(0) });
+    (RingProducer{ ring: ring.clone() }, RingConsumer{ ring })
+}
+
+impl<T> RingProducer<T>{
+    // hands the item back when the buffer is full
+    pub fn push(&mut self, item: T) -> Result<(), T>{
+        let ring = &self.ring;
+        let tail = ring.tail.load(Ordering::Relaxed);
+        let head = ring.head.load(Ordering::Acquire);
+        if tail.wrapping_sub(head) == ring.buf.len() {
+            return Err(item);
+        }
+        unsafe { (*ring.buf[tail % ring.buf.len()].get()).write(item); }
+        ring.tail.store(tail.wrapping_add(1), Ordering::Release);
+        Ok(())
+    }
+
+    // keeps room for what matters more, droppable items only go in while the buffer is less than
+    // half full and normal ones while a quarter is still free, critical ones whenever there is room
+    pub fn push_with(&mut self, item: T, priority: Priority) -> Result<(), T>{
+        let cap = self.capacity();
+        let limit = match priority{
+            Priority::Droppable => cap / 2,
+            Priority::Normal => cap - cap / 4,
+            Priority::Critical => cap,
+        };
+        if self.len() >= limit {
+            return Err(item);
+        }
+        self.push(item)
+    }
+
+    pub fn len(&self) -> usize{
+        self.ring.len()
+    }
+
+    pub fn is_empty(&self) -> bool{
+        self.len() == 0
+    }
+
+    pub fn capacity(&self) -> usize{
+        self.ring.buf.len()
+    }
+}
+
+impl RingProducer<FlowwPacket>{
+    // push_with with the priority every packet has for priorities, hands back what was refused,
+    // once a track header is refused the points after it are refused as well until a header
+    // gets in, so points never end up on the track before it
+    pub fn push_prioritized(&mut self, packets: Vec<FlowwPacket>, priorities: &Priorities) -> Vec<FlowwPacket>{
+        let classes = priorities.classify_all(&packets);
+        let mut refused = Vec::new();
+        let mut lost_track = false;
+        for (packet, priority) in packets.into_iter().zip(classes){
+            if lost_track && packet.has_points() {
+                refused.push(packet);
+                continue;
+            }
+            let is_track = matches!(packet, FlowwPacket::Track(_));
+            match self.push_with(packet, priority){
+                Ok(()) => if is_track { lost_track = false; },
+                Err(packet) => {
+                    lost_track |= is_track;
+                    refused.push(packet);
+                },
+            }
+        }
+        refused
+    }
+}
+
+impl<T> RingConsumer<T>{
+    pub fn pop(&mut self) -> Option<T>{
+        let ring = &self.ring;
+        let head = ring.head.load(Ordering::Relaxed);
+        let tail = ring.tail.load(Ordering::Acquire);
+        if head == tail {
+            return None;
+        }
+        let item = unsafe { (*ring.buf[head % ring.buf.len()].get()).assume_init_read() };
+        ring.head.store(head.wrapping_add(1), Ordering::Release);
+        Some(item)
+    }
+
+    // pops until empty, items pushed meanwhile are included
+    pub fn drain(&mut self) -> impl Iterator<Item = T> + '_{
+        std::iter::from_fn(move || self.pop())
+    }
+
+    pub fn len(&self) -> usize{
+        self.ring.len()
+    }
+
+    pub fn is_empty(&self) -> bool{
+        self.len() == 0
+    }
+
+    pub fn capacity(&self) -> usize{
+        self.ring.buf.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+    #[test]
+    fn ring(){
+        let (mut tx, mut rx) = ring_buffer(2);
+        assert!(tx.push(FlowwPacket::Track("kick".to_string())).is_ok());
+        assert!(tx.push(FlowwPacket::Point((0, 0.0, 36.0, 1.0))).is_ok());
+        assert_eq!(tx.push(FlowwPacket::Msg("full".to_string())), Err(FlowwPacket::Msg("full".to_string())));
+        assert_eq!(rx.pop(), Some(FlowwPacket::Track("kick".to_string())));
+        assert!(tx.push(FlowwPacket::Msg("wrapped".to_string())).is_ok());
+        assert_eq!(rx.len(), 2);
+        assert_eq!(rx.drain().count(), 2);
+        assert_eq!(rx.pop(), None);
+        let (mut tx, _) = ring_buffer::<Point>(0);
+        assert!(tx.push((0, 0.0, 0.0, 0.0)).is_err());
+        let (mut tx, mut rx) = ring_buffer::<Point>(16);
+        let handle = std::thread::spawn(move || {
+            for i in 0..1000{
+                let mut p = (i, 0.0, 0.0, 0.0);
+                while let Err(back) = tx.push(p){
+                    p = back;
+                    std::thread::yield_now();
+                }
+            }
+        });
+        let mut next = 0;
+        while next < 1000{
+            if let Some(p) = rx.pop(){
+                assert_eq!(p.0, next);
+                next += 1;
+            } else {
+                std::thread::yield_now();
+            }
+        }
+        handle.join().unwrap();
+        let (mut tx, rx) = ring_buffer(4);
+        tx.push(String::from("dropped with the buffer")).unwrap();
+        drop(rx);
+        let (mut tx, mut rx) = ring_buffer(8);
+        let prio = Priorities::new().droppable("viz");
+        let mut batch = vec![FlowwPacket::Track("viz".to_string())];
+        batch.extend((0..6).map(|i| FlowwPacket::Point((0, i as f32, 1.0, 1.0))));
+        batch.push(FlowwPacket::Point((0, 9.0, 1.0, 0.0)));
+        assert_eq!(tx.push_prioritized(batch, &prio).len(), 3);
+        assert!(tx.push_with(FlowwPacket::Point((0, 0.0, 60.0, 1.0)), Priority::Normal).is_ok());
+        assert!(tx.push_with(FlowwPacket::Point((0, 0.0, 60.0, 1.0)), Priority::Normal).is_err());
+        assert!(tx.push_with(FlowwPacket::Stop, Priority::Critical).is_ok());
+        assert_eq!(rx.drain().last(), Some(FlowwPacket::Stop));
+        // with the consumer popping meanwhile every point has to arrive behind its own track
+        let (mut tx, mut rx) = ring_buffer(2);
+        let handle = std::thread::spawn(move || {
+            let prio = Priorities::new();
+            for i in 0..20000{
+                let track = (i % 3) as f32;
+                let mut batch = vec![FlowwPacket::Track(track.to_string())];
+                batch.extend((0..3).map(|_| FlowwPacket::Point((0, 0.0, track, 0.0))));
+                tx.push_prioritized(batch, &prio);
+            }
+        });
+        let mut current = None;
+        let mut points = 0;
+        while !handle.is_finished() || !rx.is_empty(){
+            match rx.pop(){
+                Some(FlowwPacket::Track(name)) => current = Some(name),
+                Some(FlowwPacket::Point(p)) => {
+                    assert_eq!(current, Some(p.2.to_string()));
+                    points += 1;
+                },
+                _ => std::thread::yield_now(),
+            }
+        }
+        handle.join().unwrap();
+        assert!(points > 0);
+    }
+}
@@ -0,0 +1,45 @@
+// Small deterministic PRNG (splitmix64), so seeded results stay the same across platforms and versions.
+#[derive(Clone,Debug)]
+pub(crate) struct Rng{
+    state: u64,
+}
+
+impl Rng{
+    pub(crate) fn new(seed: u64) -> Self{
+        Self{ state: seed }
+    }
+
+    pub(crate) fn next_u64(&mut self) -> u64{
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    // [0, 1)
+    pub(crate) fn next_f32(&mut self) -> f32{
+        (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32
+    }
+
+    // [a, b)
+    pub(crate) fn range(&mut self, a: f32, b: f32) -> f32{
+        a + (b - a) * self.next_f32()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn deterministic(){
+        let mut a = Rng::new(42);
+        let mut b = Rng::new(42);
+        for _ in 0..100{
+            let x = a.next_f32();
+            assert_eq!(x, b.next_f32());
+            assert!((0.0..1.0).contains(&x));
+        }
+        assert_ne!(Rng::new(1).next_u64(), Rng::new(2).next_u64());
+    }
+}
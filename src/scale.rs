@@ -0,0 +1,87 @@
+use crate::Floww;
+
+// root is a pitch class (0 = C), intervals are semitones above the root within one octave
+#[derive(Clone,PartialEq,Debug)]
+pub struct Scale{
+    root: u8,
+    intervals: Vec<u8>,
+}
+
+impl Scale{
+    pub fn new(root: u8, intervals: &[u8]) -> Self{
+        let mut intervals = intervals.iter().map(|i| i % 12).collect::<Vec<_>>();
+        intervals.sort_unstable();
+        intervals.dedup();
+        Self{ root: root % 12, intervals }
+    }
+
+    pub fn chromatic(root: u8) -> Self{ Self::new(root, &[0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11]) }
+    pub fn major(root: u8) -> Self{ Self::new(root, &[0, 2, 4, 5, 7, 9, 11]) }
+    pub fn minor(root: u8) -> Self{ Self::new(root, &[0, 2, 3, 5, 7, 8, 10]) }
+    pub fn harmonic_minor(root: u8) -> Self{ Self::new(root, &[0, 2, 3, 5, 7, 8, 11]) }
+    pub fn melodic_minor(root: u8) -> Self{ Self::new(root, &[0, 2, 3, 5, 7, 9, 11]) }
+    pub fn dorian(root: u8) -> Self{ Self::new(root, &[0, 2, 3, 5, 7, 9, 10]) }
+    pub fn phrygian(root: u8) -> Self{ Self::new(root, &[0, 1, 3, 5, 7, 8, 10]) }
+    pub fn lydian(root: u8) -> Self{ Self::new(root, &[0, 2, 4, 6, 7, 9, 11]) }
+    pub fn mixolydian(root: u8) -> Self{ Self::new(root, &[0, 2, 4, 5, 7, 9, 10]) }
+    pub fn locrian(root: u8) -> Self{ Self::new(root, &[0, 1, 3, 5, 6, 8, 10]) }
+    pub fn major_pentatonic(root: u8) -> Self{ Self::new(root, &[0, 2, 4, 7, 9]) }
+    pub fn minor_pentatonic(root: u8) -> Self{ Self::new(root, &[0, 3, 5, 7, 10]) }
+    pub fn blues(root: u8) -> Self{ Self::new(root, &[0, 3, 5, 6, 7, 10]) }
+
+    pub fn root(&self) -> u8{
+        self.root
+    }
+
+    pub fn intervals(&self) -> &[u8]{
+        &self.intervals
+    }
+
+    pub fn contains(&self, note: f32) -> bool{
+        self.snap(note) == note
+    }
+
+    // nearest scale degree, ties go down
+    pub fn snap(&self, note: f32) -> f32{
+        if self.intervals.is_empty() { return note; }
+        let rel = note - self.root as f32;
+        let octave = (rel / 12.0).floor();
+        let pc = rel - octave * 12.0;
+        let first = self.intervals[0] as f32;
+        let last = self.intervals[self.intervals.len() - 1] as f32;
+        let mut best = last - 12.0;
+        for c in self.intervals.iter().map(|i| *i as f32).chain(std::iter::once(first + 12.0)){
+            if (pc - c).abs() < (pc - best).abs() {
+                best = c;
+            }
+        }
+        self.root as f32 + octave * 12.0 + best
+    }
+}
+
+pub fn snap_to_scale(floww: &mut Floww, scale: &Scale){
+    floww.iter_mut().for_each(|p| p.2 = scale.snap(p.2));
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+    #[test]
+    fn snap(){
+        let c = Scale::major(0);
+        assert_eq!(c.snap(60.0), 60.0);
+        assert_eq!(c.snap(61.0), 60.0);
+        assert_eq!(c.snap(66.0), 65.0);
+        assert_eq!(c.snap(70.0), 69.0);
+        assert_eq!(c.snap(71.4), 71.0);
+        assert_eq!(c.snap(71.6), 72.0);
+        assert!(c.contains(64.0));
+        assert!(!c.contains(63.0));
+        let a = Scale::minor_pentatonic(9);
+        assert_eq!(a.snap(58.0), 57.0);
+        assert_eq!(a.snap(56.0), 55.0);
+        let mut f = vec![(0, 0.0, 61.0, 1.0), (0, 1.0, 66.0, 1.0)];
+        snap_to_scale(&mut f, &c);
+        assert_eq!(f, vec![(0, 0.0, 60.0, 1.0), (0, 1.0, 65.0, 1.0)]);
+    }
+}
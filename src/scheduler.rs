@@ -0,0 +1,167 @@
+use crate::{ Point, FlowwSheet, FlowwCursor, FlowwPacket };
+use crate::notes::sort_offs_first;
+
+// plays a sheet: every tick hands the points that became due to a callback, with the track
+// offset applied, muted tracks are skipped and a loop range wraps the clock around
+pub struct Scheduler{
+    sheet: FlowwSheet,
+    cursors: Vec<FlowwCursor>,
+    time: f32,
+    playing: bool,
+    loop_range: Option<(f32, f32)>,
+    // set when the sheet may have changed or the clock jumped, cursors are rebuilt on the next tick
+    dirty: bool,
+}
+
+impl Scheduler{
+    pub fn new(sheet: FlowwSheet) -> Self{
+        Self{ sheet, cursors: Vec::new(), time: 0.0, playing: false, loop_range: None, dirty: true }
+    }
+
+    pub fn sheet(&self) -> &FlowwSheet{
+        &self.sheet
+    }
+
+    // tracks are sorted again before the next tick
+    pub fn sheet_mut(&mut self) -> &mut FlowwSheet{
+        self.dirty = true;
+        &mut self.sheet
+    }
+
+    pub fn into_sheet(self) -> FlowwSheet{
+        self.sheet
+    }
+
+    pub fn play(&mut self){
+        self.playing = true;
+    }
+
+    pub fn stop(&mut self){
+        self.playing = false;
+    }
+
+    pub fn is_playing(&self) -> bool{
+        self.playing
+    }
+
+    pub fn time(&self) -> f32{
+        self.time
+    }
+
+    pub fn seek(&mut self, time: f32){
+        self.time = time;
+        self.dirty = true;
+    }
+
+    // ranges that are empty or inverted turn looping off
+    pub fn set_loop(&mut self, range: Option<(f32, f32)>){
+        self.loop_range = range.filter(|(start, end)| end > start);
+    }
+
+    pub fn loop_range(&self) -> Option<(f32, f32)>{
+        self.loop_range
+    }
+
+    // follows Start, Stop and Seek from a remote transport, false for packets it doesn't act on
+    pub fn apply_transport(&mut self, packet: &FlowwPacket) -> bool{
+        match packet{
+            FlowwPacket::Start => self.play(),
+            FlowwPacket::Stop => self.stop(),
+            FlowwPacket::Seek(t) => self.seek(*t),
+            _ => return false,
+        }
+        true
+    }
+
+    fn prepare(&mut self){
+        if !self.dirty { return; }
+        for i in 0..self.sheet.len(){
+            let (name, floww) = self.sheet.get_by_index(i).unwrap();
+            if floww.windows(2).any(|w| w[0].1 > w[1].1) {
+                let name = name.to_string();
+                sort_offs_first(self.sheet.get_floww_mut_by_name(&name).unwrap());
+            }
+        }
+        let time = self.time;
+        self.cursors = (0..self.sheet.len()).map(|i| {
+            let (name, floww) = self.sheet.get_by_index(i).unwrap();
+            let offset = self.sheet.get_meta(name).map(|m| m.offset).unwrap_or(0.0);
+            let mut cursor = FlowwCursor::new();
+            cursor.seek(floww, time - offset);
+            cursor
+        }).collect();
+        self.dirty = false;
+    }
+
+    // [from, to) on the sheet clock, cursors are expected at from
+    fn emit<F: FnMut(&str, &Point)>(&mut self, to: f32, f: &mut F){
+        for (i, cursor) in self.cursors.iter_mut().enumerate(){
+            let (name, floww) = self.sheet.get_by_index(i).unwrap();
+            if !self.sheet.is_audible(name) {
+                continue;
+            }
+            let offset = self.sheet.get_meta(name).map(|m| m.offset).unwrap_or(0.0);
+            for p in cursor.advance(floww, to - offset){
+                f(name, &(p.0, p.1 + offset, p.2, p.3));
+            }
+        }
+        self.time = to;
+    }
+
+    // does nothing while stopped or for dt <= 0
+    pub fn tick<F: FnMut(&str, &Point)>(&mut self, dt: f32, mut f: F){
+        if !self.playing || dt.is_nan() || dt <= 0.0 { return; }
+        self.prepare();
+        let mut left = dt;
+        loop{
+            let end = match self.loop_range{
+                Some((_, end)) if self.time < end && self.time + left >= end => end,
+                _ => {
+                    self.emit(self.time + left, &mut f);
+                    return;
+                },
+            };
+            left -= end - self.time;
+            self.emit(end, &mut f);
+            self.seek(self.loop_range.unwrap().0);
+            self.prepare();
+            if left <= 0.0 { return; }
+        }
+    }
+
+    pub fn tick_collect(&mut self, dt: f32) -> Vec<(String, Point)>{
+        let mut res = Vec::new();
+        self.tick(dt, |name, p| res.push((name.to_string(), *p)));
+        res
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+    #[test]
+    fn scheduler(){
+        let mut sheet = FlowwSheet::new();
+        sheet.add(vec![(0, 1.0, 36.0, 1.0), (0, 0.0, 36.0, 1.0), (0, 2.0, 36.0, 1.0)], "kick".to_string());
+        sheet.add(vec![(0, 0.5, 60.0, 1.0)], "keys".to_string());
+        sheet.set_offset("keys", 0.25);
+        let mut s = Scheduler::new(sheet);
+        assert!(s.tick_collect(1.0).is_empty());
+        s.play();
+        assert_eq!(s.tick_collect(0.5), vec![("kick".to_string(), (0, 0.0, 36.0, 1.0))]);
+        assert_eq!(s.tick_collect(0.5), vec![("keys".to_string(), (0, 0.75, 60.0, 1.0))]);
+        s.set_loop(Some((0.0, 1.5)));
+        let due = s.tick_collect(1.0);
+        assert_eq!(due.iter().map(|(n, p)| (n.as_str(), p.1)).collect::<Vec<_>>(),
+            vec![("kick", 1.0), ("kick", 0.0)]);
+        assert_eq!(s.time(), 0.5);
+        s.sheet_mut().set_mute("kick", true);
+        s.seek(0.0);
+        assert_eq!(s.tick_collect(2.5).len(), 2);
+        assert!(s.apply_transport(&FlowwPacket::Stop));
+        assert!(!s.is_playing());
+        assert!(s.apply_transport(&FlowwPacket::Seek(3.0)));
+        assert_eq!(s.time(), 3.0);
+        assert!(!s.apply_transport(&FlowwPacket::Tempo(90.0)));
+    }
+}
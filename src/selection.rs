@@ -0,0 +1,196 @@
+use crate::{ Point, Floww, TimedVec, VelocityCurve, MIN_VELOCITY };
+use crate::notes::{ note_spans, sort_offs_first };
+
+// unset fields don't restrict, time range is [start, end), note range is inclusive
+#[derive(Clone,PartialEq,Debug,Default)]
+pub struct Selection{
+    pub time_range: Option<(f32, f32)>,
+    pub ids: Option<Vec<usize>>,
+    pub note_range: Option<(f32, f32)>,
+}
+
+impl Selection{
+    pub fn all() -> Self{
+        Self::default()
+    }
+
+    pub fn time(start: f32, end: f32) -> Self{
+        Self{ time_range: Some((start, end)), ..Default::default() }
+    }
+
+    pub fn with_time(mut self, start: f32, end: f32) -> Self{
+        self.time_range = Some((start, end));
+        self
+    }
+
+    pub fn with_ids(mut self, ids: Vec<usize>) -> Self{
+        self.ids = Some(ids);
+        self
+    }
+
+    pub fn with_notes(mut self, low: f32, high: f32) -> Self{
+        self.note_range = Some((low, high));
+        self
+    }
+
+    pub fn contains(&self, p: &Point) -> bool{
+        if let Some((start, end)) = self.time_range{
+            if p.1 < start || p.1 >= end { return false; }
+        }
+        if let Some(ids) = &self.ids{
+            if !ids.contains(&p.0) { return false; }
+        }
+        if let Some((low, high)) = self.note_range{
+            if p.2 < low || p.2 > high { return false; }
+        }
+        true
+    }
+
+    // a note off is selected exactly when its note on is, so edits never split a note
+    pub fn mask(&self, floww: &[Point]) -> Vec<bool>{
+        let mut mask = floww.iter().map(|p| self.contains(p)).collect::<Vec<_>>();
+        for (on, off) in note_spans(floww){
+            mask[off] = mask[on];
+        }
+        mask
+    }
+}
+
+pub trait SelectionVec{
+    fn select(&self, selection: &Selection) -> Floww;
+    fn quantize_selected(&mut self, selection: &Selection, grid: f32);
+    fn transpose_selected(&mut self, selection: &Selection, semitones: f32);
+    fn scale_velocity_selected(&mut self, selection: &Selection, factor: f32);
+    fn shape_velocity_selected(&mut self, selection: &Selection, curve: VelocityCurve);
+}
+
+impl SelectionVec for Floww{
+    fn select(&self, selection: &Selection) -> Floww{
+        let mask = selection.mask(self);
+        self.iter().zip(mask).filter(|(_, m)| *m).map(|(p, _)| *p).collect()
+    }
+
+    fn quantize_selected(&mut self, selection: &Selection, grid: f32){
+        if grid.is_nan() || grid <= 0.0 { return; }
+        let mask = selection.mask(self);
+        self.iter_mut().zip(mask).filter(|(_, m)| *m).for_each(|(p, _)| p.1 = (p.1 / grid).round() * grid);
+        sort_offs_first(self);
+    }
+
+    fn transpose_selected(&mut self, selection: &Selection, semitones: f32){
+        let mask = selection.mask(self);
+        self.iter_mut().zip(mask).filter(|(_, m)| *m).for_each(|(p, _)| p.2 += semitones);
+    }
+
+    fn scale_velocity_selected(&mut self, selection: &Selection, factor: f32){
+        let mask = selection.mask(self);
+        self.iter_mut().zip(mask).filter(|(p, m)| *m && p.3 > 0.0)
+            .for_each(|(p, _)| p.3 = (p.3 * factor).clamp(MIN_VELOCITY, 1.0));
+    }
+
+    fn shape_velocity_selected(&mut self, selection: &Selection, curve: VelocityCurve){
+        let mask = selection.mask(self);
+        self.iter_mut().zip(mask).filter(|(p, m)| *m && p.3 > 0.0)
+            .for_each(|(p, _)| p.3 = curve.apply(p.3).max(MIN_VELOCITY));
+    }
+}
+
+// points of a region with times relative to its start, len keeps trailing silence
+#[derive(Clone,PartialEq,Debug,Default)]
+pub struct Clip{
+    pub points: Floww,
+    pub len: f32,
+}
+
+#[derive(Clone,Copy,PartialEq,Eq,Debug)]
+pub enum PasteMode{
+    // clip is merged with what is there
+    Mix,
+    // material inside the pasted region is removed first
+    Overwrite,
+    // material from the paste time on moves later by the clip length
+    Insert,
+}
+
+pub trait RegionVec{
+    fn copy_region(&self, start: f32, end: f32) -> Clip;
+    fn cut_region(&mut self, start: f32, end: f32) -> Clip;
+    fn paste_at(&mut self, clip: &Clip, t: f32, mode: PasteMode);
+}
+
+impl RegionVec for Floww{
+    // [start, end)
+    fn copy_region(&self, start: f32, end: f32) -> Clip{
+        Clip{
+            points: self.clone().cropped(start, end).sorted(),
+            len: (end - start).max(0.0),
+        }
+    }
+
+    // leaves a gap, paste the rest back with PasteMode::Insert to close it
+    fn cut_region(&mut self, start: f32, end: f32) -> Clip{
+        let clip = self.copy_region(start, end);
+        self.retain(|p| p.1 < start || p.1 >= end);
+        clip
+    }
+
+    fn paste_at(&mut self, clip: &Clip, t: f32, mode: PasteMode){
+        match mode{
+            PasteMode::Mix => {},
+            PasteMode::Overwrite => self.retain(|p| p.1 < t || p.1 >= t + clip.len),
+            PasteMode::Insert => self.iter_mut().filter(|p| p.1 >= t).for_each(|p| p.1 += clip.len),
+        }
+        self.extend(clip.points.iter().map(|p| (p.0, p.1 + t, p.2, p.3)));
+        sort_offs_first(self);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+    #[test]
+    fn selection(){
+        let f = vec![
+            (0, 0.1, 60.0, 1.0), (1, 0.4, 72.0, 0.5), (0, 0.9, 60.0, 0.0), (1, 1.1, 48.0, 0.5),
+        ];
+        let sel = Selection::time(0.0, 0.5).with_notes(50.0, 65.0);
+        assert!(sel.contains(&f[0]));
+        assert!(!sel.contains(&f[1]));
+        assert_eq!(sel.mask(&f), vec![true, false, true, false]);
+        assert_eq!(f.select(&sel), vec![(0, 0.1, 60.0, 1.0), (0, 0.9, 60.0, 0.0)]);
+        let mut a = f.clone();
+        a.transpose_selected(&sel, 2.0);
+        assert_eq!(a, vec![
+            (0, 0.1, 62.0, 1.0), (1, 0.4, 72.0, 0.5), (0, 0.9, 62.0, 0.0), (1, 1.1, 48.0, 0.5),
+        ]);
+        let mut b = f.clone();
+        b.quantize_selected(&Selection::all().with_ids(vec![1]), 0.5);
+        assert_eq!(b, vec![
+            (0, 0.1, 60.0, 1.0), (1, 0.5, 72.0, 0.5), (0, 0.9, 60.0, 0.0), (1, 1.0, 48.0, 0.5),
+        ]);
+        let mut c = f;
+        c.scale_velocity_selected(&Selection::all(), 0.5);
+        assert_eq!(c.iter().map(|p| p.3).collect::<Vec<_>>(), vec![0.5, 0.25, 0.0, 0.25]);
+        c.shape_velocity_selected(&Selection::time(1.0, 2.0), VelocityCurve::Gamma(0.5));
+        assert_eq!(c[3].3, 0.5);
+    }
+
+    #[test]
+    fn regions(){
+        let f = vec![(0, 0.0, 36.0, 1.0), (1, 1.0, 38.0, 1.0), (2, 1.5, 42.0, 1.0), (3, 2.0, 36.0, 1.0)];
+        let clip = f.copy_region(1.0, 2.0);
+        assert_eq!(clip, Clip{ points: vec![(1, 0.0, 38.0, 1.0), (2, 0.5, 42.0, 1.0)], len: 1.0 });
+        let mut a = f.clone();
+        assert_eq!(a.cut_region(1.0, 2.0), clip);
+        assert_eq!(a, vec![(0, 0.0, 36.0, 1.0), (3, 2.0, 36.0, 1.0)]);
+        let mut b = f.clone();
+        b.paste_at(&clip, 0.0, PasteMode::Overwrite);
+        assert_eq!(b, vec![(1, 0.0, 38.0, 1.0), (2, 0.5, 42.0, 1.0), (1, 1.0, 38.0, 1.0), (2, 1.5, 42.0, 1.0), (3, 2.0, 36.0, 1.0)]);
+        let mut c = vec![(0, 0.0, 36.0, 1.0), (3, 1.0, 36.0, 1.0)];
+        c.paste_at(&clip, 1.0, PasteMode::Insert);
+        assert_eq!(c, vec![(0, 0.0, 36.0, 1.0), (1, 1.0, 38.0, 1.0), (2, 1.5, 42.0, 1.0), (3, 2.0, 36.0, 1.0)]);
+        let mut d = vec![(0, 0.5, 36.0, 1.0)];
+        d.paste_at(&clip, 0.0, PasteMode::Mix);
+        assert_eq!(d, vec![(1, 0.0, 38.0, 1.0), (0, 0.5, 36.0, 1.0), (2, 0.5, 42.0, 1.0)]);
+    }
+}
@@ -0,0 +1,114 @@
+use crate::{ FlowwPacket, PacketSink, point_to_midi_bytes };
+
+use serialport::SerialPort;
+
+use std::io::{ self, Write };
+use std::time::Duration;
+
+#[derive(Clone,Copy,PartialEq,Eq,Debug)]
+pub enum SerialFormat{
+    // note on and off messages on this channel, start and stop as realtime messages
+    Midi(u8),
+    // three bytes per point: 0x80 | track, note, velocity, only the first has the high bit set
+    // so a receiver can find the start of a point again after losing bytes
+    Compact,
+}
+
+// writes points as they are sent, so a microcontroller can play them on arrival, tracks are
+// numbered in the order they first show up unless set up front
+pub struct SerialOutput<W>{
+    writer: W,
+    format: SerialFormat,
+    tracks: Vec<String>,
+    current: usize,
+}
+
+impl SerialOutput<Box<dyn SerialPort>>{
+    pub fn open(path: &str, baud_rate: u32, format: SerialFormat) -> io::Result<Self>{
+        let port = serialport::new(path, baud_rate).timeout(Duration::from_millis(100)).open()?;
+        Ok(Self::new(port, format))
+    }
+}
+
+impl<W: Write> SerialOutput<W>{
+    pub fn new(writer: W, format: SerialFormat) -> Self{
+        Self{ writer, format, tracks: Vec::new(), current: 0 }
+    }
+
+    pub fn set_tracks(&mut self, tracks: &[&str]){
+        self.tracks = tracks.iter().map(|t| t.to_string()).collect();
+    }
+
+    pub fn tracks(&self) -> &[String]{
+        &self.tracks
+    }
+
+    pub fn into_inner(self) -> W{
+        self.writer
+    }
+
+    fn track_index(&mut self, name: &str) -> usize{
+        if let Some(i) = self.tracks.iter().position(|t| t == name){
+            i
+        } else {
+            self.tracks.push(name.to_string());
+            self.tracks.len() - 1
+        }
+    }
+
+    // what send writes, packets that have no byte form are skipped
+    pub fn encode(&mut self, packets: &[FlowwPacket]) -> Vec<u8>{
+        let mut bytes = Vec::new();
+        for packet in packets{
+            match (packet, self.format){
+                (FlowwPacket::Track(name), _) => self.current = self.track_index(name),
+                (_, SerialFormat::Midi(channel)) if packet.has_points() => for p in packet.points().iter(){
+                    bytes.extend(point_to_midi_bytes(p, channel));
+                },
+                (_, SerialFormat::Compact) if packet.has_points() => for p in packet.points().iter(){
+                    let midi = point_to_midi_bytes(p, 0);
+                    bytes.extend([0x80 | (self.current.min(0x7f) as u8), midi[1], midi[2]]);
+                },
+                (FlowwPacket::Start, SerialFormat::Midi(_)) => bytes.push(0xfa),
+                (FlowwPacket::Stop, SerialFormat::Midi(_)) => bytes.push(0xfc),
+                _ => {},
+            }
+        }
+        bytes
+    }
+
+    pub fn send(&mut self, packets: &[FlowwPacket]) -> io::Result<()>{
+        let bytes = self.encode(packets);
+        if bytes.is_empty() { return Ok(()); }
+        self.writer.write_all(&bytes)?;
+        self.writer.flush()
+    }
+}
+
+impl<W: Write> PacketSink for SerialOutput<W>{
+    fn send_batch(&mut self, batch: Vec<FlowwPacket>) -> io::Result<()>{
+        self.send(&batch)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+    #[test]
+    fn serial_bytes(){
+        let packets = vec![
+            FlowwPacket::Start, FlowwPacket::Point((0, 0.0, 36.0, 1.0)),
+            FlowwPacket::Track("keys".to_string()), FlowwPacket::Point((0, 0.0, 60.0, 0.5)),
+            FlowwPacket::Msg("skipped".to_string()), FlowwPacket::Point((0, 1.0, 60.0, 0.0)),
+        ];
+        let mut out = SerialOutput::new(Vec::new(), SerialFormat::Midi(1));
+        out.send(&packets).unwrap();
+        assert_eq!(out.into_inner(), vec![0xfa, 0x91, 36, 127, 0x91, 60, 64, 0x81, 60, 0]);
+        let mut out = SerialOutput::new(Vec::new(), SerialFormat::Compact);
+        out.set_tracks(&["kick", "keys"]);
+        assert_eq!(out.encode(&packets), vec![0x80, 36, 127, 0x81, 60, 64, 0x81, 60, 0]);
+        out.send(&[FlowwPacket::Track("bass".to_string()), FlowwPacket::Point((0, 0.0, 40.0, 1.0))]).unwrap();
+        assert_eq!(out.tracks().len(), 3);
+        assert_eq!(out.into_inner(), vec![0x82, 40, 127]);
+    }
+}
@@ -0,0 +1,188 @@
+use crate::{ FlowwPacket };
+use crate::net::invalid_data;
+
+use memmap2::MmapMut;
+
+use std::fs::OpenOptions;
+use std::io;
+use std::path::Path;
+use std::sync::atomic::{ AtomicU64, Ordering };
+
+const SHM_MAGIC: u64 = u64::from_le_bytes(*b"FLWWSHM1");
+
+// magic and capacity up front, then the read and write positions on their own cache lines
+const CAPACITY: usize = 8;
+const HEAD: usize = 64;
+const TAIL: usize = 128;
+const DATA: usize = 192;
+
+// a byte ring in a mapped file, put the file on a tmpfs (like /dev/shm) to keep it in memory,
+// positions only grow and every batch is its u32 little endian length and then the batch
+struct ShmRing{
+    map: MmapMut,
+    cap: u64,
+}
+
+impl ShmRing{
+    fn create(path: &Path, capacity: usize) -> io::Result<Self>{
+        let file = OpenOptions::new().read(true).write(true).create(true).truncate(true).open(path)?;
+        file.set_len((DATA + capacity) as u64)?;
+        let mut map = unsafe { MmapMut::map_mut(&file)? };
+        map[..DATA].fill(0);
+        map[CAPACITY..CAPACITY + 8].copy_from_slice(&(capacity as u64).to_le_bytes());
+        let ring = Self{ map, cap: capacity as u64 };
+        // the magic goes in last so a receiver never sees a half set up ring
+        ring.atomic(0).store(SHM_MAGIC, Ordering::Release);
+        Ok(ring)
+    }
+
+    fn open(path: &Path) -> io::Result<Self>{
+        let file = OpenOptions::new().read(true).write(true).open(path)?;
+        let map = unsafe { MmapMut::map_mut(&file)? };
+        if map.len() < DATA {
+            return Err(invalid_data("not a floww shared memory ring"));
+        }
+        let mut ring = Self{ map, cap: 0 };
+        if ring.atomic(0).load(Ordering::Acquire) != SHM_MAGIC {
+            return Err(invalid_data("not a floww shared memory ring"));
+        }
+        let mut cap = [0u8; 8];
+        cap.copy_from_slice(&ring.map[CAPACITY..CAPACITY + 8]);
+        ring.cap = u64::from_le_bytes(cap);
+        if ring.cap == 0 || ring.map.len() as u64 != DATA as u64 + ring.cap {
+            return Err(invalid_data("shared memory ring has the wrong size"));
+        }
+        Ok(ring)
+    }
+
+    // the map is page aligned so every offset that is a multiple of 8 is fine for an AtomicU64
+    fn atomic(&self, offset: usize) -> &AtomicU64{
+        unsafe { &*(self.map.as_ptr().add(offset) as *const AtomicU64) }
+    }
+
+    // raw pointers only, the other process writes the same memory
+    fn write_at(&mut self, pos: u64, bytes: &[u8]){
+        let data = unsafe { self.map.as_mut_ptr().add(DATA) };
+        let start = (pos % self.cap) as usize;
+        let first = bytes.len().min(self.cap as usize - start);
+        unsafe {
+            std::ptr::copy_nonoverlapping(bytes.as_ptr(), data.add(start), first);
+            std::ptr::copy_nonoverlapping(bytes.as_ptr().add(first), data, bytes.len() - first);
+        }
+    }
+
+    fn read_at(&self, pos: u64, buf: &mut [u8]){
+        let data = unsafe { self.map.as_ptr().add(DATA) };
+        let start = (pos % self.cap) as usize;
+        let first = buf.len().min(self.cap as usize - start);
+        unsafe {
+            std::ptr::copy_nonoverlapping(data.add(start), buf.as_mut_ptr(), first);
+            std::ptr::copy_nonoverlapping(data, buf.as_mut_ptr().add(first), buf.len() - first);
+        }
+    }
+}
+
+// the writing end, creates the ring, there can only be one sender and one receiver per file
+pub struct ShmSender{
+    ring: ShmRing,
+}
+
+impl ShmSender{
+    pub fn create<P: AsRef<Path>>(path: P, capacity: usize) -> io::Result<Self>{
+        Ok(Self{ ring: ShmRing::create(path.as_ref(), capacity.max(8))? })
+    }
+
+    // false when the receiver didn't make room yet
+    pub fn try_send(&mut self, packets: &[FlowwPacket]) -> io::Result<bool>{
+        let bytes = bincode::serialize(packets).map_err(invalid_data)?;
+        let need = 4 + bytes.len() as u64;
+        if need > self.ring.cap || bytes.len() > u32::MAX as usize {
+            return Err(invalid_data("batch larger than the shared memory ring"));
+        }
+        let head = self.ring.atomic(HEAD).load(Ordering::Acquire);
+        let tail = self.ring.atomic(TAIL).load(Ordering::Relaxed);
+        if self.ring.cap - (tail - head) < need {
+            return Ok(false);
+        }
+        self.ring.write_at(tail, &(bytes.len() as u32).to_le_bytes());
+        self.ring.write_at(tail + 4, &bytes);
+        self.ring.atomic(TAIL).store(tail + need, Ordering::Release);
+        Ok(true)
+    }
+
+    // spins until there is room
+    pub fn send(&mut self, packets: &[FlowwPacket]) -> io::Result<()>{
+        while !self.try_send(packets)?{
+            std::thread::yield_now();
+        }
+        Ok(())
+    }
+}
+
+pub struct ShmReceiver{
+    ring: ShmRing,
+}
+
+impl ShmReceiver{
+    // fails until a sender created the ring
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self>{
+        Ok(Self{ ring: ShmRing::open(path.as_ref())? })
+    }
+
+    pub fn try_recv(&mut self) -> io::Result<Option<Vec<FlowwPacket>>>{
+        let tail = self.ring.atomic(TAIL).load(Ordering::Acquire);
+        let head = self.ring.atomic(HEAD).load(Ordering::Relaxed);
+        if head == tail { return Ok(None); }
+        let mut len = [0u8; 4];
+        self.ring.read_at(head, &mut len);
+        let len = u32::from_le_bytes(len) as u64;
+        if 4 + len > tail - head {
+            return Err(invalid_data("shared memory ring is corrupt"));
+        }
+        let mut bytes = vec![0u8; len as usize];
+        self.ring.read_at(head + 4, &mut bytes);
+        self.ring.atomic(HEAD).store(head + 4 + len, Ordering::Release);
+        bincode::deserialize(&bytes).map(Some).map_err(invalid_data)
+    }
+
+    // spins until a batch arrives
+    pub fn recv(&mut self) -> io::Result<Vec<FlowwPacket>>{
+        loop{
+            if let Some(batch) = self.try_recv()?{
+                return Ok(batch);
+            }
+            std::thread::yield_now();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+    #[test]
+    fn shared_memory(){
+        let path = std::env::temp_dir().join(format!("floww-shm-test-{}", std::process::id()));
+        let mut tx = ShmSender::create(&path, 80).unwrap();
+        let mut rx = ShmReceiver::open(&path).unwrap();
+        assert_eq!(rx.try_recv().unwrap(), None);
+        let batch = vec![FlowwPacket::Point((0, 0.0, 36.0, 1.0))];
+        assert!(tx.try_send(&batch).unwrap());
+        assert!(tx.try_send(&batch).unwrap());
+        assert!(!tx.try_send(&batch).unwrap());
+        assert!(tx.try_send(&[FlowwPacket::Msg("x".repeat(100))]).is_err());
+        assert_eq!(rx.recv().unwrap(), batch);
+        let handle = std::thread::spawn(move || {
+            for i in 0..200{
+                tx.send(&[FlowwPacket::Point((i, 0.0, 36.0, 1.0))]).unwrap();
+            }
+        });
+        assert_eq!(rx.recv().unwrap(), batch);
+        for i in 0..200{
+            assert_eq!(rx.recv().unwrap(), vec![FlowwPacket::Point((i, 0.0, 36.0, 1.0))]);
+        }
+        handle.join().unwrap();
+        std::fs::write(&path, [0u8; 256]).unwrap();
+        assert!(ShmReceiver::open(&path).is_err());
+        std::fs::remove_file(&path).unwrap();
+    }
+}
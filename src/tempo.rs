@@ -0,0 +1,93 @@
+use crate::{ Point, Floww };
+
+// piecewise constant tempo, every segment is (start beat, bpm, seconds at start beat)
+#[derive(Clone,PartialEq,Debug)]
+pub struct TimeMap{
+    segments: Vec<(f32, f32, f32)>,
+}
+
+impl Default for TimeMap{
+    fn default() -> Self{
+        Self::new(60.0)
+    }
+}
+
+impl TimeMap{
+    pub fn new(bpm: f32) -> Self{
+        Self{ segments: vec![(0.0, bpm, 0.0)] }
+    }
+
+    // replaces a change at the same beat, beats before zero are ignored
+    pub fn set_tempo(&mut self, beat: f32, bpm: f32){
+        if beat < 0.0 { return; }
+        let i = self.segments.partition_point(|s| s.0 < beat);
+        if i < self.segments.len() && self.segments[i].0 == beat {
+            self.segments[i].1 = bpm;
+        } else {
+            self.segments.insert(i, (beat, bpm, 0.0));
+        }
+        for i in 1..self.segments.len(){
+            let (b, bpm, s) = self.segments[i - 1];
+            self.segments[i].2 = s + (self.segments[i].0 - b) * 60.0 / bpm;
+        }
+    }
+
+    pub fn with_tempo(mut self, beat: f32, bpm: f32) -> Self{
+        self.set_tempo(beat, bpm);
+        self
+    }
+
+    pub fn segments(&self) -> impl Iterator<Item = (f32, f32)> + '_{
+        self.segments.iter().map(|(b, bpm, _)| (*b, *bpm))
+    }
+
+    pub fn bpm_at(&self, beat: f32) -> f32{
+        self.segment_for_beat(beat).1
+    }
+
+    // negative beats extrapolate the first tempo
+    pub fn seconds_at(&self, beat: f32) -> f32{
+        let (b, bpm, s) = self.segment_for_beat(beat);
+        s + (beat - b) * 60.0 / bpm
+    }
+
+    pub fn beat_at(&self, seconds: f32) -> f32{
+        let i = self.segments.partition_point(|s| s.2 <= seconds).max(1);
+        let (b, bpm, s) = self.segments[i - 1];
+        b + (seconds - s) * bpm / 60.0
+    }
+
+    fn segment_for_beat(&self, beat: f32) -> (f32, f32, f32){
+        let i = self.segments.partition_point(|s| s.0 <= beat).max(1);
+        self.segments[i - 1]
+    }
+}
+
+pub fn to_seconds(floww: &[Point], map: &TimeMap) -> Floww{
+    floww.iter().map(|p| (p.0, map.seconds_at(p.1), p.2, p.3)).collect()
+}
+
+pub fn to_beats(floww: &[Point], map: &TimeMap) -> Floww{
+    floww.iter().map(|p| (p.0, map.beat_at(p.1), p.2, p.3)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+    #[test]
+    fn time_map(){
+        let map = TimeMap::new(120.0).with_tempo(4.0, 60.0).with_tempo(8.0, 240.0);
+        assert_eq!(map.seconds_at(2.0), 1.0);
+        assert_eq!(map.seconds_at(4.0), 2.0);
+        assert_eq!(map.seconds_at(6.0), 4.0);
+        assert_eq!(map.seconds_at(10.0), 6.5);
+        assert_eq!(map.beat_at(6.5), 10.0);
+        assert_eq!(map.beat_at(3.0), 5.0);
+        assert_eq!(map.bpm_at(7.9), 60.0);
+        let f = vec![(0, 0.0, 60.0, 1.0), (0, 4.0, 60.0, 1.0), (0, 10.0, 60.0, 1.0)];
+        let s = to_seconds(&f, &map);
+        assert_eq!(s, vec![(0, 0.0, 60.0, 1.0), (0, 2.0, 60.0, 1.0), (0, 6.5, 60.0, 1.0)]);
+        assert_eq!(to_beats(&s, &map), f);
+        assert_eq!(TimeMap::default().seconds_at(3.0), 3.0);
+    }
+}
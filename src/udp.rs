@@ -0,0 +1,138 @@
+use crate::{ FlowwPacket, Priority, Priorities };
+use crate::net::invalid_data;
+
+use std::io;
+use std::ops::Range;
+use std::collections::{ HashMap };
+use std::net::{ UdpSocket, SocketAddr, ToSocketAddrs };
+
+// largest payload a udp datagram can carry over ipv4
+pub const MAX_DATAGRAM_LEN: usize = 65507;
+
+// a datagram is the u64 little endian sequence number, then the encoded batch
+pub struct UdpSender{
+    socket: UdpSocket,
+    seq: u64,
+}
+
+impl UdpSender{
+    pub fn connect<A: ToSocketAddrs>(addr: A) -> io::Result<Self>{
+        let target = addr.to_socket_addrs()?.next().ok_or_else(|| invalid_data("no address"))?;
+        let local: SocketAddr = if target.is_ipv4() { ([0, 0, 0, 0], 0).into() } else { ([0u16; 8], 0).into() };
+        let socket = UdpSocket::bind(local)?;
+        socket.connect(target)?;
+        Ok(Self{ socket, seq: 0 })
+    }
+
+    // returns the sequence number the batch went out with, batches that don't fit a datagram
+    // are refused so they can be split by the caller
+    pub fn send(&mut self, packets: &[FlowwPacket]) -> io::Result<u64>{
+        let mut bytes = self.seq.to_le_bytes().to_vec();
+        bytes.extend(bincode::serialize(packets).map_err(invalid_data)?);
+        if bytes.len() > MAX_DATAGRAM_LEN {
+            return Err(invalid_data("batch too large for a datagram"));
+        }
+        self.socket.send(&bytes)?;
+        self.seq += 1;
+        Ok(self.seq - 1)
+    }
+
+    // a batch too large for a datagram loses its droppable packets first and then everything
+    // that isn't critical, only the critical packets not fitting is an error
+    pub fn send_prioritized(&mut self, packets: &[FlowwPacket], priorities: &Priorities) -> io::Result<u64>{
+        let fits = |p: &[FlowwPacket]| bincode::serialized_size(p).map(|n| n as usize <= MAX_DATAGRAM_LEN - 8).unwrap_or(false);
+        if fits(packets) {
+            return self.send(packets);
+        }
+        let normal = priorities.shed(packets, Priority::Normal);
+        if fits(&normal) {
+            return self.send(&normal);
+        }
+        self.send(&priorities.shed(packets, Priority::Critical))
+    }
+}
+
+#[derive(Clone,PartialEq,Debug)]
+pub struct UdpBatch{
+    pub seq: u64,
+    pub packets: Vec<FlowwPacket>,
+    pub from: SocketAddr,
+    // sequence numbers skipped right before this batch, empty when nothing went missing
+    pub missed: Range<u64>,
+    // older than a batch that was already received, it may fill an earlier gap
+    pub late: bool,
+}
+
+pub struct UdpReceiver{
+    socket: UdpSocket,
+    expected: HashMap<SocketAddr, u64>,
+    buf: Vec<u8>,
+}
+
+impl UdpReceiver{
+    pub fn bind<A: ToSocketAddrs>(addr: A) -> io::Result<Self>{
+        Ok(Self{ socket: UdpSocket::bind(addr)?, expected: HashMap::new(), buf: vec![0; MAX_DATAGRAM_LEN] })
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr>{
+        self.socket.local_addr()
+    }
+
+    pub fn set_read_timeout(&self, timeout: Option<std::time::Duration>) -> io::Result<()>{
+        self.socket.set_read_timeout(timeout)
+    }
+
+    // sequences are tracked per sender, the first batch of a sender sets its starting point
+    // so a late joiner sees no loss
+    pub fn recv(&mut self) -> io::Result<UdpBatch>{
+        let (n, from) = self.socket.recv_from(&mut self.buf)?;
+        if n < 8 {
+            return Err(invalid_data("datagram too short"));
+        }
+        let mut seq = [0u8; 8];
+        seq.copy_from_slice(&self.buf[..8]);
+        let seq = u64::from_le_bytes(seq);
+        let packets = bincode::deserialize(&self.buf[8..n]).map_err(invalid_data)?;
+        let expected = *self.expected.get(&from).unwrap_or(&seq);
+        let (missed, late) = if seq >= expected {
+            self.expected.insert(from, seq + 1);
+            (expected..seq, false)
+        } else {
+            (seq..seq, true)
+        };
+        Ok(UdpBatch{ seq, packets, from, missed, late })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+    #[test]
+    fn udp(){
+        let mut receiver = UdpReceiver::bind("127.0.0.1:0").unwrap();
+        receiver.set_read_timeout(Some(std::time::Duration::from_secs(5))).unwrap();
+        let mut sender = UdpSender::connect(receiver.local_addr().unwrap()).unwrap();
+        let packets = vec![FlowwPacket::Track("kick".to_string()), FlowwPacket::Point((0, 0.0, 36.0, 1.0))];
+        assert_eq!(sender.send(&packets).unwrap(), 0);
+        let b = receiver.recv().unwrap();
+        assert_eq!((b.seq, b.packets, b.missed, b.late), (0, packets, 0..0, false));
+        sender.seq = 3;
+        sender.send(&[]).unwrap();
+        assert_eq!(receiver.recv().unwrap().missed, 1..3);
+        sender.seq = 1;
+        sender.send(&[]).unwrap();
+        let b = receiver.recv().unwrap();
+        assert!(b.late && b.missed.is_empty());
+        let mut other = UdpSender::connect(receiver.local_addr().unwrap()).unwrap();
+        other.seq = 7;
+        other.send(&[]).unwrap();
+        assert_eq!(receiver.recv().unwrap().missed, 7..7);
+        let big = vec![FlowwPacket::Msg("x".repeat(MAX_DATAGRAM_LEN))];
+        assert!(sender.send(&big).is_err());
+        let prio = Priorities::new();
+        let mut shed = vec![FlowwPacket::Track("kick".to_string()), FlowwPacket::Point((0, 0.0, 36.0, 0.0))];
+        shed.extend(big);
+        sender.send_prioritized(&shed, &prio).unwrap();
+        assert_eq!(receiver.recv().unwrap().packets, shed[..2]);
+    }
+}
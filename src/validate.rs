@@ -0,0 +1,116 @@
+use crate::{ Point, FlowwPacket, TrackIds };
+
+use std::collections::HashMap;
+use std::io::Cursor;
+
+// packet is the index of the packet over the whole stream
+#[derive(Clone,PartialEq,Debug)]
+pub enum StreamIssue{
+    // bytes that aren't a batch of known packets, nothing after offset is checked
+    Decode{ offset: usize, error: String },
+    PointBeforeTrack{ packet: usize },
+    UnknownTrackId{ packet: usize, id: u32 },
+    NotFinite{ packet: usize, track: String, point: Point },
+    VelocityOutOfRange{ packet: usize, track: String, point: Point },
+    // a point that is earlier than the one before it on the same track
+    TimeWentBack{ packet: usize, track: String, time: f32, previous: f32 },
+}
+
+// checks bytes holding one or more encoded batches back to back, as a debugging aid when two
+// implementations disagree, a Clear or Seek starts the time check of tracks over
+pub fn validate_stream(bytes: &[u8]) -> Vec<StreamIssue>{
+    let mut issues = Vec::new();
+    let mut cursor = Cursor::new(bytes);
+    let mut ids = TrackIds::new();
+    let mut last: HashMap<String, f32> = HashMap::new();
+    let mut current: Option<String> = None;
+    let mut index = 0;
+    while (cursor.position() as usize) < bytes.len(){
+        let offset = cursor.position() as usize;
+        let batch: Vec<FlowwPacket> = match bincode::deserialize_from(&mut cursor){
+            Ok(batch) => batch,
+            Err(e) => {
+                issues.push(StreamIssue::Decode{ offset, error: e.to_string() });
+                break;
+            },
+        };
+        for packet in batch{
+            match &packet{
+                FlowwPacket::Track(name) => current = Some(ids.canonical(name).to_string()),
+                FlowwPacket::DefineTrack(id, name) => ids.define(*id, name.clone()),
+                FlowwPacket::Alias{ from, to } => ids.alias(from.clone(), to.clone()),
+                FlowwPacket::TrackId(id) => {
+                    current = ids.name(*id).map(|n| ids.canonical(n).to_string());
+                    if current.is_none() {
+                        issues.push(StreamIssue::UnknownTrackId{ packet: index, id: *id });
+                    }
+                },
+                FlowwPacket::Clear(name) => { last.remove(ids.canonical(name)); },
+                FlowwPacket::ClearAll | FlowwPacket::Seek(_) => last.clear(),
+                _ if packet.has_points() => {
+                    let track = match &current{
+                        Some(track) => track,
+                        None => {
+                            issues.push(StreamIssue::PointBeforeTrack{ packet: index });
+                            index += 1;
+                            continue;
+                        },
+                    };
+                    for point in packet.points().iter(){
+                        check_point(index, track, *point, &mut last, &mut issues);
+                    }
+                },
+                _ => {},
+            }
+            index += 1;
+        }
+    }
+    issues
+}
+
+fn check_point(packet: usize, track: &str, point: Point, last: &mut HashMap<String, f32>, issues: &mut Vec<StreamIssue>){
+    let (_, time, note, vel) = point;
+    if !time.is_finite() || !note.is_finite() || !vel.is_finite() {
+        issues.push(StreamIssue::NotFinite{ packet, track: track.to_string(), point });
+        return;
+    }
+    if !(0.0..=1.0).contains(&vel) {
+        issues.push(StreamIssue::VelocityOutOfRange{ packet, track: track.to_string(), point });
+    }
+    if let Some(previous) = last.insert(track.to_string(), time){
+        if time < previous {
+            issues.push(StreamIssue::TimeWentBack{ packet, track: track.to_string(), time, previous });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+    #[test]
+    fn validate(){
+        let mut bytes = vec![
+            FlowwPacket::Point((0, 0.0, 36.0, 1.0)),
+            FlowwPacket::Track("kick".to_string()), FlowwPacket::Point((0, 1.0, 36.0, 1.0)),
+            FlowwPacket::Point((0, 0.5, 36.0, 1.5)), FlowwPacket::Seek(0.0),
+            FlowwPacket::Point((0, 0.25, 36.0, 0.0)),
+        ].encoded();
+        bytes.extend(vec![
+            FlowwPacket::DefineTrack(0, "keys".to_string()), FlowwPacket::TrackId(0),
+            FlowwPacket::Points(vec![(0, 0.0, 60.0, 1.0), (0, f32::NAN, 60.0, 0.0)]), FlowwPacket::TrackId(4),
+        ].encoded());
+        let good = bytes.len();
+        bytes.extend([1, 0, 0, 0, 0, 0, 0, 0, 99, 0, 0, 0]);
+        let issues = validate_stream(&bytes);
+        assert_eq!(issues.len(), 6);
+        assert_eq!(issues[..3], [
+            StreamIssue::PointBeforeTrack{ packet: 0 },
+            StreamIssue::VelocityOutOfRange{ packet: 3, track: "kick".to_string(), point: (0, 0.5, 36.0, 1.5) },
+            StreamIssue::TimeWentBack{ packet: 3, track: "kick".to_string(), time: 0.5, previous: 1.0 },
+        ]);
+        assert!(matches!(&issues[3], StreamIssue::NotFinite{ packet: 8, track, .. } if track == "keys"));
+        assert_eq!(issues[4], StreamIssue::UnknownTrackId{ packet: 9, id: 4 });
+        assert!(matches!(issues[5], StreamIssue::Decode{ offset, .. } if offset == good));
+        assert!(validate_stream(&[]).is_empty());
+    }
+}
@@ -0,0 +1,70 @@
+use crate::{ Point };
+
+use serde::{ Serialize, Serializer, Deserialize, Deserializer };
+use serde::de::Error;
+
+use std::convert::TryFrom;
+
+// the id of a point is a u64 on the wire whatever usize is on either end, a receiver with a
+// smaller usize refuses ids that don't fit instead of cutting them off
+pub type WireId = u64;
+
+pub type WirePoint = (WireId, f32, f32, f32);
+
+pub fn to_wire((id, time, note, vel): Point) -> WirePoint{
+    (id as WireId, time, note, vel)
+}
+
+pub fn from_wire((id, time, note, vel): WirePoint) -> Option<Point>{
+    usize::try_from(id).ok().map(|id| (id, time, note, vel))
+}
+
+fn from_wire_or<E: Error>(point: WirePoint) -> Result<Point, E>{
+    from_wire(point).ok_or_else(|| E::custom(format!("point id {} doesn't fit in usize", point.0)))
+}
+
+// for #[serde(with = "...")] on fields holding a Point
+pub(crate) mod wire_point{
+    use super::*;
+
+    pub fn serialize<S: Serializer>(point: &Point, s: S) -> Result<S::Ok, S::Error>{
+        to_wire(*point).serialize(s)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<Point, D::Error>{
+        from_wire_or(WirePoint::deserialize(d)?)
+    }
+}
+
+pub(crate) mod wire_points{
+    use super::*;
+
+    pub fn serialize<S: Serializer>(points: &[Point], s: S) -> Result<S::Ok, S::Error>{
+        s.collect_seq(points.iter().map(|p| to_wire(*p)))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<Vec<Point>, D::Error>{
+        Vec::<WirePoint>::deserialize(d)?.into_iter().map(from_wire_or).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+    #[test]
+    fn wire_ids(){
+        let bytes = vec![FlowwPacket::Point((0x0102, 0.0, 0.0, 0.0))].encoded();
+        // batch length, variant, then the id as 8 little endian bytes on every platform
+        assert_eq!(bytes.len(), 8 + 4 + 8 + 12);
+        assert_eq!(bytes[12..20], [2, 1, 0, 0, 0, 0, 0, 0]);
+        let points = vec![FlowwPacket::Points(vec![(7, 1.0, 2.0, 0.5); 2])];
+        assert_eq!(points.encode().as_slice().decoded().unwrap(), points);
+        let rich = vec![FlowwPacket::RichPoint(RichPoint::new((3, 1.0, 2.0, 0.5)).with(PARAM_PAN, 0.0))];
+        assert_eq!(rich.encode().as_slice().decoded().unwrap(), rich);
+        assert_eq!(to_wire((5, 1.0, 2.0, 3.0)), (5u64, 1.0, 2.0, 3.0));
+        assert_eq!(from_wire((5, 1.0, 2.0, 3.0)), Some((5, 1.0, 2.0, 3.0)));
+        if usize::BITS < 64 {
+            assert_eq!(from_wire((u64::MAX, 0.0, 0.0, 0.0)), None);
+        }
+    }
+}
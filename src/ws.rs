@@ -0,0 +1,164 @@
+use crate::{ FlowwPacket };
+use crate::net::invalid_data;
+
+use tungstenite::{ Message, WebSocket, Error as WsError };
+use tungstenite::error::ProtocolError;
+use tungstenite::stream::MaybeTlsStream;
+
+use std::io;
+use std::net::{ TcpStream, TcpListener, SocketAddr, ToSocketAddrs };
+use std::time::Duration;
+
+// how long a client gets to send the upgrade request, so one that never does can't hold up the stream
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_millis(500);
+
+fn ws_error(e: WsError) -> io::Error{
+    match e{
+        WsError::Io(e) => e,
+        WsError::ConnectionClosed | WsError::AlreadyClosed => io::ErrorKind::ConnectionAborted.into(),
+        e => invalid_data(e),
+    }
+}
+
+fn batch_message(packets: &[FlowwPacket]) -> io::Result<Message>{
+    bincode::serialize(packets).map(Message::Binary).map_err(invalid_data)
+}
+
+// a batch per binary message, text, ping and pong messages are skipped, None once closed,
+// also when the peer went away without a close frame
+fn read_batch<S: io::Read + io::Write>(socket: &mut WebSocket<S>) -> io::Result<Option<Vec<FlowwPacket>>>{
+    loop{
+        match socket.read(){
+            Ok(Message::Binary(bytes)) => return bincode::deserialize(&bytes).map(Some).map_err(invalid_data),
+            Ok(Message::Close(_)) | Err(WsError::ConnectionClosed) | Err(WsError::AlreadyClosed) => return Ok(None),
+            Err(WsError::Protocol(ProtocolError::ResetWithoutClosingHandshake)) => return Ok(None),
+            Ok(_) => {},
+            Err(e) => return Err(ws_error(e)),
+        }
+    }
+}
+
+// serves a live stream to any number of websocket clients, every batch goes to all of them
+pub struct WsServer{
+    listener: TcpListener,
+    clients: Vec<WebSocket<TcpStream>>,
+}
+
+impl WsServer{
+    pub fn bind<A: ToSocketAddrs>(addr: A) -> io::Result<Self>{
+        let listener = TcpListener::bind(addr)?;
+        listener.set_nonblocking(true)?;
+        Ok(Self{ listener, clients: Vec::new() })
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr>{
+        self.listener.local_addr()
+    }
+
+    pub fn clients(&self) -> usize{
+        self.clients.len()
+    }
+
+    fn handshake(&mut self, stream: TcpStream) -> io::Result<()>{
+        stream.set_nonblocking(false)?;
+        stream.set_nodelay(true)?;
+        stream.set_read_timeout(Some(HANDSHAKE_TIMEOUT))?;
+        stream.set_write_timeout(Some(HANDSHAKE_TIMEOUT))?;
+        let socket = tungstenite::accept(stream).map_err(invalid_data)?;
+        socket.get_ref().set_read_timeout(None)?;
+        socket.get_ref().set_write_timeout(None)?;
+        self.clients.push(socket);
+        Ok(())
+    }
+
+    // blocks until one client connected
+    pub fn accept(&mut self) -> io::Result<()>{
+        self.listener.set_nonblocking(false)?;
+        let res = self.listener.accept();
+        self.listener.set_nonblocking(true)?;
+        self.handshake(res?.0)
+    }
+
+    // takes in the clients that are waiting without blocking, failed handshakes are skipped
+    pub fn accept_pending(&mut self) -> io::Result<usize>{
+        let mut n = 0;
+        loop{
+            match self.listener.accept(){
+                Ok((stream, _)) => if self.handshake(stream).is_ok() { n += 1; },
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => return Ok(n),
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    // clients that can't be written to are dropped, returns how many got the batch
+    pub fn broadcast(&mut self, packets: &[FlowwPacket]) -> io::Result<usize>{
+        self.accept_pending()?;
+        let msg = batch_message(packets)?;
+        self.clients.retain_mut(|c| c.send(msg.clone()).is_ok());
+        Ok(self.clients.len())
+    }
+
+    // says goodbye to every client
+    pub fn close(mut self){
+        for c in &mut self.clients{
+            let _ = c.close(None);
+            let _ = c.flush();
+        }
+    }
+}
+
+// subscribes to a WsServer, or anything else that sends batches as binary messages
+pub struct WsClient{
+    socket: WebSocket<MaybeTlsStream<TcpStream>>,
+}
+
+impl WsClient{
+    // a ws:// url, like ws://127.0.0.1:9002
+    pub fn connect(url: &str) -> io::Result<Self>{
+        let (socket, _) = tungstenite::connect(url).map_err(ws_error)?;
+        Ok(Self{ socket })
+    }
+
+    pub fn send(&mut self, packets: &[FlowwPacket]) -> io::Result<()>{
+        let msg = batch_message(packets)?;
+        self.socket.send(msg).map_err(ws_error)
+    }
+
+    // blocks until a batch arrives, None when the server closed the connection
+    pub fn recv(&mut self) -> io::Result<Option<Vec<FlowwPacket>>>{
+        read_batch(&mut self.socket)
+    }
+
+    pub fn close(mut self) -> io::Result<()>{
+        self.socket.close(None).map_err(ws_error)?;
+        while read_batch(&mut self.socket)?.is_some(){}
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+    #[test]
+    fn websocket(){
+        let mut server = WsServer::bind("127.0.0.1:0").unwrap();
+        let url = format!("ws://{}", server.local_addr().unwrap());
+        assert_eq!(server.broadcast(&[FlowwPacket::Msg("nobody".to_string())]).unwrap(), 0);
+        // connects but never asks for the upgrade
+        let silent = std::net::TcpStream::connect(server.local_addr().unwrap()).unwrap();
+        assert_eq!(server.broadcast(&[FlowwPacket::Msg("nobody".to_string())]).unwrap(), 0);
+        drop(silent);
+        let handle = std::thread::spawn(move || {
+            let mut client = WsClient::connect(&url).unwrap();
+            let got = client.recv().unwrap();
+            assert_eq!(client.recv().unwrap(), None);
+            got
+        });
+        server.accept().unwrap();
+        let packets = vec![FlowwPacket::Track("kick".to_string()), FlowwPacket::Point((0, 0.0, 36.0, 1.0))];
+        assert_eq!(server.broadcast(&packets).unwrap(), 1);
+        server.close();
+        assert_eq!(handle.join().unwrap(), Some(packets));
+    }
+}